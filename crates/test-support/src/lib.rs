@@ -16,6 +16,7 @@ pub struct Project {
     files: Vec<(String, String)>,
     debug: bool,
     js: bool,
+    features: Vec<String>,
 }
 
 pub fn project() -> Project {
@@ -29,6 +30,7 @@ pub fn project() -> Project {
     Project {
         debug: true,
         js: false,
+        features: Vec::new(),
         files: vec![
             ("Cargo.toml".to_string(), format!(r#"
                 [package]
@@ -42,7 +44,7 @@ pub fn project() -> Project {
                 crate-type = ["cdylib"]
 
                 [dependencies]
-                wasm-bindgen = {{ path = '{}' }}
+                wasm-bindgen = {{ path = '{}', features = [FEATURES] }}
 
                 [profile.dev]
                 opt-level = 2 # TODO: decrease when upstream is not buggy
@@ -57,8 +59,20 @@ pub fn project() -> Project {
                 import * as out from "./out_wasm";
                 import * as test from "./test";
 
+                // Tests slower than this many milliseconds are flagged, as
+                // they often point at a pathological trip across the boundary.
+                const slow = Number(process.env.WASM_BINDGEN_SLOW_TEST_MS || 1000);
+                let start: [number, number];
+
                 out.booted.then(() => {
-                  test.test();
+                  start = process.hrtime();
+                  return test.test();
+                }).then(() => {
+                  const [secs, nanos] = process.hrtime(start);
+                  const ms = secs * 1000 + nanos / 1e6;
+                  console.log(`test took ${ms.toFixed(3)}ms`);
+                  if (ms > slow)
+                    console.log(`warning: test is slow, taking over ${slow}ms`);
                   if ((out as any).assertHeapAndStackEmpty)
                     (out as any).assertHeapAndStackEmpty();
                 }).catch(error => {
@@ -95,7 +109,8 @@ pub fn project() -> Project {
                         "strictFunctionTypes": true,
                         "strictNullChecks": true,
                         "alwaysStrict": true,
-                        "strict": true
+                        "strict": true,
+                        "lib": ["es2015", "esnext.disposable", "dom", "scripthost"]
 					}
 				}
 			"#.to_string()),
@@ -165,10 +180,22 @@ impl Project {
         self
     }
 
+    /// Enables features of the `wasm-bindgen` crate for this project.
+    pub fn feature(&mut self, feature: &str) -> &mut Project {
+        self.features.push(format!("{:?}", feature));
+        self
+    }
+
     pub fn test(&mut self) {
         let root = root();
         drop(fs::remove_dir_all(&root));
+        let features = self.features.join(", ");
         for &(ref file, ref contents) in self.files.iter() {
+            let contents = if file == "Cargo.toml" {
+                contents.replace("[FEATURES]", &format!("[{}]", features))
+            } else {
+                contents.clone()
+            };
             let dst = root.join(file);
             fs::create_dir_all(dst.parent().unwrap()).unwrap();
             fs::File::create(&dst).unwrap().write_all(contents.as_ref()).unwrap();
@@ -203,6 +230,7 @@ impl Project {
             .nodejs(true)
             .typescript(true)
             .debug(self.debug)
+            .test_harness(true)
             .generate(&root)
             .expect("failed to run bindgen");
 
@@ -0,0 +1,65 @@
+//! Wraps arbitrary strings up as the metadata the macro embeds in a module,
+//! checking that malformed programs are rejected with an error rather than a
+//! panic.
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate wasm_bindgen_cli_support as cli;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::str;
+
+fuzz_target!(|data: &[u8]| {
+    let json = match str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    let dir = env::temp_dir().join("wasm-bindgen-fuzz-metadata");
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("input.wasm");
+    File::create(&input).unwrap()
+        .write_all(&module_with_metadata(json)).unwrap();
+    drop(cli::Bindgen::new()
+        .input_path(&input)
+        .typescript(true)
+        .generate(&dir));
+});
+
+/// Builds a wasm module with one page of memory and a data segment holding
+/// `json` in the same form the macro emits, one `u32` per character.
+fn module_with_metadata(json: &str) -> Vec<u8> {
+    let mut value = Vec::new();
+    for c in "wbg:".chars().chain(json.chars()) {
+        let c = c as u32;
+        value.extend_from_slice(&[c as u8, (c >> 8) as u8, (c >> 16) as u8, (c >> 24) as u8]);
+    }
+
+    let mut data = Vec::new();
+    leb128(&mut data, 1); // one segment
+    data.push(0); // memory 0
+    data.extend_from_slice(&[0x41, 0x00, 0x0b]); // i32.const 0; end
+    leb128(&mut data, value.len() as u32);
+    data.extend_from_slice(&value);
+
+    let mut module = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+    module.extend_from_slice(&[0x05, 0x03, 0x01, 0x00, 0x01]); // memory section
+    module.push(0x0b); // data section
+    leb128(&mut module, data.len() as u32);
+    module.extend_from_slice(&data);
+    module
+}
+
+fn leb128(dst: &mut Vec<u8>, mut val: u32) {
+    loop {
+        let byte = (val & 0x7f) as u8;
+        val >>= 7;
+        if val == 0 {
+            dst.push(byte);
+            return
+        }
+        dst.push(byte | 0x80);
+    }
+}
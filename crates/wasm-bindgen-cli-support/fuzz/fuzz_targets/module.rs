@@ -0,0 +1,22 @@
+//! Runs arbitrary bytes through `Bindgen` as a wasm module, which should
+//! either succeed or return an error but never panic.
+
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate wasm_bindgen_cli_support as cli;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+
+fuzz_target!(|data: &[u8]| {
+    let dir = env::temp_dir().join("wasm-bindgen-fuzz-module");
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("input.wasm");
+    File::create(&input).unwrap().write_all(data).unwrap();
+    drop(cli::Bindgen::new()
+        .input_path(&input)
+        .typescript(true)
+        .generate(&dir));
+});
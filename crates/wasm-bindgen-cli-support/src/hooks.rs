@@ -0,0 +1,114 @@
+use failure::Error;
+use parity_wasm::elements::Module;
+
+/// Support code in the generated JS which a `TypeHook` can ask for.
+///
+/// Each variant makes the corresponding function available to the JS
+/// expressions returned by a hook.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Intrinsic {
+    /// `getObject(idx)`, reading a JS value out of the heap or stack
+    GetObject,
+    /// `addHeapObject(obj)`, returning a new owned index for `obj`
+    AddHeapObject,
+    /// `takeObject(idx)`, reading a JS value and dropping the index
+    TakeObject,
+    /// `getStringFromWasm(ptr, len)`, decoding utf-8 from wasm memory
+    GetStringFromWasm,
+    /// `passStringToWasm(s)`, returning `[ptr, len]` of a malloc'd copy
+    PassStringToWasm,
+    /// `getUint8Memory()`, a cached `Uint8Array` view of wasm memory
+    Uint8Memory,
+    /// `getUint32Memory()`, a cached `Uint32Array` view of wasm memory
+    Uint32Memory,
+}
+
+/// A hook into the JS generator for a type descriptor that `wasm-bindgen`
+/// doesn't natively understand.
+///
+/// Rust types pick their descriptor through the `DESCRIPTOR` constant of
+/// `WasmBoundary`. If that descriptor isn't one of the builtin types or an
+/// exported struct then each hook registered with `Bindgen::type_hook` is
+/// consulted, in order, and the first one which `handles` the descriptor is
+/// used to generate the glue for it. Only descriptors from
+/// `shared::TYPE_HOOK_START` (`DESCRIPTOR_HOOK_START` in the `wasm-bindgen`
+/// crate) upwards are handed to hooks, as no builtin or exported type uses
+/// them.
+///
+/// All conversions default to passing the value through unchanged, which is
+/// appropriate for types whose `WasmBoundary::Js` is already a number.
+pub trait TypeHook {
+    /// Returns whether this hook knows how to convert `descriptor`.
+    fn handles(&self, descriptor: char) -> bool;
+
+    /// Returns the TypeScript type used for `descriptor` in the `.d.ts` file.
+    fn typescript(&self, _descriptor: char) -> String {
+        String::from("any")
+    }
+
+    /// Returns a JS expression converting the argument `arg` of an exported
+    /// function into the value passed to wasm.
+    fn export_argument(&self, _descriptor: char, arg: &str) -> String {
+        arg.to_string()
+    }
+
+    /// Returns a JS expression converting the value `ret` returned from wasm
+    /// into the value an exported function returns to its caller.
+    fn export_return(&self, _descriptor: char, ret: &str) -> String {
+        ret.to_string()
+    }
+
+    /// Returns a JS expression converting the value `arg` passed from wasm
+    /// into the argument given to an imported function.
+    fn import_argument(&self, _descriptor: char, arg: &str) -> String {
+        arg.to_string()
+    }
+
+    /// Returns a JS expression converting the value `ret` returned from an
+    /// imported function into the value passed back to wasm.
+    fn import_return(&self, _descriptor: char, ret: &str) -> String {
+        ret.to_string()
+    }
+
+    /// JS source for any helpers the expressions above rely on, emitted once
+    /// at the top level of the generated module.
+    fn globals(&self) -> String {
+        String::new()
+    }
+
+    /// Generated support functions the expressions above rely on.
+    fn intrinsics(&self) -> Vec<Intrinsic> {
+        Vec::new()
+    }
+
+    /// Internal wasm exports (such as `__wbindgen_malloc`) which must be kept
+    /// in the final module for the expressions above to work.
+    fn required_exports(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+}
+
+/// An extra step run over the output of `Bindgen`, for transforms which
+/// aren't built in.
+///
+/// Passes registered with `Bindgen::pass` run in order once the builtin
+/// generation is complete, so they see the final JS and TypeScript and a wasm
+/// module whose imports have already been rewritten. Each method defaults to
+/// leaving its input alone.
+pub trait Pass {
+    /// Transforms the wasm module before it's garbage collected and written
+    /// out.
+    fn module(&self, _module: &mut Module) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Transforms the generated JS.
+    fn js(&self, _js: &mut String) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Transforms the generated TypeScript declarations.
+    fn typescript(&self, _ts: &mut String) -> Result<(), Error> {
+        Ok(())
+    }
+}
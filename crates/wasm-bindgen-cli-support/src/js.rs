@@ -2,10 +2,11 @@ use std::char;
 use std::collections::{HashSet, HashMap};
 use std::mem;
 
+use failure::Error;
 use shared;
 use parity_wasm::elements::*;
 
-use super::Bindgen;
+use super::{Bindgen, Intrinsic, SlabGrowth, TypeHook};
 
 pub struct Context<'a> {
     pub globals: String,
@@ -17,14 +18,59 @@ pub struct Context<'a> {
     pub module: &'a mut Module,
     pub imports_to_rewrite: HashSet<String>,
     pub custom_type_names: HashMap<char, String>,
+    pub string_enums: HashMap<char, String>,
+    pub enums: HashMap<char, String>,
+    pub tagged_enums: HashMap<char, String>,
+    pub error_enums: HashSet<char>,
     pub imported_names: HashSet<String>,
     pub exported_classes: HashMap<String, ExportedClass>,
+    pub referenced_classes: HashSet<String>,
+    pub constructor_classes: HashSet<String>,
+    pub exposed_type_hooks: HashSet<usize>,
 }
 
 #[derive(Default)]
 pub struct ExportedClass {
+    pub comments: String,
+    pub implements: Option<String>,
     pub contents: String,
     pub typescript: String,
+    pub unit: bool,
+    pub free_method: Option<String>,
+    pub methods: bool,
+    pub statics: Vec<String>,
+    pub statics_typescript: String,
+    pub properties: Vec<ClassProperty>,
+    pub constructor: Option<String>,
+    pub constructor_typescript: String,
+    // the TypeScript type of the items, if the class is an iterator
+    pub iterator: Option<String>,
+}
+
+/// A property of an exported class defined by `getter` and `setter` methods,
+/// which is declared in TypeScript once all of the class has been seen.
+#[derive(Default)]
+pub struct ClassProperty {
+    pub name: String,
+    pub comments: String,
+    pub ty: String,
+    pub getter: bool,
+    pub setter: bool,
+}
+
+impl ExportedClass {
+    fn property(&mut self, name: &str) -> &mut ClassProperty {
+        match self.properties.iter().position(|p| p.name == name) {
+            Some(i) => &mut self.properties[i],
+            None => {
+                self.properties.push(ClassProperty {
+                    name: name.to_string(),
+                    ..ClassProperty::default()
+                });
+                self.properties.last_mut().unwrap()
+            }
+        }
+    }
 }
 
 pub struct SubContext<'a, 'b: 'a> {
@@ -33,20 +79,280 @@ pub struct SubContext<'a, 'b: 'a> {
 }
 
 impl<'a> Context<'a> {
-    pub fn add_custom_type_names(&mut self, program: &shared::Program) {
+    pub fn add_custom_type_names(&mut self, program: &shared::Program)
+        -> Result<(), Error>
+    {
+        for e in program.string_enums.iter() {
+            if self.string_enums.insert(e.descriptor, e.name.clone()).is_some() {
+                bail!("duplicate descriptor for string enum `{}`", e.name);
+            }
+        }
+        for e in program.enums.iter() {
+            if self.enums.insert(e.descriptor, e.name.clone()).is_some() {
+                bail!("duplicate descriptor for enum `{}`", e.name);
+            }
+        }
+        for e in program.tagged_enums.iter() {
+            if self.tagged_enums.insert(e.descriptor, e.name.clone()).is_some() {
+                bail!("duplicate descriptor for enum `{}`", e.name);
+            }
+        }
+        // Classes with their own constructor need to know about it before any
+        // function returning an instance is generated.
+        for export in program.exports.iter().filter(|e| e.constructor) {
+            let class = match export.class {
+                Some(ref class) => class,
+                None => bail!("constructor `{}` isn't part of a class",
+                              export.function.name),
+            };
+            if !self.constructor_classes.insert(class.clone()) {
+                bail!("class `{}` has more than one constructor", class);
+            }
+        }
+        // Enums thrown as errors get a JS class per variant, which may be
+        // needed by exports in other programs.
+        for export in program.exports.iter() {
+            if let Some(e) = export.error {
+                self.error_enums.insert(e);
+            }
+        }
         for custom in program.custom_type_names.iter() {
-            assert!(self.custom_type_names.insert(custom.descriptor,
-                                                  custom.name.clone()).is_none());
             let val = custom.descriptor as u32;
-            assert!(val & 1 == 0);
-            let descriptor = char::from_u32(val | 1).unwrap();
-            assert!(self.custom_type_names.insert(descriptor,
-                                                  custom.name.clone()).is_none());
+            if val & 1 != 0 {
+                bail!("invalid descriptor for type `{}`", custom.name);
+            }
+            let descriptor = char::from_u32(val | 1)
+                .ok_or_else(|| format_err!("invalid descriptor for type `{}`",
+                                           custom.name))?;
+            // A class is listed by every program exporting some of its
+            // methods, which may be split up by `cfg`.
+            for &d in [custom.descriptor, descriptor].iter() {
+                if let Some(prev) = self.custom_type_names.insert(d, custom.name.clone()) {
+                    if prev != custom.name {
+                        bail!("duplicate descriptor for type `{}`", custom.name);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that every type used by `program` is one we know how to
+    /// generate bindings for.
+    pub fn check_types(&self, program: &shared::Program) -> Result<(), Error> {
+        let functions = program.exports.iter().map(|e| &e.function)
+            .chain(program.imports.iter().map(|i| &i.function));
+        for f in functions {
+            for &ty in f.arguments.iter().chain(f.ret.iter()) {
+                let known = if (ty as u32) >= shared::TYPE_HOOK_START {
+                    self.config.type_hooks.iter().any(|h| h.handles(ty))
+                } else if (ty as u32) >= shared::TYPE_CUSTOM_START {
+                    self.custom_type_names.contains_key(&ty) ||
+                        self.string_enums.contains_key(&ty) ||
+                        self.enums.contains_key(&ty) ||
+                        self.tagged_enums.contains_key(&ty)
+                } else {
+                    (ty as u32) >= shared::TYPE_NUMBER as u32
+                };
+                if !known {
+                    bail!("function `{}` uses unknown type descriptor {:#x}",
+                          f.name, ty as u32);
+                }
+            }
+            for &i in f.ascii.iter() {
+                match f.arguments.get(i) {
+                    Some(&shared::TYPE_BORROWED_STR) |
+                    Some(&shared::TYPE_STRING) => {}
+                    _ => bail!("function `{}` has a non-string `ascii` argument",
+                               f.name),
+                }
+            }
+        }
+        for export in program.exports.iter() {
+            self.check_export(export)?;
+        }
+        for import in program.imports.iter() {
+            self.check_import(import)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the parts of an export's signature which only some of its
+    /// attributes or positions support.
+    fn check_export(&self, export: &shared::Export) -> Result<(), Error> {
+        let function = &export.function;
+        let name = &function.name;
+        if export.optional_args > function.arguments.len() {
+            bail!("function `{}` has more optional arguments than arguments", name);
+        }
+        for (i, &ty) in function.arguments.iter().enumerate() {
+            let supported = if function.optional.contains(&i) {
+                self.optional_supported(ty) || self.owned_class(ty)
+            } else {
+                ty != shared::TYPE_STATIC_STR
+            };
+            if !supported {
+                bail!("function `{}` has an argument of unsupported type {:#x}",
+                      name, ty as u32);
+            }
+        }
+        if let Some(ty) = function.ret {
+            let unsupported = ty == shared::TYPE_JS_REF ||
+                ty == shared::TYPE_BORROWED_STR ||
+                ty == shared::TYPE_STATIC_STR ||
+                (self.custom_type_names.contains_key(&ty) && !self.owned_class(ty));
+            if unsupported {
+                bail!("function `{}` returns an unsupported type {:#x}",
+                      name, ty as u32);
+            }
+        }
+        if export.tuple_ret.len() > 0 {
+            if function.ret.is_some() {
+                bail!("function `{}` returns both a value and a tuple", name);
+            }
+            for &ty in export.tuple_ret.iter() {
+                match ty {
+                    shared::TYPE_NUMBER | shared::TYPE_BOOLEAN | shared::TYPE_CHAR => {}
+                    e if self.enums.contains_key(&e) => {}
+                    _ => bail!("function `{}` returns a tuple with an unsupported \
+                                element type {:#x}", name, ty as u32),
+                }
+            }
+        }
+        if export.blob.is_some() && function.ret != Some(shared::TYPE_VECTOR_U8) {
+            bail!("the `blob` attribute can only be used on functions \
+                   returning `Vec<u8>`, but `{}` doesn't", name);
+        }
+        if export.plain_object &&
+            function.ret != Some(shared::TYPE_MAP_STRING) &&
+            function.ret != Some(shared::TYPE_MAP_JSVALUE)
+        {
+            bail!("the `plain_object` attribute can only be used on functions \
+                   returning a map, but `{}` doesn't", name);
+        }
+        if export.readable_stream &&
+            (function.arguments.len() > 0 ||
+             function.ret != Some(shared::TYPE_VECTOR_U8) ||
+             export.optional_ret)
+        {
+            bail!("`readable_stream` functions must take no arguments and \
+                   return `Vec<u8>`, but `{}` doesn't", name);
+        }
+        if export.stream_sink &&
+            (function.arguments != [shared::TYPE_VECTOR_U8] || function.ret.is_some())
+        {
+            bail!("`stream_sink` functions must take a single `Vec<u8>` \
+                   argument and return nothing, but `{}` doesn't", name);
+        }
+        if let Some(ref class) = export.class {
+            if export.constructor {
+                let returns_class = function.ret
+                    .and_then(|t| self.custom_type_names.get(&t))
+                    .map(|c| c == class)
+                    .unwrap_or(false);
+                if !returns_class || export.optional_ret {
+                    bail!("the constructor of `{}` must return an instance of it",
+                          class);
+                }
+            }
+            if export.setter.is_some() && function.arguments.len() != 1 {
+                bail!("setter `{}` must take exactly one argument", name);
+            }
+            if export.getter.is_some() && function.arguments.len() != 0 {
+                bail!("getter `{}` can't take any arguments", name);
+            }
+        }
+        if let Some(err) = export.error {
+            if export.readable_stream || export.stream_sink {
+                bail!("streaming function `{}` can't return a `Result`", name);
+            }
+            if err != shared::TYPE_JS_OWNED && !self.tagged_enums.contains_key(&err) {
+                bail!("function `{}` must return errors as a `JsValue` or an \
+                       enum with fields", name);
+            }
         }
+        Ok(())
     }
 
+    /// Checks that every argument and the return value of an import are of
+    /// types which can be passed to and from JS.
+    fn check_import(&self, import: &shared::Import) -> Result<(), Error> {
+        let function = &import.function;
+        for (i, &ty) in function.arguments.iter().enumerate() {
+            let supported = if function.optional.contains(&i) {
+                self.optional_supported(ty)
+            } else {
+                (ty as u32) >= shared::TYPE_HOOK_START ||
+                    self.import_supported(ty) ||
+                    ty == shared::TYPE_U64 || ty == shared::TYPE_I64 ||
+                    ty == shared::TYPE_U128 || ty == shared::TYPE_I128 ||
+                    ty == shared::TYPE_BORROWED_STR ||
+                    ty == shared::TYPE_STATIC_STR ||
+                    ty == shared::TYPE_JS_REF
+            };
+            if !supported {
+                bail!("imported function `{}` has an argument of unsupported \
+                       type {:#x}", function.name, ty as u32);
+            }
+        }
+        if let Some(ty) = function.ret {
+            let supported = (ty as u32) >= shared::TYPE_HOOK_START ||
+                self.import_supported(ty) ||
+                ty == shared::TYPE_U64 || ty == shared::TYPE_I64 ||
+                ty == shared::TYPE_U128 || ty == shared::TYPE_I128;
+            if !supported {
+                bail!("imported function `{}` returns an unsupported type {:#x}",
+                      function.name, ty as u32);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `ty` can be passed as an `Option` in either direction.
+    fn optional_supported(&self, ty: char) -> bool {
+        ty == shared::TYPE_BORROWED_STR || self.import_supported(ty)
+    }
+
+    /// Whether `ty` can be passed by value between JS and an import.
+    fn import_supported(&self, ty: char) -> bool {
+        match ty {
+            shared::TYPE_NUMBER |
+            shared::TYPE_BOOLEAN |
+            shared::TYPE_CHAR |
+            shared::TYPE_DATE |
+            shared::TYPE_STRING |
+            shared::TYPE_JS_OWNED => true,
+            e => {
+                self.string_enums.contains_key(&e) ||
+                    self.enums.contains_key(&e) ||
+                    self.tagged_enums.contains_key(&e)
+            }
+        }
+    }
+
+    /// Whether `ty` is an exported class passed by value.
+    fn owned_class(&self, ty: char) -> bool {
+        (ty as u32) & shared::TYPE_CUSTOM_REF_FLAG == 0 &&
+            self.custom_type_names.contains_key(&ty)
+    }
+
+    /// Produces the JS and TypeScript for everything generated so far,
+    /// updating the wasm module to match.
+    ///
+    /// This runs in a few passes: classes are written out, the intrinsics the
+    /// module imports are bound, the module's imports and exports are fixed up,
+    /// and finally the JS is rendered.
     pub fn finalize(&mut self, module_name: &str) -> (String, String) {
         self.write_classes();
+        self.bind_intrinsics();
+        self.rewrite_imports(module_name);
+        self.unexport_unused_internal_exports();
+        (self.render_js(module_name), self.typescript.clone())
+    }
+
+    /// Defines each `__wbindgen_*` function imported by the wasm module,
+    /// along with whatever support code it needs.
+    fn bind_intrinsics(&mut self) {
         {
             let mut bind = |name: &str, f: &Fn(&mut Self) -> String| {
                 if !self.wasm_import_needed(name) {
@@ -59,10 +365,21 @@ impl<'a> Context<'a> {
             bind("__wbindgen_object_clone_ref", &|me| {
                 me.expose_add_heap_object();
                 me.expose_get_object();
-                let bump_cnt = if me.config.debug {
+                if me.config.map_heap {
+                    return String::from("
+                        function(idx) {
+                            if ((idx & 1) === 1)
+                                return addHeapObject(getObject(idx));
+                            heapEntry(idx >> 1).cnt += 1;
+                            return idx;
+                        }
+                    ")
+                }
+                let bump_cnt = if me.config.debug_assertions {
+                    me.expose_corrupt_slab();
                     String::from("
                         if (typeof(val) === 'number')
-                            throw new Error('corrupt slab');
+                            corruptSlab(idx >> 1);
                         val.cnt += 1;
                     ")
                 } else {
@@ -118,6 +435,67 @@ impl<'a> Context<'a> {
                 String::from("() => addHeapObject(undefined)")
             });
 
+            bind("__wbindgen_object_new", &|me| {
+                me.expose_add_heap_object();
+                String::from("() => addHeapObject({})")
+            });
+
+            bind("__wbindgen_object_keys", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_object();
+                String::from("(i) => addHeapObject(Object.keys(getObject(i)))")
+            });
+
+            bind("__wbindgen_array_new", &|me| {
+                me.expose_add_heap_object();
+                String::from("() => addHeapObject([])")
+            });
+
+            bind("__wbindgen_array_push", &|me| {
+                me.expose_get_object();
+                String::from("(i, v) => { getObject(i).push(getObject(v)); }")
+            });
+
+            // Keep in sync with the `KIND_*` constants in `src/json.rs`.
+            bind("__wbindgen_json_kind", &|me| {
+                me.expose_get_object();
+                String::from("(i) => {
+                    const v = getObject(i);
+                    if (v === null || v === undefined)
+                        return 0;
+                    switch (typeof(v)) {
+                        case 'boolean': return 1;
+                        case 'number': return 2;
+                        case 'string': return 3;
+                        case 'object':
+                            if (Array.isArray(v))
+                                return 4;
+                            const proto = Object.getPrototypeOf(v);
+                            if (proto === Object.prototype || proto === null)
+                                return 5;
+                    }
+                    return 6;
+                }")
+            });
+
+            bind("__wbindgen_json_parse", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_string_from_wasm();
+                String::from("(p, l) => addHeapObject(JSON.parse(getStringFromWasm(p, l)))")
+            });
+
+            bind("__wbindgen_json_serialize", &|me| {
+                me.expose_get_object();
+                me.expose_pass_string_to_wasm();
+                me.expose_uint32_memory();
+                String::from("(i, len_ptr) => {
+                    const json = JSON.stringify(getObject(i));
+                    const [ptr, len] = passStringToWasm(json === undefined ? 'null' : json);
+                    getUint32Memory()[len_ptr / 4] = len;
+                    return ptr;
+                }")
+            });
+
             bind("__wbindgen_null_new", &|me| {
                 me.expose_add_heap_object();
                 String::from("() => addHeapObject(null)")
@@ -170,6 +548,57 @@ impl<'a> Context<'a> {
                 String::from("(i) => typeof(getObject(i)) == 'symbol' ? 1 : 0")
             });
 
+            bind("__wbindgen_now", &|me| {
+                let node = if me.config.nodejs {
+                    "try {
+                        const perf = require('perf_hooks').performance;
+                        return () => perf.now();
+                    } catch (e) {}"
+                } else {
+                    ""
+                };
+                format!("(() => {{
+                    if (typeof(performance) === 'object' &&
+                        typeof(performance.now) === 'function')
+                        return () => performance.now();
+                    {}
+                    return () => Date.now();
+                }})()", node)
+            });
+
+            bind("__wbindgen_random_fill", &|me| {
+                me.expose_uint8_memory();
+                let fill = if me.config.nodejs {
+                    "const fill = require('crypto').randomFillSync;"
+                } else {
+                    "const fill = buf => crypto.getRandomValues(buf);"
+                };
+                // `getRandomValues` refuses to fill more than 64k at a time.
+                format!("(ptr, len) => {{
+                    {}
+                    const buf = getUint8Memory().subarray(ptr, ptr + len);
+                    for (let i = 0; i < len; i += 65536)
+                        fill(buf.subarray(i, i + 65536));
+                }}", fill)
+            });
+
+            // Keep in sync with the method constants in `src/console.rs`.
+            bind("__wbindgen_console", &|me| {
+                me.expose_get_object();
+                me.expose_uint32_memory();
+                String::from("(method, ptr, len) => {
+                    const idxs = getUint32Memory().subarray(ptr / 4, ptr / 4 + len);
+                    const args = Array.from(idxs, getObject);
+                    switch (method) {
+                        case 0: console.log(...args); break;
+                        case 1: console.group(...args); break;
+                        case 2: console.groupCollapsed(...args); break;
+                        case 3: console.groupEnd(); break;
+                        case 4: console.table(args); break;
+                    }
+                }")
+            });
+
             bind("__wbindgen_throw", &|me| {
                 me.expose_get_string_from_wasm();
                 format!("
@@ -179,6 +608,138 @@ impl<'a> Context<'a> {
                 ")
             });
 
+            bind("__wbindgen_rethrow", &|me| {
+                me.expose_take_object();
+                String::from("function(idx) { throw takeObject(idx); }")
+            });
+
+            bind("__wbindgen_uint8_array_view_new", &|me| {
+                me.expose_add_heap_object();
+                me.expose_uint8_memory();
+                String::from("(ptr, len) => {
+                    return addHeapObject(getUint8Memory().subarray(ptr, ptr + len));
+                }")
+            });
+
+            bind("__wbindgen_float32_array_view_new", &|me| {
+                me.expose_add_heap_object();
+                me.expose_float32_memory();
+                String::from("(ptr, len) => {
+                    const start = ptr / 4;
+                    return addHeapObject(getFloat32Memory().subarray(start, start + len));
+                }")
+            });
+
+            bind("__wbindgen_structured_clone", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_object();
+                // `structuredClone` is only a global in newer engines, but
+                // older versions of node can still get at the same algorithm
+                // synchronously through the `v8` module.
+                let fallback = if me.config.nodejs {
+                    "const v8 = require('v8');
+                    return addHeapObject(v8.deserialize(v8.serialize(obj)));"
+                } else {
+                    "throw new Error('structuredClone is not supported');"
+                };
+                format!("(i) => {{
+                    const obj = getObject(i);
+                    if (typeof(structuredClone) === 'function')
+                        return addHeapObject(structuredClone(obj));
+                    {}
+                }}", fallback)
+            });
+
+            bind("__wbindgen_reflect_construct", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_object();
+                me.expose_uint32_memory();
+                String::from("(i, ptr, len) => {
+                    const idxs = getUint32Memory().subarray(ptr / 4, ptr / 4 + len);
+                    const args = Array.from(idxs, getObject);
+                    return addHeapObject(Reflect.construct(getObject(i), args));
+                }")
+            });
+
+            bind("__wbindgen_iter_new", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_object();
+                String::from("(i) => {
+                    const obj = getObject(i);
+                    return addHeapObject(obj[Symbol.iterator]());
+                }")
+            });
+
+            bind("__wbindgen_iter_next", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_object();
+                me.expose_uint32_memory();
+                String::from("(i, done_ptr) => {
+                    const next = getObject(i).next();
+                    if (next.done) {
+                        getUint32Memory()[done_ptr / 4] = 1;
+                        return 0;
+                    }
+                    return addHeapObject(next.value);
+                }")
+            });
+
+            bind("__wbindgen_generator_resume", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_object();
+                me.expose_uint32_memory();
+                String::from("(i, method, v, done_ptr) => {
+                    const gen = getObject(i);
+                    const arg = getObject(v);
+                    let step;
+                    if (method === 0)
+                        step = gen.next(arg);
+                    else if (method === 1)
+                        step = gen.return(arg);
+                    else
+                        step = gen.throw(arg);
+                    getUint32Memory()[done_ptr / 4] = step.done ? 1 : 0;
+                    return addHeapObject(step.value);
+                }")
+            });
+
+            bind("__wbindgen_reflect_get", &|me| {
+                me.expose_add_heap_object();
+                me.expose_get_object();
+                me.expose_get_string_from_wasm();
+                String::from("(i, ptr, len) => {
+                    const key = getStringFromWasm(ptr, len);
+                    return addHeapObject(Reflect.get(getObject(i), key));
+                }")
+            });
+
+            bind("__wbindgen_reflect_set", &|me| {
+                me.expose_get_object();
+                me.expose_get_string_from_wasm();
+                String::from("(i, ptr, len, v) => {
+                    const key = getStringFromWasm(ptr, len);
+                    return Reflect.set(getObject(i), key, getObject(v)) ? 1 : 0;
+                }")
+            });
+
+            bind("__wbindgen_reflect_has", &|me| {
+                me.expose_get_object();
+                me.expose_get_string_from_wasm();
+                String::from("(i, ptr, len) => {
+                    const key = getStringFromWasm(ptr, len);
+                    return Reflect.has(getObject(i), key) ? 1 : 0;
+                }")
+            });
+
+            bind("__wbindgen_reflect_delete", &|me| {
+                me.expose_get_object();
+                me.expose_get_string_from_wasm();
+                String::from("(i, ptr, len) => {
+                    const key = getStringFromWasm(ptr, len);
+                    return Reflect.deleteProperty(getObject(i), key) ? 1 : 0;
+                }")
+            });
+
             bind("__wbindgen_string_get", &|me| {
                 me.expose_pass_string_to_wasm();
                 me.expose_get_object();
@@ -194,7 +755,21 @@ impl<'a> Context<'a> {
             });
         }
 
-        let js = format!("
+        if self.config.test_harness &&
+            (self.config.debug_assertions || self.config.map_heap) &&
+            self.exposed_globals.contains("add_heap_object")
+        {
+            self.expose_assert_heap_and_stack_empty();
+        }
+
+        // Only present with the `alloc-accounting` feature of `wasm-bindgen`
+        if self.wasm_export_exists("__wbindgen_alloc_stats_ptr") {
+            self.expose_allocation_stats();
+        }
+    }
+
+    fn render_js(&self, module_name: &str) -> String {
+        format!("
             /* tslint:disable */
             import * as wasm from './{module_name}_wasm'; // imports from wasm file
             {imports}
@@ -204,32 +779,56 @@ impl<'a> Context<'a> {
             module_name = module_name,
             globals = self.globals,
             imports = self.imports,
-        );
-
-        self.rewrite_imports(module_name);
-        self.unexport_unused_internal_exports();
-
-        (js, self.typescript.clone())
+        )
     }
 
     fn write_classes(&mut self) {
         let classes = mem::replace(&mut self.exported_classes, Default::default());
         for (class, exports) in classes {
+            // A unit struct which only has static members and is never passed
+            // to or from wasm is just grouping functions together, so there's
+            // no need for instances and it's exported as a plain object.
+            if exports.unit &&
+                !exports.methods &&
+                !self.referenced_classes.contains(&class)
+            {
+                self.globals.push_str(&format!("
+                    export const {} = Object.freeze({{
+                        {}
+                    }});
+                ", class, exports.statics.join(",\n")));
+                self.typescript.push_str(&exports.comments);
+                self.typescript.push_str(&format!("export namespace {} {{\n", class));
+                self.typescript.push_str(&exports.statics_typescript);
+                self.typescript.push_str("}\n");
+                continue
+            }
+
             let mut dst = String::new();
             dst.push_str(&format!("export class {} {{", class));
-            let mut ts_dst = dst.clone();
+            let mut ts_dst = exports.comments.clone();
+            match exports.implements {
+                Some(ref i) => {
+                    ts_dst.push_str(&format!("export class {} implements {} {{", class, i))
+                }
+                None => ts_dst.push_str(&dst),
+            }
+            // The pointer is only for the generated glue to use, so it's kept
+            // out of the TypeScript API and `isFreed` is offered instead.
             ts_dst.push_str("
-                public ptr: number;
+                private ptr: number;
             ");
-            if self.config.debug {
-                self.expose_check_token();
-                dst.push_str(&format!("
-                    constructor(ptr, sym) {{
-                        _checkToken(sym);
-                        this.ptr = ptr;
-                    }}
-                "));
-                ts_dst.push_str("constructor(ptr: number, sym: Symbol);\n");
+            if let Some(ref constructor) = exports.constructor {
+                dst.push_str(constructor);
+                dst.push_str("\n");
+                ts_dst.push_str(&exports.constructor_typescript);
+            } else if self.config.protect_constructors {
+                dst.push_str("
+                    constructor() {
+                        throw new Error('cannot invoke `new` directly');
+                    }
+                ");
+                ts_dst.push_str("private constructor();\n");
             } else {
                 dst.push_str(&format!("
                     constructor(ptr) {{
@@ -238,26 +837,97 @@ impl<'a> Context<'a> {
                 "));
                 ts_dst.push_str("constructor(ptr: number);\n");
             }
+            // Instances are otherwise only ever created by the module-private
+            // `__wbg_wrap_*` function, bypassing the constructor.
+            if self.wraps_instances(&class) {
+                self.globals.push_str(&format!("
+                    function __wbg_wrap_{name}(ptr) {{
+                        const obj = Object.create({name}.prototype);
+                        obj.ptr = ptr;
+                        return obj;
+                    }}
+                ", name = class));
+            }
 
+            // Freeing twice is a no-op, although in debug mode it's flagged
+            // as it usually means an object is being used after it's gone.
+            let free = exports.free_method.as_ref().map(|s| &s[..]).unwrap_or("free");
+            let freed = if self.config.debug_assertions {
+                format!("throw new Error('`{}` has already been freed');", class)
+            } else {
+                format!("return;")
+            };
             dst.push_str(&format!("
-                free() {{
+                {}() {{
                     const ptr = this.ptr;
+                    if (ptr === 0) {{
+                        {}
+                    }}
                     this.ptr = 0;
                     wasm.{}(ptr);
                 }}
-            ", shared::free_function(&class)));
-            ts_dst.push_str("free(): void;\n");
+
+                isFreed() {{
+                    return this.ptr === 0;
+                }}
+            ", free, freed, shared::free_function(&class)));
+            ts_dst.push_str(&format!("{}(): void;\n", free));
+            ts_dst.push_str("isFreed(): boolean;\n");
+            ts_dst.push_str("[Symbol.dispose](): void;\n");
+
+            for p in exports.properties.iter() {
+                ts_dst.push_str(&p.comments);
+                if !p.setter {
+                    ts_dst.push_str("readonly ");
+                }
+                ts_dst.push_str(&format!("{}: {};\n", p.name, p.ty));
+            }
+
+            // An item of `undefined` ends the iteration, as the end is
+            // otherwise signalled by `next` returning `undefined`.
+            if let Some(ref item) = exports.iterator {
+                dst.push_str("
+                    next() {
+                        const value = this.__wbg_next();
+                        if (value === undefined)
+                            return { done: true, value };
+                        return { done: false, value };
+                    }
+
+                    [Symbol.iterator]() {
+                        return this;
+                    }
+                ");
+                ts_dst.push_str(&format!("next(): IteratorResult<{}>;\n", item));
+                ts_dst.push_str("[Symbol.iterator](): this;\n");
+            }
 
             dst.push_str(&exports.contents);
             ts_dst.push_str(&exports.typescript);
             dst.push_str("}\n");
             ts_dst.push_str("}\n");
 
+            // Lets `using` declarations free instances, in the engines which
+            // support explicit resource management.
+            dst.push_str(&format!("
+                if (typeof Symbol.dispose === 'symbol') {{
+                    {}.prototype[Symbol.dispose] = function() {{
+                        this.{}();
+                    }};
+                }}
+            ", class, free));
+
             self.globals.push_str(&dst);
             self.typescript.push_str(&ts_dst);
         }
     }
 
+    /// Returns whether instances of the exported class `class` are created
+    /// from a pointer without calling its JS constructor.
+    fn wraps_instances(&self, class: &str) -> bool {
+        self.config.protect_constructors || self.constructor_classes.contains(class)
+    }
+
     fn rewrite_imports(&mut self, module_name: &str) {
         for section in self.module.sections_mut() {
             let imports = match *section {
@@ -304,9 +974,7 @@ impl<'a> Context<'a> {
         if !self.exposed_globals.insert("drop_ref") {
             return
         }
-        self.expose_global_slab();
-        self.expose_global_slab_next();
-        let validate_owned = if self.config.debug {
+        let validate_owned = if self.config.debug_assertions {
             String::from("
                 if ((idx & 1) === 1)
                     throw new Error('cannot drop ref of stack objects');
@@ -314,10 +982,28 @@ impl<'a> Context<'a> {
         } else {
             String::new()
         };
-        let dec_ref = if self.config.debug {
+        if self.config.map_heap {
+            self.expose_global_heap();
+            self.globals.push_str(&format!("
+                function dropRef(idx) {{
+                    {}
+
+                    const obj = heapEntry(idx >> 1);
+                    obj.cnt -= 1;
+                    if (obj.cnt > 0)
+                        return;
+                    heap.delete(idx >> 1);
+                }}
+            ", validate_owned));
+            return
+        }
+        self.expose_global_slab();
+        self.expose_global_slab_next();
+        let dec_ref = if self.config.debug_assertions {
+            self.expose_corrupt_slab();
             String::from("
                 if (typeof(obj) === 'number')
-                    throw new Error('corrupt slab');
+                    corruptSlab(idx >> 1);
                 obj.cnt -= 1;
                 if (obj.cnt > 0)
                     return;
@@ -356,11 +1042,34 @@ impl<'a> Context<'a> {
         if !self.exposed_globals.insert("slab") {
             return
         }
-        self.globals.push_str(&format!("let slab = [];"));
+        self.globals.push_str(&format!("
+            const SLAB_INITIAL_SIZE = {};
+            let slab = [];
+            for (let i = 0; i < SLAB_INITIAL_SIZE; i++)
+                slab.push(i + 1);
+        ", self.config.slab_initial_size));
     }
 
-    fn expose_global_slab_next(&mut self) {
-        if !self.exposed_globals.insert("slab_next") {
+    fn expose_global_heap(&mut self) {
+        if !self.exposed_globals.insert("heap") {
+            return
+        }
+        // Ids are never reused, so a stale index can't silently resolve to
+        // an unrelated object which happens to occupy the same slot.
+        self.globals.push_str("
+            const heap = new Map();
+            let heap_next_id = 0;
+            function heapEntry(id) {
+                const val = heap.get(id);
+                if (val === undefined)
+                    throw new Error('unknown heap id ' + id);
+                return val;
+            }
+        ");
+    }
+
+    fn expose_global_slab_next(&mut self) {
+        if !self.exposed_globals.insert("slab_next") {
             return
         }
         self.globals.push_str(&format!("
@@ -373,12 +1082,24 @@ impl<'a> Context<'a> {
             return
         }
         self.expose_global_stack();
+        if self.config.map_heap {
+            self.expose_global_heap();
+            self.globals.push_str("
+                function getObject(idx) {
+                    if ((idx & 1) === 1)
+                        return stack[idx >> 1];
+                    return heapEntry(idx >> 1).obj;
+                }
+            ");
+            return
+        }
         self.expose_global_slab();
 
-        let get_obj = if self.config.debug {
+        let get_obj = if self.config.debug_assertions {
+            self.expose_corrupt_slab();
             String::from("
                 if (typeof(val) === 'number')
-                    throw new Error('corrupt slab');
+                    corruptSlab(idx >> 1);
                 return val.obj;
             ")
         } else {
@@ -398,19 +1119,6 @@ impl<'a> Context<'a> {
         ", get_obj));
     }
 
-    fn expose_check_token(&mut self) {
-        if !self.exposed_globals.insert("check_token") {
-            return
-        }
-        self.globals.push_str(&format!("
-            const token = Symbol('foo');
-            function _checkToken(sym) {{
-                if (token !== sym)
-                    throw new Error('cannot invoke `new` directly');
-            }}
-        "));
-    }
-
     fn expose_assert_num(&mut self) {
         if !self.exposed_globals.insert("assert_num") {
             return
@@ -435,38 +1143,313 @@ impl<'a> Context<'a> {
         "));
     }
 
-    fn expose_pass_string_to_wasm(&mut self) {
-        if !self.exposed_globals.insert("pass_string_to_wasm") {
+    fn expose_assert_char(&mut self) {
+        if !self.exposed_globals.insert("assert_char") {
             return
         }
-        self.required_internal_exports.insert("__wbindgen_malloc");
-        if self.config.nodejs {
+        self.globals.push_str(&format!("
+            function _assertChar(c) {{
+                if (typeof(c) !== 'string' ||
+                    c.length === 0 ||
+                    String.fromCodePoint(c.codePointAt(0)) !== c)
+                    throw new Error('expected a single character argument');
+            }}
+        "));
+    }
+
+    fn expose_assert_date(&mut self) {
+        if !self.exposed_globals.insert("assert_date") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function _assertDate(d) {{
+                if (!(d instanceof Date))
+                    throw new Error('expected a Date argument');
+            }}
+        "));
+    }
+
+    fn expose_assert_bigint(&mut self) {
+        if !self.exposed_globals.insert("assert_bigint") {
+            return
+        }
+        self.expose_require_bigint();
+        self.globals.push_str(&format!("
+            function _assertBigInt(n) {{
+                requireBigInt();
+                if (typeof(n) !== 'bigint')
+                    throw new Error('expected a bigint argument');
+            }}
+        "));
+    }
+
+    // `BigInt` literals are a syntax error where it isn't supported, so the
+    // constants below are all built at runtime instead.
+    fn expose_require_bigint(&mut self) {
+        if !self.exposed_globals.insert("require_bigint") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function requireBigInt() {{
+                if (typeof(BigInt) === 'undefined')
+                    throw new Error('this JS engine lacks `BigInt`, which is \
+                                     needed to pass 64-bit and 128-bit integers');
+            }}
+        "));
+    }
+
+    fn expose_pass_int64_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_int64_to_wasm") {
+            return
+        }
+        self.expose_require_bigint();
+        self.globals.push_str(&format!("
+            function passInt64ToWasm(arg) {{
+                requireBigInt();
+                const val = BigInt.asUintN(64, BigInt(arg));
+                return [Number(val & BigInt(0xffffffff)), Number(val >> BigInt(32))];
+            }}
+        "));
+    }
+
+    fn expose_get_int64_from_wasm(&mut self, signed: bool) -> &'static str {
+        if self.exposed_globals.insert("get_u64_from_wasm") {
+            self.expose_require_bigint();
             self.globals.push_str(&format!("
-                function passStringToWasm(arg) {{
-                    if (typeof(arg) !== 'string')
-                        throw new Error('expected a string argument');
-                    const buf = Buffer.from(arg);
-                    const len = buf.length;
-                    const ptr = wasm.__wbindgen_malloc(len);
-                    buf.copy(Buffer.from(wasm.memory.buffer), ptr);
-                    return [ptr, len];
+                function getU64FromWasm(lo, hi) {{
+                    requireBigInt();
+                    return BigInt(hi >>> 0) << BigInt(32) | BigInt(lo >>> 0);
                 }}
             "));
-        } else {
-            self.expose_text_encoder();
-            self.expose_uint8_memory();
+        }
+        if !signed {
+            return "getU64FromWasm"
+        }
+        if self.exposed_globals.insert("get_i64_from_wasm") {
+            self.globals.push_str(&format!("
+                function getI64FromWasm(lo, hi) {{
+                    return BigInt.asIntN(64, getU64FromWasm(lo, hi));
+                }}
+            "));
+        }
+        "getI64FromWasm"
+    }
+
+    fn expose_pass_int128_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_int128_to_wasm") {
+            return
+        }
+        self.expose_require_bigint();
+        self.globals.push_str(&format!("
+            function passInt128ToWasm(arg) {{
+                requireBigInt();
+                let val = BigInt.asUintN(128, BigInt(arg));
+                const limbs = [];
+                for (let i = 0; i < 4; i++) {{
+                    limbs.push(Number(val & BigInt(0xffffffff)));
+                    val >>= BigInt(32);
+                }}
+                return limbs;
+            }}
+        "));
+    }
+
+    fn expose_get_int128_from_wasm(&mut self, signed: bool) -> &'static str {
+        if self.exposed_globals.insert("get_u128_from_wasm") {
+            self.expose_require_bigint();
             self.globals.push_str(&format!("
-                function passStringToWasm(arg) {{
-                    if (typeof(arg) !== 'string')
-                        throw new Error('expected a string argument');
-                    const buf = textEncoder().encode(arg);
-                    const len = buf.length;
-                    const ptr = wasm.__wbindgen_malloc(len);
-                    getUint8Memory().set(buf, ptr);
-                    return [ptr, len];
+                function getU128FromWasm(l0, l1, l2, l3) {{
+                    requireBigInt();
+                    let ret = BigInt(0);
+                    for (const limb of [l3, l2, l1, l0])
+                        ret = ret << BigInt(32) | BigInt(limb >>> 0);
+                    return ret;
+                }}
+            "));
+        }
+        if !signed {
+            return "getU128FromWasm"
+        }
+        if self.exposed_globals.insert("get_i128_from_wasm") {
+            self.globals.push_str(&format!("
+                function getI128FromWasm(l0, l1, l2, l3) {{
+                    return BigInt.asIntN(128, getU128FromWasm(l0, l1, l2, l3));
                 }}
             "));
         }
+        "getI128FromWasm"
+    }
+
+    fn expose_pass_string_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_string_to_wasm") {
+            return
+        }
+        self.required_internal_exports.insert("__wbindgen_malloc");
+        self.required_internal_exports.insert("__wbindgen_realloc");
+        self.expose_uint8_memory();
+        let encode = if self.config.nodejs {
+            "
+                return Buffer.from(view.buffer, view.byteOffset, view.byteLength)
+                    .write(arg);
+            "
+        } else {
+            self.expose_text_encoder();
+            "
+                const encoder = textEncoder();
+                if (typeof(encoder.encodeInto) === 'function')
+                    return encoder.encodeInto(arg, view).written;
+                const buf = encoder.encode(arg);
+                view.set(buf);
+                return buf.length;
+            "
+        };
+        // Strings are usually ASCII, so optimistically allocate one byte per
+        // UTF-16 code unit and copy characters over until one isn't. The rest
+        // is then encoded into an allocation grown to the worst case of three
+        // bytes per code unit, which is shrunk back down afterwards.
+        self.globals.push_str(&format!("
+            function passStringToWasm(arg) {{
+                if (typeof(arg) !== 'string')
+                    throw new Error('expected a string argument');
+                let len = arg.length;
+                let ptr = wasm.__wbindgen_malloc(len, 1);
+                const mem = getUint8Memory();
+                let offset = 0;
+                for (; offset < len; offset++) {{
+                    const code = arg.charCodeAt(offset);
+                    if (code > 0x7f)
+                        break;
+                    mem[ptr + offset] = code;
+                }}
+                if (offset !== len) {{
+                    const rest = arg.slice(offset);
+                    const cap = offset + rest.length * 3;
+                    ptr = wasm.__wbindgen_realloc(ptr, len, cap, 1);
+                    const view = getUint8Memory().subarray(ptr + offset, ptr + cap);
+                    offset += encodeStringInto(rest, view);
+                    ptr = wasm.__wbindgen_realloc(ptr, cap, offset, 1);
+                    len = offset;
+                }}
+                return [ptr, len];
+            }}
+
+            function encodeStringInto(arg, view) {{
+                {}
+            }}
+        ", encode));
+    }
+
+    fn expose_pass_ascii_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_ascii_to_wasm") {
+            return
+        }
+        self.required_internal_exports.insert("__wbindgen_malloc");
+        self.expose_uint8_memory();
+        let check = if self.config.debug_assertions {
+            "if (/[^\\x00-\\x7f]/.test(arg))
+                throw new Error(`non-ASCII string passed as ASCII: ${arg}`);"
+        } else {
+            ""
+        };
+        self.globals.push_str(&format!("
+            function passAsciiToWasm(arg) {{
+                if (typeof(arg) !== 'string')
+                    throw new Error('expected a string argument');
+                {}
+                const len = arg.length;
+                const ptr = wasm.__wbindgen_malloc(len, 1);
+                const mem = getUint8Memory();
+                for (let i = 0; i < len; i++)
+                    mem[ptr + i] = arg.charCodeAt(i);
+                return [ptr, len];
+            }}
+        ", check));
+    }
+
+    fn expose_get_ascii_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("get_ascii_from_wasm") {
+            return
+        }
+        self.expose_uint8_memory();
+        let check = if self.config.debug_assertions {
+            "if (bytes.some(c => c > 0x7f))
+                throw new Error('non-ASCII string passed as ASCII');"
+        } else {
+            ""
+        };
+        // `fromCharCode` is called on chunks to stay well under engines'
+        // limits on the number of arguments to a function.
+        self.globals.push_str(&format!("
+            function getAsciiFromWasm(ptr, len) {{
+                const bytes = getUint8Memory().subarray(ptr, ptr + len);
+                {}
+                let ret = '';
+                for (let i = 0; i < len; i += 4096)
+                    ret += String.fromCharCode.apply(null, bytes.subarray(i, i + 4096));
+                return ret;
+            }}
+        ", check));
+    }
+
+    fn expose_pass_array8_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_array8_to_wasm") {
+            return
+        }
+        self.expose_pass_slice_u8_to_wasm();
+        self.globals.push_str(&format!("
+            function passArray8ToWasm(arg) {{
+                const ret = passSliceU8ToWasm(arg);
+                // Ownership of a whole `ArrayBuffer` moves into wasm, so
+                // detach it to ensure JS doesn't keep using a stale copy.
+                if (arg instanceof ArrayBuffer)
+                    detachArrayBuffer(arg);
+                return ret;
+            }}
+
+            function detachArrayBuffer(buf) {{
+                if (typeof(structuredClone) === 'function')
+                    structuredClone(buf, {{ transfer: [buf] }});
+                else if (typeof(MessageChannel) === 'function')
+                    new MessageChannel().port1.postMessage(buf, [buf]);
+            }}
+        "));
+    }
+
+    fn expose_pass_slice_u8_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_slice_u8_to_wasm") {
+            return
+        }
+        self.required_internal_exports.insert("__wbindgen_malloc");
+        self.expose_uint8_memory();
+        // Unlike `passArray8ToWasm` the argument is only borrowed, so JS keeps
+        // ownership of its buffer.
+        self.globals.push_str(&format!("
+            function passSliceU8ToWasm(arg) {{
+                let bytes;
+                if (arg instanceof ArrayBuffer)
+                    bytes = new Uint8Array(arg);
+                else if (ArrayBuffer.isView(arg))
+                    bytes = new Uint8Array(arg.buffer, arg.byteOffset, arg.byteLength);
+                else
+                    throw new Error('expected an ArrayBuffer or Uint8Array argument');
+                const len = bytes.length;
+                const ptr = wasm.__wbindgen_malloc(len, 1);
+                getUint8Memory().set(bytes, ptr);
+                return [ptr, len];
+            }}
+        "));
+    }
+
+    fn expose_get_array_u8_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("get_array_u8_from_wasm") {
+            return
+        }
+        self.expose_uint8_memory();
+        self.globals.push_str(&format!("
+            function getArrayU8FromWasm(ptr, len) {{
+                return getUint8Memory().slice(ptr, ptr + len);
+            }}
+        "));
     }
 
     fn expose_text_encoder(&mut self) {
@@ -525,6 +1508,64 @@ impl<'a> Context<'a> {
         }
     }
 
+    fn expose_take_string_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("take_string_from_wasm") {
+            return
+        }
+        self.expose_get_string_from_wasm();
+        self.required_internal_exports.insert("__wbindgen_boxed_str_ptr");
+        self.required_internal_exports.insert("__wbindgen_boxed_str_len");
+        self.required_internal_exports.insert("__wbindgen_boxed_str_free");
+        self.globals.push_str("
+            function takeStringFromWasm(ret) {
+                const ptr = wasm.__wbindgen_boxed_str_ptr(ret);
+                const len = wasm.__wbindgen_boxed_str_len(ret);
+                const realRet = getStringFromWasm(ptr, len);
+                wasm.__wbindgen_boxed_str_free(ret);
+                return realRet;
+            }
+        ");
+    }
+
+    fn expose_take_array_u8_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("take_array_u8_from_wasm") {
+            return
+        }
+        self.expose_global_argument_ptr();
+        self.expose_uint32_memory();
+        self.expose_get_array_u8_from_wasm();
+        self.required_internal_exports.insert("__wbindgen_free");
+        self.globals.push_str("
+            function takeArrayU8FromWasm(ret) {
+                const len = getUint32Memory()[globalArgumentPtr() / 4];
+                const realRet = getArrayU8FromWasm(ret, len);
+                wasm.__wbindgen_free(ret, len, 1);
+                return realRet;
+            }
+        ");
+    }
+
+    fn expose_get_static_string_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("get_static_string_from_wasm") {
+            return
+        }
+        self.expose_get_string_from_wasm();
+        // The contents of a `&'static str` can never change, so each one only
+        // needs to be decoded the first time it's passed to JS.
+        self.globals.push_str("
+            const staticStrings = new Map();
+            function getStaticStringFromWasm(ptr, len) {
+                const cached = staticStrings.get(ptr);
+                if (cached !== undefined && cached.len === len)
+                    return cached.str;
+                const str = getStringFromWasm(ptr, len);
+                if (cached === undefined)
+                    staticStrings.set(ptr, { len, str });
+                return str;
+            }
+        ");
+    }
+
     fn expose_uint8_memory(&mut self) {
         if !self.exposed_globals.insert("uint8_memory") {
             return
@@ -540,6 +1581,66 @@ impl<'a> Context<'a> {
         "));
     }
 
+    fn expose_int8_memory(&mut self) {
+        if !self.exposed_globals.insert("int8_memory") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedInt8Memory = null;
+            function getInt8Memory() {{
+                if (cachedInt8Memory === null ||
+                    cachedInt8Memory.buffer !== wasm.memory.buffer)
+                    cachedInt8Memory = new Int8Array(wasm.memory.buffer);
+                return cachedInt8Memory;
+            }}
+        "));
+    }
+
+    fn expose_uint16_memory(&mut self) {
+        if !self.exposed_globals.insert("uint16_memory") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedUint16Memory = null;
+            function getUint16Memory() {{
+                if (cachedUint16Memory === null ||
+                    cachedUint16Memory.buffer !== wasm.memory.buffer)
+                    cachedUint16Memory = new Uint16Array(wasm.memory.buffer);
+                return cachedUint16Memory;
+            }}
+        "));
+    }
+
+    fn expose_int16_memory(&mut self) {
+        if !self.exposed_globals.insert("int16_memory") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedInt16Memory = null;
+            function getInt16Memory() {{
+                if (cachedInt16Memory === null ||
+                    cachedInt16Memory.buffer !== wasm.memory.buffer)
+                    cachedInt16Memory = new Int16Array(wasm.memory.buffer);
+                return cachedInt16Memory;
+            }}
+        "));
+    }
+
+    fn expose_int32_memory(&mut self) {
+        if !self.exposed_globals.insert("int32_memory") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedInt32Memory = null;
+            function getInt32Memory() {{
+                if (cachedInt32Memory === null ||
+                    cachedInt32Memory.buffer !== wasm.memory.buffer)
+                    cachedInt32Memory = new Int32Array(wasm.memory.buffer);
+                return cachedInt32Memory;
+            }}
+        "));
+    }
+
     fn expose_uint32_memory(&mut self) {
         if !self.exposed_globals.insert("uint32_memory") {
             return
@@ -555,6 +1656,360 @@ impl<'a> Context<'a> {
         "));
     }
 
+    fn expose_float32_memory(&mut self) {
+        if !self.exposed_globals.insert("float32_memory") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedFloat32Memory = null;
+            function getFloat32Memory() {{
+                if (cachedFloat32Memory === null ||
+                    cachedFloat32Memory.buffer !== wasm.memory.buffer)
+                    cachedFloat32Memory = new Float32Array(wasm.memory.buffer);
+                return cachedFloat32Memory;
+            }}
+        "));
+    }
+
+    fn expose_float64_memory(&mut self) {
+        if !self.exposed_globals.insert("float64_memory") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedFloat64Memory = null;
+            function getFloat64Memory() {{
+                if (cachedFloat64Memory === null ||
+                    cachedFloat64Memory.buffer !== wasm.memory.buffer)
+                    cachedFloat64Memory = new Float64Array(wasm.memory.buffer);
+                return cachedFloat64Memory;
+            }}
+        "));
+    }
+
+    fn expose_bigint64_memory(&mut self) {
+        if !self.exposed_globals.insert("bigint64_memory") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedBigInt64Memory = null;
+            function getBigInt64Memory() {{
+                if (cachedBigInt64Memory === null ||
+                    cachedBigInt64Memory.buffer !== wasm.memory.buffer)
+                    cachedBigInt64Memory = new BigInt64Array(wasm.memory.buffer);
+                return cachedBigInt64Memory;
+            }}
+        "));
+    }
+
+    fn expose_biguint64_memory(&mut self) {
+        if !self.exposed_globals.insert("biguint64_memory") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedBigUint64Memory = null;
+            function getBigUint64Memory() {{
+                if (cachedBigUint64Memory === null ||
+                    cachedBigUint64Memory.buffer !== wasm.memory.buffer)
+                    cachedBigUint64Memory = new BigUint64Array(wasm.memory.buffer);
+                return cachedBigUint64Memory;
+            }}
+        "));
+    }
+
+    fn expose_uint8_clamped_memory(&mut self) {
+        if !self.exposed_globals.insert("uint8_clamped_memory") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedUint8ClampedMemory = null;
+            function getUint8ClampedMemory() {{
+                if (cachedUint8ClampedMemory === null ||
+                    cachedUint8ClampedMemory.buffer !== wasm.memory.buffer)
+                    cachedUint8ClampedMemory = new Uint8ClampedArray(wasm.memory.buffer);
+                return cachedUint8ClampedMemory;
+            }}
+        "));
+    }
+
+    /// Makes a view of wasm memory for the numeric slice type `ty` available,
+    /// returning how such slices are represented in JS.
+    fn expose_slice_memory(&mut self, ty: shared::Type) -> SliceArray {
+        match ty {
+            shared::TYPE_SLICE_I8 => self.expose_int8_memory(),
+            shared::TYPE_SLICE_U8 => self.expose_uint8_memory(),
+            shared::TYPE_SLICE_U8_CLAMPED => self.expose_uint8_clamped_memory(),
+            shared::TYPE_SLICE_I16 => self.expose_int16_memory(),
+            shared::TYPE_SLICE_U16 => self.expose_uint16_memory(),
+            shared::TYPE_SLICE_I32 => self.expose_int32_memory(),
+            shared::TYPE_SLICE_U32 => self.expose_uint32_memory(),
+            shared::TYPE_SLICE_F32 => self.expose_float32_memory(),
+            shared::TYPE_SLICE_F64 => self.expose_float64_memory(),
+            shared::TYPE_SLICE_I64 => self.expose_bigint64_memory(),
+            shared::TYPE_SLICE_U64 => self.expose_biguint64_memory(),
+            _ => panic!("not a slice type: {:#x}", ty as u32),
+        }
+        slice_array(ty).unwrap()
+    }
+
+    /// Defines a function copying a typed array into a fresh allocation for
+    /// the numeric slice type `ty`, aligned to its element size, returning its
+    /// name.
+    fn expose_pass_slice_to_wasm(&mut self, ty: shared::Type) -> &'static str {
+        if ty == shared::TYPE_SLICE_U8 {
+            self.expose_pass_slice_u8_to_wasm();
+            return "passSliceU8ToWasm"
+        }
+        let a = self.expose_slice_memory(ty);
+        if !self.exposed_globals.insert(a.pass) {
+            return a.pass
+        }
+        self.required_internal_exports.insert("__wbindgen_malloc");
+        self.globals.push_str(&format!("
+            function {name}(arg) {{
+                if (!(arg instanceof {array}))
+                    throw new Error('expected a {array} argument');
+                const ptr = wasm.__wbindgen_malloc(arg.length * {size}, {size});
+                {memory}().set(arg, ptr / {size});
+                return [ptr, arg.length];
+            }}
+        ", name = a.pass, array = a.array, size = a.size, memory = a.memory));
+        a.pass
+    }
+
+    /// Defines a function copying a returned vector of the numeric slice type
+    /// `ty` out of wasm memory and freeing it, returning its name.
+    fn expose_take_array_from_wasm(&mut self, ty: shared::Type) -> &'static str {
+        let a = self.expose_slice_memory(ty);
+        if !self.exposed_globals.insert(a.take) {
+            return a.take
+        }
+        self.expose_global_argument_ptr();
+        self.expose_uint32_memory();
+        self.required_internal_exports.insert("__wbindgen_free");
+        self.globals.push_str(&format!("
+            function {name}(ret) {{
+                const len = getUint32Memory()[globalArgumentPtr() / 4];
+                const realRet = {memory}().slice(ret / {size}, ret / {size} + len);
+                wasm.__wbindgen_free(ret, len * {size}, {size});
+                return realRet;
+            }}
+        ", name = a.take, size = a.size, memory = a.memory));
+        a.take
+    }
+
+    fn expose_pass_array_jsvalue_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_array_jsvalue_to_wasm") {
+            return
+        }
+        self.expose_uint32_memory();
+        self.expose_add_heap_object();
+        self.required_internal_exports.insert("__wbindgen_malloc");
+        self.globals.push_str(&format!("
+            function passArrayJsValueToWasm(array) {{
+                if (!Array.isArray(array))
+                    throw new Error('expected an array argument');
+                const ptr = wasm.__wbindgen_malloc(array.length * 4, 4);
+                const mem = getUint32Memory();
+                for (let i = 0; i < array.length; i++)
+                    mem[ptr / 4 + i] = addHeapObject(array[i]);
+                return [ptr, array.length];
+            }}
+        "));
+    }
+
+    fn expose_flatten_map(&mut self) {
+        if !self.exposed_globals.insert("flatten_map") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function flattenMap(map) {{
+                const ret = [];
+                if (map instanceof Map) {{
+                    map.forEach((value, key) => ret.push(key, value));
+                }} else if (map !== null && typeof(map) === 'object') {{
+                    for (const key of Object.keys(map))
+                        ret.push(key, map[key]);
+                }} else {{
+                    throw new Error('expected a Map or an object');
+                }}
+                for (let i = 0; i < ret.length; i += 2) {{
+                    if (typeof(ret[i]) !== 'string')
+                        throw new Error('expected map keys to be strings');
+                }}
+                return ret;
+            }}
+        "));
+    }
+
+    fn expose_unflatten_map(&mut self) {
+        if !self.exposed_globals.insert("unflatten_map") {
+            return
+        }
+        self.globals.push_str(&format!("
+            function unflattenMap(array, plainObject) {{
+                if (plainObject) {{
+                    const ret = {{}};
+                    for (let i = 0; i < array.length; i += 2)
+                        ret[array[i]] = array[i + 1];
+                    return ret;
+                }}
+                const ret = new Map();
+                for (let i = 0; i < array.length; i += 2)
+                    ret.set(array[i], array[i + 1]);
+                return ret;
+            }}
+        "));
+    }
+
+    fn expose_pass_array_string_to_wasm(&mut self) {
+        if !self.exposed_globals.insert("pass_array_string_to_wasm") {
+            return
+        }
+        self.expose_uint32_memory();
+        self.expose_pass_string_to_wasm();
+        self.required_internal_exports.insert("__wbindgen_malloc");
+        self.globals.push_str(&format!("
+            function passArrayStringToWasm(array) {{
+                if (!Array.isArray(array))
+                    throw new Error('expected an array argument');
+                const ptr = wasm.__wbindgen_malloc(array.length * 8, 4);
+                for (let i = 0; i < array.length; i++) {{
+                    const [strptr, strlen] = passStringToWasm(array[i]);
+                    // passing the string may grow memory
+                    const mem = getUint32Memory();
+                    mem[ptr / 4 + i * 2] = strptr;
+                    mem[ptr / 4 + i * 2 + 1] = strlen;
+                }}
+                return [ptr, array.length];
+            }}
+        "));
+    }
+
+    fn expose_take_array_string_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("take_array_string_from_wasm") {
+            return
+        }
+        self.expose_global_argument_ptr();
+        self.expose_uint32_memory();
+        self.expose_get_string_from_wasm();
+        self.required_internal_exports.insert("__wbindgen_free");
+        self.globals.push_str(&format!("
+            function takeArrayStringFromWasm(ret) {{
+                const mem = getUint32Memory();
+                const len = mem[globalArgumentPtr() / 4];
+                const realRet = [];
+                for (let i = 0; i < len; i++) {{
+                    const strptr = mem[ret / 4 + i * 2];
+                    const strlen = mem[ret / 4 + i * 2 + 1];
+                    realRet.push(getStringFromWasm(strptr, strlen));
+                    wasm.__wbindgen_free(strptr, strlen, 1);
+                }}
+                wasm.__wbindgen_free(ret, len * 8, 4);
+                return realRet;
+            }}
+        "));
+    }
+
+    fn expose_take_array_jsvalue_from_wasm(&mut self) {
+        if !self.exposed_globals.insert("take_array_jsvalue_from_wasm") {
+            return
+        }
+        self.expose_global_argument_ptr();
+        self.expose_uint32_memory();
+        self.expose_take_object();
+        self.required_internal_exports.insert("__wbindgen_free");
+        self.globals.push_str(&format!("
+            function takeArrayJsValueFromWasm(ret) {{
+                const mem = getUint32Memory();
+                const len = mem[globalArgumentPtr() / 4];
+                const realRet = [];
+                for (let i = 0; i < len; i++)
+                    realRet.push(takeObject(mem[ret / 4 + i]));
+                wasm.__wbindgen_free(ret, len * 4, 4);
+                return realRet;
+            }}
+        "));
+    }
+
+    fn expose_enum_index(&mut self) {
+        if !self.exposed_globals.insert("enum_index") {
+            return
+        }
+        self.globals.push_str("
+            function enumIndex(values, val, name) {
+                const idx = values.indexOf(val);
+                if (idx === -1)
+                    throw new Error('invalid value for ' + name + ': ' + val);
+                return idx;
+            }
+        ");
+    }
+
+    fn expose_read_blob(&mut self) {
+        if !self.exposed_globals.insert("read_blob") {
+            return
+        }
+        self.globals.push_str("
+            function readBlob(arg) {
+                if (typeof Blob === 'undefined' || !(arg instanceof Blob))
+                    return Promise.resolve(arg);
+                if (typeof arg.arrayBuffer === 'function')
+                    return arg.arrayBuffer();
+                return new Promise((resolve, reject) => {
+                    const reader = new FileReader();
+                    reader.onload = () => resolve(reader.result);
+                    reader.onerror = () => reject(reader.error);
+                    reader.readAsArrayBuffer(arg);
+                });
+            }
+        ");
+    }
+
+    fn expose_data_view(&mut self) {
+        if !self.exposed_globals.insert("data_view") {
+            return
+        }
+        self.globals.push_str(&format!("
+            let cachedDataView = null;
+            function getDataView() {{
+                if (cachedDataView === null ||
+                    cachedDataView.buffer !== wasm.memory.buffer)
+                    cachedDataView = new DataView(wasm.memory.buffer);
+                return cachedDataView;
+            }}
+        "));
+    }
+
+    fn expose_global_argument_ptr(&mut self) {
+        if !self.exposed_globals.insert("global_argument_ptr") {
+            return
+        }
+        self.required_internal_exports.insert("__wbindgen_global_argument_ptr");
+        self.globals.push_str("
+            let cachedGlobalArgumentPtr = null;
+            function globalArgumentPtr() {
+                if (cachedGlobalArgumentPtr === null)
+                    cachedGlobalArgumentPtr = wasm.__wbindgen_global_argument_ptr();
+                return cachedGlobalArgumentPtr;
+            }
+        ");
+    }
+
+    fn expose_tuple_ret_ptr(&mut self) {
+        if !self.exposed_globals.insert("tuple_ret_ptr") {
+            return
+        }
+        self.required_internal_exports.insert("__wbindgen_tuple_ret_ptr");
+        self.globals.push_str("
+            let cachedTupleRetPtr = null;
+            function tupleRetPtr() {
+                if (cachedTupleRetPtr === null)
+                    cachedTupleRetPtr = wasm.__wbindgen_tuple_ret_ptr();
+                return cachedTupleRetPtr;
+            }
+        ");
+    }
+
     fn expose_assert_class(&mut self) {
         if !self.exposed_globals.insert("assert_class") {
             return
@@ -600,13 +2055,29 @@ impl<'a> Context<'a> {
         if !self.exposed_globals.insert("add_heap_object") {
             return
         }
+        if self.config.map_heap {
+            self.expose_global_heap();
+            self.globals.push_str("
+                function addHeapObject(obj) {
+                    const id = heap_next_id++;
+                    if (id >= 0x40000000)
+                        throw new Error('heap ids exhausted');
+                    heap.set(id, { obj, cnt: 1, site: new Error().stack });
+                    return id << 1;
+                }
+            ");
+            return
+        }
         self.expose_global_slab();
         self.expose_global_slab_next();
-        let set_slab_next = if self.config.debug {
+        self.expose_grow_slab();
+        let set_slab_next = if self.config.debug_assertions {
+            self.expose_corrupt_slab();
             String::from("
                 if (typeof(next) !== 'number')
-                    throw new Error('corrupt slab');
+                    corruptSlab(idx);
                 slab_next = next;
+                slabAllocationSites[idx] = new Error().stack;
             ")
         } else {
             String::from("
@@ -616,7 +2087,7 @@ impl<'a> Context<'a> {
         self.globals.push_str(&format!("
             function addHeapObject(obj) {{
                 if (slab_next == slab.length)
-                    slab.push(slab.length + 1);
+                    growSlab();
                 const idx = slab_next;
                 const next = slab[idx];
                 {}
@@ -626,6 +2097,171 @@ impl<'a> Context<'a> {
         ", set_slab_next));
     }
 
+    fn expose_grow_slab(&mut self) {
+        if !self.exposed_globals.insert("grow_slab") {
+            return
+        }
+        self.expose_global_slab();
+        let growth = match self.config.slab_growth {
+            SlabGrowth::Single => String::from("1"),
+            SlabGrowth::Chunk(n) => n.to_string(),
+            SlabGrowth::Double => String::from("Math.max(slab.length, 1)"),
+        };
+        self.globals.push_str(&format!("
+            function growSlab() {{
+                const end = slab.length + {};
+                for (let i = slab.length; i < end; i++)
+                    slab.push(i + 1);
+            }}
+        ", growth));
+    }
+
+    fn expose_corrupt_slab(&mut self) {
+        if !self.exposed_globals.insert("corrupt_slab") {
+            return
+        }
+        // Remember where each slot was last allocated so that errors about
+        // stale or leaked references can point at the binding responsible.
+        self.globals.push_str("
+            const slabAllocationSites = [];
+            function corruptSlab(idx) {
+                let msg = 'corrupt slab';
+                const site = slabAllocationSites[idx];
+                if (site !== undefined)
+                    msg += ', index ' + idx + ' was last allocated at\\n' + site;
+                throw new Error(msg);
+            }
+        ");
+    }
+
+    fn expose_assert_heap_and_stack_empty(&mut self) {
+        if !self.exposed_globals.insert("assert_heap_and_stack_empty") {
+            return
+        }
+        self.expose_global_stack();
+        let find_leaks = if self.config.map_heap {
+            self.expose_global_heap();
+            "
+                for (const [id, val] of heap)
+                    leaks.push('id ' + id + ' allocated at\\n' + val.site);
+            "
+        } else {
+            self.expose_global_slab();
+            self.expose_corrupt_slab();
+            "
+                for (let i = 0; i < slab.length; i++) {
+                    if (typeof(slab[i]) !== 'number')
+                        leaks.push('index ' + i + ' allocated at\\n' + slabAllocationSites[i]);
+                }
+            "
+        };
+        self.globals.push_str(&format!("
+            export function assertHeapAndStackEmpty() {{
+                if (stack.length > 0)
+                    throw new Error('stack is not empty');
+                const leaks = [];
+                {}
+                if (leaks.length > 0)
+                    throw new Error('heap is not empty, leaked objects:\\n' + leaks.join('\\n'));
+            }}
+        ", find_leaks));
+    }
+
+    fn expose_allocation_stats(&mut self) {
+        if !self.exposed_globals.insert("allocation_stats") {
+            return
+        }
+        self.required_internal_exports.insert("__wbindgen_alloc_stats_ptr");
+        self.expose_uint32_memory();
+        self.globals.push_str("
+            export function allocationStats() {
+                const ptr = wasm.__wbindgen_alloc_stats_ptr() / 4;
+                const mem = getUint32Memory();
+                return {
+                    mallocs: mem[ptr],
+                    mallocBytes: mem[ptr + 1],
+                    frees: mem[ptr + 2],
+                    freeBytes: mem[ptr + 3],
+                    reallocs: mem[ptr + 4],
+                };
+            }
+        ");
+        self.typescript.push_str("export function allocationStats(): \
+            { mallocs: number, mallocBytes: number, frees: number, freeBytes: number, \
+              reallocs: number };\n");
+    }
+
+    /// Makes the DOM type `name` usable in the `.d.ts` file.
+    ///
+    /// Declarations for Node.js can't rely on `lib.dom.d.ts` being present,
+    /// so a minimal stand-in is declared alongside the exports instead. Being
+    /// local to the module it doesn't conflict with the real type when the
+    /// DOM library is available.
+    fn declare_dom_type(&mut self, name: &'static str) {
+        if !self.config.nodejs || !self.exposed_globals.insert(name) {
+            return
+        }
+        let decl = match name {
+            "Blob" => "
+                interface Blob {
+                    readonly size: number;
+                    readonly type: string;
+                    arrayBuffer(): Promise<ArrayBuffer>;
+                    slice(start?: number, end?: number, contentType?: string): Blob;
+                    text(): Promise<string>;
+                }
+            ",
+            "ReadableStream" => "
+                interface ReadableStream<R> {
+                    readonly locked: boolean;
+                    cancel(reason?: any): Promise<void>;
+                    getReader(): any;
+                }
+            ",
+            _ => panic!("unknown DOM type {}", name),
+        };
+        self.typescript.push_str(decl);
+    }
+
+    /// Looks up the registered `TypeHook` for the descriptor `ty`, if any,
+    /// emitting everything it depends on the first time it's used.
+    fn type_hook(&mut self, ty: char) -> Option<&'a dyn TypeHook> {
+        if (ty as u32) < shared::TYPE_HOOK_START {
+            return None
+        }
+        let config: &'a Bindgen = self.config;
+        let idx = config.type_hooks.iter().position(|h| h.handles(ty))?;
+        let hook = &*config.type_hooks[idx];
+        if !self.exposed_type_hooks.insert(idx) {
+            return Some(hook)
+        }
+        for intrinsic in hook.intrinsics() {
+            match intrinsic {
+                Intrinsic::GetObject => self.expose_get_object(),
+                Intrinsic::AddHeapObject => self.expose_add_heap_object(),
+                Intrinsic::TakeObject => self.expose_take_object(),
+                Intrinsic::GetStringFromWasm => self.expose_get_string_from_wasm(),
+                Intrinsic::PassStringToWasm => self.expose_pass_string_to_wasm(),
+                Intrinsic::Uint8Memory => self.expose_uint8_memory(),
+                Intrinsic::Uint32Memory => self.expose_uint32_memory(),
+            }
+        }
+        for export in hook.required_exports() {
+            self.required_internal_exports.insert(export);
+        }
+        self.globals.push_str(&hook.globals());
+        Some(hook)
+    }
+
+    fn wasm_export_exists(&self, name: &str) -> bool {
+        let exports = match self.module.export_section() {
+            Some(s) => s,
+            None => return false,
+        };
+
+        exports.entries().iter().any(|e| e.field() == name)
+    }
+
     fn wasm_import_needed(&self, name: &str) -> bool {
         let imports = match self.module.import_section() {
             Some(s) => s,
@@ -640,69 +2276,485 @@ impl<'a> Context<'a> {
 
 impl<'a, 'b> SubContext<'a, 'b> {
     pub fn generate(&mut self) {
-        for f in self.program.exports.iter() {
+        let program = self.program;
+        let mut overloads: Vec<(&str, Vec<&shared::Export>)> = Vec::new();
+        for f in program.exports.iter() {
+            if let (None, &Some(ref name)) = (&f.class, &f.js_name) {
+                let count = program.exports.iter()
+                    .filter(|e| e.class.is_none() && e.js_name.as_ref() == Some(name))
+                    .count();
+                if count > 1 {
+                    match overloads.iter().position(|o| o.0 == &name[..]) {
+                        Some(i) => overloads[i].1.push(f),
+                        None => overloads.push((name, vec![f])),
+                    }
+                    continue
+                }
+            }
             self.generate_export(f);
         }
+        for (name, exports) in overloads {
+            self.generate_overloads(name, &exports);
+        }
         for f in self.program.imports.iter() {
             self.generate_import(f);
         }
+        for v in self.program.raw_views.iter() {
+            self.generate_raw_view(v);
+        }
+        for e in self.program.string_enums.iter() {
+            self.generate_string_enum(e);
+        }
+        for e in self.program.enums.iter() {
+            self.generate_enum(e);
+        }
+        for e in self.program.tagged_enums.iter() {
+            self.generate_tagged_enum(e);
+        }
+        for s in self.program.structs.iter() {
+            if let Some(class) = self.cx.exported_classes.get_mut(&s.name) {
+                class.comments = format_doc_comments(&s.comments);
+                class.implements = s.typescript_implements.clone();
+                class.unit = s.unit;
+                class.free_method = s.free_method.clone();
+            }
+        }
+        let functions = self.program.exports.iter().map(|e| &e.function)
+            .chain(self.program.imports.iter().map(|i| &i.function));
+        for f in functions {
+            for ty in f.arguments.iter().chain(f.ret.iter()) {
+                if let Some(name) = self.cx.custom_type_names.get(ty) {
+                    self.cx.referenced_classes.insert(name.clone());
+                }
+            }
+        }
+    }
+
+    pub fn generate_string_enum(&mut self, e: &shared::StringEnum) {
+        let values = e.variants.iter()
+            .map(|v| format!("{:?}", v))
+            .collect::<Vec<_>>();
+        self.cx.globals.push_str(&format!("
+            const __wbg_{}_values = [{}];
+        ", e.name, values.join(", ")));
+        self.cx.typescript.push_str(&format_doc_comments(&e.comments));
+        self.cx.typescript.push_str(&format!("export type {} = {};\n",
+                                             e.name,
+                                             values.join(" | ")));
+    }
+
+    pub fn generate_enum(&mut self, e: &shared::Enum) {
+        let js_variants = e.variants.iter()
+            .map(|v| format!("{}: {}", v.name, v.value))
+            .collect::<Vec<_>>();
+        self.cx.globals.push_str(&format!("
+            export const {} = Object.freeze({{ {} }});
+        ", e.name, js_variants.join(", ")));
+        let ts_variants = e.variants.iter()
+            .map(|v| format!("{} = {}", v.name, v.value))
+            .collect::<Vec<_>>();
+        self.cx.typescript.push_str(&format_doc_comments(&e.comments));
+        self.cx.typescript.push_str(&format!("export enum {} {{ {} }}\n",
+                                             e.name,
+                                             ts_variants.join(", ")));
+    }
+
+    pub fn generate_tagged_enum(&mut self, e: &shared::TaggedEnum) {
+        let variants = e.variants.iter()
+            .map(|v| {
+                let mut fields = vec![format!("kind: {:?}", v.kind)];
+                fields.extend(v.fields.iter()
+                    .map(|f| format!("{}: {}", f.name, f.typescript)));
+                format!("{{ {} }}", fields.join(", "))
+            })
+            .collect::<Vec<_>>();
+        if self.cx.error_enums.contains(&e.descriptor) {
+            return self.generate_error_enum(e, &variants)
+        }
+        self.cx.typescript.push_str(&format_doc_comments(&e.comments));
+        self.cx.typescript.push_str(&format!("export type {} = {};\n",
+                                             e.name,
+                                             variants.join(" | ")));
+    }
+
+    /// Generates an `Error` subclass for an enum thrown by exports, along with
+    /// a subclass of that for each variant, such that `ParseError::Empty` is
+    /// thrown as a `ParseErrorEmpty` which is also an instance of `ParseError`.
+    fn generate_error_enum(&mut self, e: &shared::TaggedEnum, variants: &[String]) {
+        self.cx.globals.push_str(&format!("
+            export class {name} extends Error {{
+                constructor(obj, message) {{
+                    super(message);
+                    Object.assign(this, obj);
+                }}
+            }}
+            {name}.prototype.name = '{name}';
+        ", name = e.name));
+        self.cx.typescript.push_str(&format_doc_comments(&e.comments));
+        self.cx.typescript.push_str(&format!("
+            export class {} extends Error {{
+                kind: {};
+            }}
+        ", e.name, e.variants.iter()
+            .map(|v| format!("{:?}", v.kind))
+            .collect::<Vec<_>>()
+            .join(" | ")));
+
+        let mut cases = String::new();
+        for (v, ts) in e.variants.iter().zip(variants) {
+            let variant = v.kind.chars()
+                .take(1)
+                .flat_map(|c| c.to_uppercase())
+                .chain(v.kind.chars().skip(1))
+                .collect::<String>();
+            let class = format!("{}{}", e.name, variant);
+            self.cx.globals.push_str(&format!("
+                export class {class} extends {name} {{}}
+                {class}.prototype.name = '{class}';
+            ", class = class, name = e.name));
+            self.cx.typescript.push_str(&format!("
+                export class {} extends {} {{
+                    {}
+                }}
+            ", class, e.name, ts[2..ts.len() - 2].replace(", ", ";\n") + ";"));
+            cases.push_str(&format!("
+                case {kind:?}:
+                    return new {class}(obj, '{name}::{variant}');
+            ", kind = v.kind, class = class, name = e.name, variant = variant));
+        }
+        self.cx.globals.push_str(&format!("
+            function __wbg_error_{name}(obj) {{
+                switch (obj.kind) {{
+                    {cases}
+                }}
+                return new {name}(obj, '{name}');
+            }}
+        ", name = e.name, cases = cases));
+    }
+
+    pub fn generate_raw_view(&mut self, view: &shared::RawView) {
+        self.cx.expose_data_view();
+        let mut dst = format!("export class {} {{", view.name);
+        let mut ts_dst = format_doc_comments(&view.comments);
+        ts_dst.push_str(&dst);
+        dst.push_str(&format!("
+            constructor(ptr) {{
+                this.ptr = ptr;
+            }}
+
+            static get size() {{
+                return {size};
+            }}
+
+            at(index) {{
+                return new {name}(this.ptr + index * {size});
+            }}
+        ", name = view.name, size = view.size));
+        ts_dst.push_str(&format!("
+            public ptr: number;
+            static readonly size: number;
+            constructor(ptr: number);
+            at(index: number): {};
+        ", view.name));
+
+        for field in view.fields.iter() {
+            dst.push_str(&format!("
+                get {name}() {{
+                    return getDataView().get{kind}(this.ptr + {offset}, {le});
+                }}
+
+                set {name}(value) {{
+                    getDataView().set{kind}(this.ptr + {offset}, value, {le});
+                }}
+            ",
+                name = field.name,
+                kind = field.kind,
+                offset = field.offset,
+                le = field.little_endian,
+            ));
+            ts_dst.push_str(&format_doc_comments(&field.comments));
+            ts_dst.push_str(&format!("{}: number;\n", field.name));
+        }
+        dst.push_str("}\n");
+        ts_dst.push_str("}\n");
+
+        self.cx.globals.push_str(&dst);
+        self.cx.typescript.push_str(&ts_dst);
     }
 
     pub fn generate_export(&mut self, export: &shared::Export) {
         if let Some(ref class) = export.class {
             return self.generate_export_for_class(class, export)
         }
-        let (js, ts) = self.generate_function("function",
-                                              &export.function.name,
-                                              false,
-                                              &export.function);
-        self.cx.globals.push_str("export ");
-        self.cx.globals.push_str(&js);
-        self.cx.globals.push_str("\n");
+        let js_name = export.js_name.as_ref().unwrap_or(&export.function.name);
+        let f = self.generate_function("function",
+                                       js_name,
+                                       &export.function.name,
+                                       false,
+                                       export);
+        // Functions which only deal in numbers don't need any glue at all, so
+        // rather than paying for a wrapper on every call just re-export the
+        // wasm function itself.
+        if self.is_abi_trivial(export) {
+            self.cx.globals.push_str(&format!("export const {} = wasm.{};\n",
+                                              js_name,
+                                              export.function.name));
+        } else {
+            self.cx.globals.push_str("export ");
+            self.cx.globals.push_str(&f.js);
+            self.cx.globals.push_str("\n");
+        }
+        self.cx.typescript.push_str(&format_doc_comments(&export.comments));
         self.cx.typescript.push_str("export ");
-        self.cx.typescript.push_str(&ts);
+        self.cx.typescript.push_str(&f.ts);
         self.cx.typescript.push_str("\n");
     }
 
+    /// Generates a set of exports sharing one `js_name`, each behind a
+    /// module-private function, along with an exported function picking
+    /// between them based on the number and types of its arguments.
+    fn generate_overloads(&mut self, name: &str, exports: &[&shared::Export]) {
+        let mut dispatch = String::new();
+        for (i, export) in exports.iter().enumerate() {
+            let inner = format!("__wbg_{}_{}", name, i);
+            let f = self.generate_function("function",
+                                           &inner,
+                                           &export.function.name,
+                                           false,
+                                           export);
+            self.cx.globals.push_str(&f.js);
+            self.cx.globals.push_str("\n");
+            self.cx.typescript.push_str(&format_doc_comments(&export.comments));
+            self.cx.typescript.push_str("export ");
+            self.cx.typescript.push_str(&f.ts.replacen(&inner, name, 1));
+            self.cx.typescript.push_str("\n");
+
+            let arguments = &export.function.arguments;
+            let required = arguments.len() - export.optional_args;
+            let mut checks = vec![
+                format!("args.length >= {}", required),
+                format!("args.length <= {}", arguments.len()),
+            ];
+            for (j, arg) in arguments.iter().enumerate() {
+                let check = match self.overload_check(*arg, &format!("args[{}]", j)) {
+                    Some(check) => check,
+                    None => continue,
+                };
+                if !export.function.optional.contains(&j) {
+                    checks.push(check);
+                } else {
+                    checks.push(format!("(args[{0}] == null || {1})", j, check));
+                }
+            }
+            dispatch.push_str(&format!("
+                if ({})
+                    return {}(...args);
+            ", checks.join(" && "), inner));
+        }
+        self.cx.globals.push_str(&format!("
+            export function {name}(...args) {{
+                {dispatch}
+                throw new Error('no overload of `{name}` matches the arguments');
+            }}
+        ", name = name, dispatch = dispatch));
+    }
+
+    /// Returns a JS expression checking whether `arg` can be passed as the
+    /// type `ty`, or `None` if any value can.
+    fn overload_check(&self, ty: shared::Type, arg: &str) -> Option<String> {
+        let check = match ty {
+            shared::TYPE_NUMBER => format!("typeof({}) === 'number'", arg),
+            shared::TYPE_BOOLEAN => format!("typeof({}) === 'boolean'", arg),
+            shared::TYPE_U64 |
+            shared::TYPE_I64 |
+            shared::TYPE_U128 |
+            shared::TYPE_I128 => format!("typeof({}) === 'bigint'", arg),
+            shared::TYPE_CHAR => {
+                format!("(typeof({0}) === 'string' && {0}.length > 0 && \
+                          String.fromCodePoint({0}.codePointAt(0)) === {0})", arg)
+            }
+            shared::TYPE_DATE => format!("{} instanceof Date", arg),
+            shared::TYPE_BORROWED_STR |
+            shared::TYPE_STRING => format!("typeof({}) === 'string'", arg),
+            shared::TYPE_SLICE_U8 |
+            shared::TYPE_VECTOR_U8 => {
+                format!("({0} instanceof ArrayBuffer || ArrayBuffer.isView({0}))", arg)
+            }
+            ty if slice_array(ty).is_some() => {
+                format!("{} instanceof {}", arg, slice_array(ty).unwrap().array)
+            }
+            ty if vector_slice(ty).is_some() => {
+                let a = slice_array(vector_slice(ty).unwrap()).unwrap();
+                format!("{} instanceof {}", arg, a.array)
+            }
+            shared::TYPE_VECTOR_JSVALUE |
+            shared::TYPE_VECTOR_STRING => format!("Array.isArray({})", arg),
+            shared::TYPE_MAP_STRING |
+            shared::TYPE_MAP_JSVALUE => {
+                format!("({0} instanceof Map || typeof({0}) === 'object')", arg)
+            }
+            shared::TYPE_JS_OWNED |
+            shared::TYPE_JS_REF => return None,
+            e if self.cx.string_enums.contains_key(&e) => {
+                format!("typeof({}) === 'string'", arg)
+            }
+            e if self.cx.enums.contains_key(&e) => {
+                format!("typeof({}) === 'number'", arg)
+            }
+            e if self.cx.tagged_enums.contains_key(&e) => {
+                format!("typeof({}) === 'object'", arg)
+            }
+            e if self.cx.custom_type_names.contains_key(&e) => {
+                format!("{} instanceof {}", arg, self.cx.custom_type_names[&e])
+            }
+            _ => return None,
+        };
+        Some(check)
+    }
+
+    fn is_abi_trivial(&self, export: &shared::Export) -> bool {
+        if self.cx.config.debug_assertions ||
+            export.function.optional.len() > 0 ||
+            export.optional_ret ||
+            export.tuple_ret.len() > 0 ||
+            export.error.is_some() ||
+            export.blob.is_some() ||
+            export.read_blobs ||
+            export.readable_stream ||
+            export.stream_sink
+        {
+            return false
+        }
+        let function = &export.function;
+        function.arguments.iter().all(|a| *a == shared::TYPE_NUMBER) &&
+            match function.ret {
+                None | Some(shared::TYPE_NUMBER) => true,
+                Some(_) => false,
+            }
+    }
+
     pub fn generate_export_for_class(&mut self, class: &str, export: &shared::Export) {
-        let (js, ts) = if export.method {
-            self.generate_function(
-                "",
-                &shared::struct_function_export_name(class, &export.function.name),
-                true,
-                &export.function,
-            )
+        let name = &export.function.name;
+        let wasm_name = shared::struct_function_export_name(class, name);
+        let name = export.js_name.as_ref().unwrap_or(name);
+        if export.constructor {
+            let f = self.generate_function("", "constructor", &wasm_name, false, export);
+            let class = self.cx.exported_classes.entry(class.to_string())
+                .or_insert(ExportedClass::default());
+            class.methods = true;
+            class.constructor = Some(f.js);
+            class.constructor_typescript = format_doc_comments(&export.comments);
+            class.constructor_typescript.push_str(&format!(" constructor({});\n", f.ts_params));
+            return
+        }
+        // The iteration protocol is implemented on top of `next` in
+        // `write_classes`, so it's only made available to the class itself.
+        if export.iterator {
+            let f = self.generate_function("", "__wbg_next", &wasm_name, true, export);
+            let class = self.cx.exported_classes.entry(class.to_string())
+                .or_insert(ExportedClass::default());
+            class.methods = true;
+            class.contents.push_str(&f.js);
+            class.contents.push_str("\n");
+            class.iterator = Some(f.ts_ret.trim_end_matches(" | undefined").to_string());
+            return
+        }
+        // Accessors are declared as a property in TypeScript, with the type
+        // taken from the getter's return value or else the setter's argument.
+        if let Some(ref prop) = export.getter {
+            let f = self.generate_function("get", prop, &wasm_name, true, export);
+            let class = self.cx.exported_classes.entry(class.to_string())
+                .or_insert(ExportedClass::default());
+            class.methods = true;
+            class.contents.push_str(&f.js);
+            class.contents.push_str("\n");
+            let property = class.property(prop);
+            property.comments = format_doc_comments(&export.comments);
+            property.ty = f.ts_ret;
+            property.getter = true;
+            return
+        }
+        if let Some(ref prop) = export.setter {
+            let f = self.generate_function("set", prop, &wasm_name, true, export);
+            let class = self.cx.exported_classes.entry(class.to_string())
+                .or_insert(ExportedClass::default());
+            class.methods = true;
+            class.contents.push_str(&f.js);
+            class.contents.push_str("\n");
+            let property = class.property(prop);
+            if !property.getter {
+                // Setters take a single argument, declared as `arg0: T`
+                property.comments = format_doc_comments(&export.comments);
+                property.ty = f.ts_params.trim_start_matches("arg0")
+                    .trim_start_matches('?')
+                    .trim_start_matches(": ")
+                    .to_string();
+            }
+            property.setter = true;
+            return
+        }
+        if export.method {
+            let f = self.generate_function("", name, &wasm_name, true, export);
+            let class = self.cx.exported_classes.entry(class.to_string())
+                .or_insert(ExportedClass::default());
+            class.methods = true;
+            class.contents.push_str(&f.js);
+            class.contents.push_str("\n");
+            class.typescript.push_str(&format_doc_comments(&export.comments));
+            class.typescript.push_str(&f.ts);
+            class.typescript.push_str("\n");
+            return
+        }
+
+        // Static members are also kept around as members of an object
+        // literal in case the class ends up exported as a namespace.
+        let (js, ts, namespace_ts) = if export.constant {
+            let f = self.generate_function("get", name, &wasm_name, false, export);
+            // Getters can't be declared in TypeScript, so describe the
+            // constant as a read-only property of the return type instead.
+            (f.js,
+             format!("readonly {}: {};", name, f.ts_ret),
+             format!("const {}: {};", name, f.ts_ret))
         } else {
-            self.generate_function(
-                "static",
-                &shared::struct_function_export_name(class, &export.function.name),
-                false,
-                &export.function,
-            )
+            let f = self.generate_function("", name, &wasm_name, false, export);
+            let ts = f.ts.trim_start().to_string();
+            let namespace_ts = format!("function {}", ts);
+            (f.js.trim_start().to_string(), ts, namespace_ts)
         };
+        let comments = format_doc_comments(&export.comments);
         let class = self.cx.exported_classes.entry(class.to_string())
             .or_insert(ExportedClass::default());
-        class.contents.push_str(&js);
-        class.contents.push_str("\n");
-        class.typescript.push_str(&ts);
-        class.typescript.push_str("\n");
+        class.contents.push_str(&format!("static {}\n", js));
+        class.typescript.push_str(&comments);
+        class.typescript.push_str(&format!("static {}\n", ts));
+        class.statics.push(js);
+        class.statics_typescript.push_str(&comments);
+        class.statics_typescript.push_str(&namespace_ts);
+        class.statics_typescript.push_str("\n");
     }
 
     fn generate_function(&mut self,
                          prefix: &str,
+                         js_name: &str,
                          wasm_name: &str,
                          is_method: bool,
-                         function: &shared::Function) -> (String, String) {
-        let mut dst = format!("{}(", function.name);
-        let mut dst_ts = format!("{}(", function.name);
+                         export: &shared::Export) -> GeneratedFunction {
+        if export.readable_stream || export.stream_sink {
+            return self.generate_stream_function(prefix, js_name, wasm_name, is_method, export)
+        }
+        let function = &export.function;
+        let mut dst = format!("{}(", js_name);
+        let mut dst_ts = format!("{}(", js_name);
         let mut passed_args = String::new();
         let mut arg_conversions = String::new();
         let mut destructors = String::new();
+        let mut blobs = Vec::new();
 
         if is_method {
             passed_args.push_str("this.ptr");
         }
 
+        let required = function.arguments.len() - export.optional_args;
         for (i, arg) in function.arguments.iter().enumerate() {
             let name = format!("arg{}", i);
             if i > 0 {
@@ -716,12 +2768,131 @@ impl<'a, 'b> SubContext<'a, 'b> {
                 if passed_args.len() > 0 {
                     passed_args.push_str(", ");
                 }
-                passed_args.push_str(arg);
-            };
+                passed_args.push_str(arg);
+            };
+            // Optional arguments are preceded by a flag saying whether
+            // they're present, with `undefined` and `null` both meaning
+            // `None`. Trailing ones may be left out entirely.
+            if function.optional.contains(&i) {
+                if i >= required {
+                    dst_ts.push_str("?");
+                }
+                // Missing strings are passed as a null pointer instead.
+                if *arg == shared::TYPE_STRING || *arg == shared::TYPE_BORROWED_STR {
+                    if i >= required {
+                        dst_ts.push_str(": string | null");
+                    } else {
+                        dst_ts.push_str(": string | undefined");
+                    }
+                    self.cx.expose_pass_string_to_wasm();
+                    arg_conversions.push_str(&format!("\
+                        const [ptr{i}, len{i}] = {arg} == null ? [0, 0] : passStringToWasm({arg});
+                    ", i = i, arg = name));
+                    pass(&format!("ptr{}", i));
+                    pass(&format!("len{}", i));
+                    if *arg == shared::TYPE_BORROWED_STR {
+                        destructors.push_str(&format!("\n\
+                            if (ptr{i} !== 0)\n\
+                                wasm.__wbindgen_free(ptr{i}, len{i}, 1);\n\
+                        ", i = i));
+                        self.cx.required_internal_exports.insert("__wbindgen_free");
+                    }
+                    continue
+                }
+                pass(&format!("{} == null ? 0 : 1", name));
+                let (ts, value) = match *arg {
+                    shared::TYPE_NUMBER => {
+                        if self.cx.config.debug_assertions {
+                            self.cx.expose_assert_num();
+                            arg_conversions.push_str(&format!("\
+                                if ({0} != null)
+                                    _assertNum({0});
+                            ", name));
+                        }
+                        (String::from("number"), name.clone())
+                    }
+                    shared::TYPE_BOOLEAN => {
+                        (String::from("boolean"), format!("{} ? 1 : 0", name))
+                    }
+                    shared::TYPE_CHAR => {
+                        if self.cx.config.debug_assertions {
+                            self.cx.expose_assert_char();
+                            arg_conversions.push_str(&format!("\
+                                if ({0} != null)
+                                    _assertChar({0});
+                            ", name));
+                        }
+                        (String::from("string"), format!("{}.codePointAt(0)", name))
+                    }
+                    shared::TYPE_DATE => {
+                        if self.cx.config.debug_assertions {
+                            self.cx.expose_assert_date();
+                            arg_conversions.push_str(&format!("\
+                                if ({0} != null)
+                                    _assertDate({0});
+                            ", name));
+                        }
+                        (String::from("Date"), format!("{}.getTime()", name))
+                    }
+                    shared::TYPE_JS_OWNED => {
+                        self.cx.expose_add_heap_object();
+                        (String::from("any"), format!("addHeapObject({})", name))
+                    }
+                    e if self.cx.string_enums.contains_key(&e) => {
+                        let s = self.cx.string_enums[&e].clone();
+                        self.cx.expose_enum_index();
+                        let value = format!("enumIndex(__wbg_{0}_values, {1}, '{0}')",
+                                            s, name);
+                        (s, value)
+                    }
+                    e if self.cx.enums.contains_key(&e) => {
+                        (self.cx.enums[&e].clone(), name.clone())
+                    }
+                    e if self.cx.tagged_enums.contains_key(&e) => {
+                        self.cx.expose_add_heap_object();
+                        (self.cx.tagged_enums[&e].clone(),
+                         format!("addHeapObject({})", name))
+                    }
+                    // Ownership moves into Rust, as for any other argument
+                    // of an exported class's type.
+                    custom if (custom as u32) & shared::TYPE_CUSTOM_REF_FLAG == 0 &&
+                        self.cx.custom_type_names.contains_key(&custom) =>
+                    {
+                        let s = self.cx.custom_type_names[&custom].clone();
+                        if self.cx.config.debug_assertions {
+                            self.cx.expose_assert_class();
+                            arg_conversions.push_str(&format!("\
+                                if ({arg} != null)
+                                    _assertClass({arg}, {struct_});
+                            ", arg = name, struct_ = s));
+                        }
+                        arg_conversions.push_str(&format!("\
+                            const ptr{i} = {arg} == null ? 0 : {arg}.ptr;
+                            if ({arg} != null)
+                                {arg}.ptr = 0;
+                        ", i = i, arg = name));
+                        (s, format!("ptr{}", i))
+                    }
+                    _ => unreachable!(),
+                };
+                if i >= required {
+                    dst_ts.push_str(&format!(": {} | null", ts));
+                } else {
+                    dst_ts.push_str(&format!(": {} | undefined", ts));
+                }
+                pass(&format!("{} == null ? 0 : {}", name, value));
+                continue
+            }
+            if let Some(hook) = self.cx.type_hook(*arg) {
+                dst_ts.push_str(": ");
+                dst_ts.push_str(&hook.typescript(*arg));
+                pass(&hook.export_argument(*arg, &name));
+                continue
+            }
             match *arg {
                 shared::TYPE_NUMBER => {
                     dst_ts.push_str(": number");
-                    if self.cx.config.debug {
+                    if self.cx.config.debug_assertions {
                         self.cx.expose_assert_num();
                         arg_conversions.push_str(&format!("_assertNum({});\n", name));
                     }
@@ -729,7 +2900,7 @@ impl<'a, 'b> SubContext<'a, 'b> {
                 }
                 shared::TYPE_BOOLEAN => {
                     dst_ts.push_str(": boolean");
-                    if self.cx.config.debug {
+                    if self.cx.config.debug_assertions {
                         self.cx.expose_assert_bool();
                         arg_conversions.push_str(&format!("\
                             _assertBoolean({name});
@@ -738,22 +2909,170 @@ impl<'a, 'b> SubContext<'a, 'b> {
                     }
                     pass(&format!("arg{i} ? 1 : 0", i = i))
                 }
+                shared::TYPE_CHAR => {
+                    dst_ts.push_str(": string");
+                    if self.cx.config.debug_assertions {
+                        self.cx.expose_assert_char();
+                        arg_conversions.push_str(&format!("_assertChar({});\n", name));
+                    }
+                    pass(&format!("{}.codePointAt(0)", name))
+                }
+                // Dates are passed as milliseconds since the epoch.
+                shared::TYPE_DATE => {
+                    dst_ts.push_str(": Date");
+                    if self.cx.config.debug_assertions {
+                        self.cx.expose_assert_date();
+                        arg_conversions.push_str(&format!("_assertDate({});\n", name));
+                    }
+                    pass(&format!("{}.getTime()", name))
+                }
+                shared::TYPE_U64 |
+                shared::TYPE_I64 => {
+                    dst_ts.push_str(": bigint");
+                    if self.cx.config.debug_assertions {
+                        self.cx.expose_assert_bigint();
+                        arg_conversions.push_str(&format!("_assertBigInt({});\n", name));
+                    }
+                    self.cx.expose_pass_int64_to_wasm();
+                    arg_conversions.push_str(&format!("\
+                        const [lo{i}, hi{i}] = passInt64ToWasm({arg});
+                    ", i = i, arg = name));
+                    pass(&format!("lo{}", i));
+                    pass(&format!("hi{}", i));
+                }
+                shared::TYPE_U128 |
+                shared::TYPE_I128 => {
+                    dst_ts.push_str(": bigint");
+                    if self.cx.config.debug_assertions {
+                        self.cx.expose_assert_bigint();
+                        arg_conversions.push_str(&format!("_assertBigInt({});\n", name));
+                    }
+                    self.cx.expose_pass_int128_to_wasm();
+                    arg_conversions.push_str(&format!("\
+                        const limbs{i} = passInt128ToWasm({arg});
+                    ", i = i, arg = name));
+                    for j in 0..4 {
+                        pass(&format!("limbs{}[{}]", i, j));
+                    }
+                }
                 shared::TYPE_BORROWED_STR |
                 shared::TYPE_STRING => {
                     dst_ts.push_str(": string");
-                    self.cx.expose_pass_string_to_wasm();
+                    let pass_fn = if function.ascii.contains(&i) {
+                        self.cx.expose_pass_ascii_to_wasm();
+                        "passAsciiToWasm"
+                    } else {
+                        self.cx.expose_pass_string_to_wasm();
+                        "passStringToWasm"
+                    };
                     arg_conversions.push_str(&format!("\
-                        const [ptr{i}, len{i}] = passStringToWasm({arg});
-                    ", i = i, arg = name));
+                        const [ptr{i}, len{i}] = {f}({arg});
+                    ", i = i, f = pass_fn, arg = name));
                     pass(&format!("ptr{}", i));
                     pass(&format!("len{}", i));
                     if *arg == shared::TYPE_BORROWED_STR {
                         destructors.push_str(&format!("\n\
-                            wasm.__wbindgen_free(ptr{i}, len{i});\n\
+                            wasm.__wbindgen_free(ptr{i}, len{i}, 1);\n\
                         ", i = i));
                         self.cx.required_internal_exports.insert("__wbindgen_free");
                     }
                 }
+                ty if slice_array(ty).is_some() => {
+                    let SliceArray { array, size, .. } = slice_array(ty).unwrap();
+                    dst_ts.push_str(": ");
+                    if ty == shared::TYPE_SLICE_U8 {
+                        dst_ts.push_str("ArrayBuffer | ");
+                    }
+                    dst_ts.push_str(array);
+                    let pass_fn = self.cx.expose_pass_slice_to_wasm(ty);
+                    arg_conversions.push_str(&format!("\
+                        const [ptr{i}, len{i}] = {f}({arg});
+                    ", i = i, f = pass_fn, arg = name));
+                    pass(&format!("ptr{}", i));
+                    pass(&format!("len{}", i));
+                    destructors.push_str(&format!("\n\
+                        wasm.__wbindgen_free(ptr{i}, len{i} * {size}, {size});\n\
+                    ", i = i, size = size));
+                    self.cx.required_internal_exports.insert("__wbindgen_free");
+                }
+                // Ownership of the copy moves into Rust along with the vector.
+                ty if vector_slice(ty).is_some() => {
+                    let slice = vector_slice(ty).unwrap();
+                    dst_ts.push_str(": ");
+                    dst_ts.push_str(slice_array(slice).unwrap().array);
+                    let pass_fn = self.cx.expose_pass_slice_to_wasm(slice);
+                    arg_conversions.push_str(&format!("\
+                        const [ptr{i}, len{i}] = {f}({arg});
+                    ", i = i, f = pass_fn, arg = name));
+                    pass(&format!("ptr{}", i));
+                    pass(&format!("len{}", i));
+                }
+                shared::TYPE_VECTOR_JSVALUE => {
+                    dst_ts.push_str(": any[]");
+                    self.cx.expose_pass_array_jsvalue_to_wasm();
+                    arg_conversions.push_str(&format!("\
+                        const [ptr{i}, len{i}] = passArrayJsValueToWasm({arg});
+                    ", i = i, arg = name));
+                    pass(&format!("ptr{}", i));
+                    pass(&format!("len{}", i));
+                }
+                // Rust takes ownership of the strings, but not of the list of
+                // pointers to them.
+                shared::TYPE_VECTOR_STRING => {
+                    dst_ts.push_str(": string[]");
+                    self.cx.expose_pass_array_string_to_wasm();
+                    arg_conversions.push_str(&format!("\
+                        const [ptr{i}, len{i}] = passArrayStringToWasm({arg});
+                    ", i = i, arg = name));
+                    pass(&format!("ptr{}", i));
+                    pass(&format!("len{}", i));
+                    destructors.push_str(&format!("\n\
+                        wasm.__wbindgen_free(ptr{i}, len{i} * 8, 4);\n\
+                    ", i = i));
+                    self.cx.required_internal_exports.insert("__wbindgen_free");
+                }
+                // Maps are flattened into a list alternating between keys and
+                // values, which is passed like any other list.
+                shared::TYPE_MAP_STRING => {
+                    dst_ts.push_str(": Map<string, string> | { [key: string]: string }");
+                    self.cx.expose_flatten_map();
+                    self.cx.expose_pass_array_string_to_wasm();
+                    arg_conversions.push_str(&format!("\
+                        const [ptr{i}, len{i}] = passArrayStringToWasm(flattenMap({arg}));
+                    ", i = i, arg = name));
+                    pass(&format!("ptr{}", i));
+                    pass(&format!("len{}", i));
+                    destructors.push_str(&format!("\n\
+                        wasm.__wbindgen_free(ptr{i}, len{i} * 8, 4);\n\
+                    ", i = i));
+                    self.cx.required_internal_exports.insert("__wbindgen_free");
+                }
+                shared::TYPE_MAP_JSVALUE => {
+                    dst_ts.push_str(": Map<string, any> | { [key: string]: any }");
+                    self.cx.expose_flatten_map();
+                    self.cx.expose_pass_array_jsvalue_to_wasm();
+                    arg_conversions.push_str(&format!("\
+                        const [ptr{i}, len{i}] = passArrayJsValueToWasm(flattenMap({arg}));
+                    ", i = i, arg = name));
+                    pass(&format!("ptr{}", i));
+                    pass(&format!("len{}", i));
+                }
+                shared::TYPE_VECTOR_U8 => {
+                    if export.read_blobs {
+                        self.cx.declare_dom_type("Blob");
+                        dst_ts.push_str(": Blob | ArrayBuffer | Uint8Array");
+                        self.cx.expose_read_blob();
+                        blobs.push(name.clone());
+                    } else {
+                        dst_ts.push_str(": ArrayBuffer | Uint8Array");
+                    }
+                    self.cx.expose_pass_array8_to_wasm();
+                    arg_conversions.push_str(&format!("\
+                        const [ptr{i}, len{i}] = passArray8ToWasm({arg});
+                    ", i = i, arg = name));
+                    pass(&format!("ptr{}", i));
+                    pass(&format!("len{}", i));
+                }
                 shared::TYPE_JS_OWNED => {
                     dst_ts.push_str(": any");
                     self.cx.expose_add_heap_object();
@@ -771,10 +3090,32 @@ impl<'a, 'b> SubContext<'a, 'b> {
                     destructors.push_str("stack.pop();\n");
                     pass(&format!("idx{}", i));
                 }
+                e if self.cx.string_enums.contains_key(&e) => {
+                    let s = self.cx.string_enums[&e].clone();
+                    dst_ts.push_str(&format!(": {}", s));
+                    self.cx.expose_enum_index();
+                    pass(&format!("enumIndex(__wbg_{0}_values, {1}, '{0}')", s, name));
+                }
+                e if self.cx.enums.contains_key(&e) => {
+                    dst_ts.push_str(&format!(": {}", self.cx.enums[&e]));
+                    if self.cx.config.debug_assertions {
+                        self.cx.expose_assert_num();
+                        arg_conversions.push_str(&format!("_assertNum({});\n", name));
+                    }
+                    pass(&name)
+                }
+                e if self.cx.tagged_enums.contains_key(&e) => {
+                    dst_ts.push_str(&format!(": {}", self.cx.tagged_enums[&e]));
+                    self.cx.expose_add_heap_object();
+                    arg_conversions.push_str(&format!("\
+                        const idx{i} = addHeapObject({arg});
+                    ", i = i, arg = name));
+                    pass(&format!("idx{}", i));
+                }
                 custom if (custom as u32) & shared::TYPE_CUSTOM_REF_FLAG != 0 => {
                     let s = self.cx.custom_type_names[&custom].clone();
                     dst_ts.push_str(&format!(": {}", s));
-                    if self.cx.config.debug {
+                    if self.cx.config.debug_assertions {
                         self.cx.expose_assert_class();
                         arg_conversions.push_str(&format!("\
                             _assertClass({arg}, {struct_});
@@ -785,7 +3126,7 @@ impl<'a, 'b> SubContext<'a, 'b> {
                 custom => {
                     let s = self.cx.custom_type_names[&custom].clone();
                     dst_ts.push_str(&format!(": {}", s));
-                    if self.cx.config.debug {
+                    if self.cx.config.debug_assertions {
                         self.cx.expose_assert_class();
                         arg_conversions.push_str(&format!("\
                             _assertClass({arg}, {struct_});
@@ -799,9 +3140,56 @@ impl<'a, 'b> SubContext<'a, 'b> {
                 }
             }
         }
+        // Views returned from methods can optionally be narrowed down to a
+        // subarray, which is handy when uploading part of a buffer to WebGL.
+        let view = function.ret.and_then(slice_array).is_some();
+        if view {
+            if function.arguments.len() > 0 {
+                dst.push_str(", ");
+                dst_ts.push_str(", ");
+            }
+            dst.push_str("offset, length");
+            dst_ts.push_str("offset?: number, length?: number");
+        }
         dst.push_str(")");
+        let ts_params = dst_ts[js_name.len() + 1..].to_string();
         dst_ts.push_str(")");
+        let ret_hook = match function.ret {
+            Some(ty) => self.cx.type_hook(ty),
+            None => None,
+        };
+        let ret_ts_start = dst_ts.len();
         let convert_ret = match function.ret {
+            Some(ty) if ret_hook.is_some() => {
+                let hook = ret_hook.unwrap();
+                dst_ts.push_str(": ");
+                dst_ts.push_str(&hook.typescript(ty));
+                format!("return {};", hook.export_return(ty, "ret"))
+            }
+            None if export.tuple_ret.len() > 0 => {
+                self.cx.expose_float64_memory();
+                self.cx.expose_tuple_ret_ptr();
+                let mut types = Vec::new();
+                let mut elems = Vec::new();
+                for (i, ty) in export.tuple_ret.iter().enumerate() {
+                    let elem = format!("getFloat64Memory()[tupleRetPtr() / 8 + {}]", i);
+                    let (ts, elem) = match *ty {
+                        shared::TYPE_NUMBER => (String::from("number"), elem),
+                        shared::TYPE_BOOLEAN => {
+                            (String::from("boolean"), format!("{} != 0", elem))
+                        }
+                        shared::TYPE_CHAR => {
+                            (String::from("string"), format!("String.fromCodePoint({})", elem))
+                        }
+                        e if self.cx.enums.contains_key(&e) => (self.cx.enums[&e].clone(), elem),
+                        _ => unreachable!(),
+                    };
+                    types.push(ts);
+                    elems.push(elem);
+                }
+                dst_ts.push_str(&format!(": [{}]", types.join(", ")));
+                format!("return [{}];", elems.join(", "))
+            }
             None => {
                 dst_ts.push_str(": void");
                 format!("return ret;")
@@ -814,35 +3202,152 @@ impl<'a, 'b> SubContext<'a, 'b> {
                 dst_ts.push_str(": boolean");
                 format!("return ret != 0;")
             }
+            Some(shared::TYPE_CHAR) => {
+                dst_ts.push_str(": string");
+                format!("return String.fromCodePoint(ret);")
+            }
+            Some(shared::TYPE_DATE) => {
+                dst_ts.push_str(": Date");
+                format!("return new Date(ret);")
+            }
+            Some(ty @ shared::TYPE_U64) |
+            Some(ty @ shared::TYPE_I64) => {
+                dst_ts.push_str(": bigint");
+                let get = self.cx.expose_get_int64_from_wasm(ty == shared::TYPE_I64);
+                self.cx.expose_uint32_memory();
+                self.cx.expose_global_argument_ptr();
+                format!("\
+                    return {}(ret, getUint32Memory()[globalArgumentPtr() / 4]);
+                ", get)
+            }
+            // The upper limbs are in the first and last two global argument
+            // slots.
+            Some(ty @ shared::TYPE_U128) |
+            Some(ty @ shared::TYPE_I128) => {
+                dst_ts.push_str(": bigint");
+                let get = self.cx.expose_get_int128_from_wasm(ty == shared::TYPE_I128);
+                self.cx.expose_uint32_memory();
+                self.cx.expose_global_argument_ptr();
+                format!("\
+                    const mem = getUint32Memory();
+                    const slots = globalArgumentPtr() / 4;
+                    return {}(ret, mem[slots], mem[slots + 3], mem[slots + 4]);
+                ", get)
+            }
             Some(shared::TYPE_JS_OWNED) => {
                 dst_ts.push_str(": any");
                 self.cx.expose_take_object();
                 format!("return takeObject(ret);")
             }
+            Some(ty) if slice_array(ty).is_some() => {
+                let SliceArray { array, size, memory, .. } =
+                    self.cx.expose_slice_memory(ty);
+                dst_ts.push_str(": ");
+                dst_ts.push_str(array);
+                self.cx.expose_global_argument_ptr();
+                self.cx.expose_uint32_memory();
+                format!("
+                    const len = getUint32Memory()[globalArgumentPtr() / 4];
+                    const base = ret / {size};
+                    const start = base + (offset === undefined ? 0 : offset);
+                    let end = length === undefined ? base + len : start + length;
+                    if (end > base + len)
+                        end = base + len;
+                    return {memory}().subarray(start, end);
+                ", size = size, memory = memory)
+            }
+            Some(ty) if vector_slice(ty).is_some() => {
+                let slice = vector_slice(ty).unwrap();
+                dst_ts.push_str(": ");
+                dst_ts.push_str(slice_array(slice).unwrap().array);
+                let take = self.cx.expose_take_array_from_wasm(slice);
+                format!("return {}(ret);", take)
+            }
+            Some(shared::TYPE_VECTOR_STRING) => {
+                dst_ts.push_str(": string[]");
+                self.cx.expose_take_array_string_from_wasm();
+                format!("return takeArrayStringFromWasm(ret);")
+            }
+            Some(shared::TYPE_VECTOR_JSVALUE) => {
+                dst_ts.push_str(": any[]");
+                self.cx.expose_take_array_jsvalue_from_wasm();
+                format!("return takeArrayJsValueFromWasm(ret);")
+            }
+            Some(shared::TYPE_MAP_STRING) => {
+                if export.plain_object {
+                    dst_ts.push_str(": { [key: string]: string }");
+                } else {
+                    dst_ts.push_str(": Map<string, string>");
+                }
+                self.cx.expose_take_array_string_from_wasm();
+                self.cx.expose_unflatten_map();
+                format!("return unflattenMap(takeArrayStringFromWasm(ret), {});",
+                        export.plain_object)
+            }
+            Some(shared::TYPE_MAP_JSVALUE) => {
+                if export.plain_object {
+                    dst_ts.push_str(": { [key: string]: any }");
+                } else {
+                    dst_ts.push_str(": Map<string, any>");
+                }
+                self.cx.expose_take_array_jsvalue_from_wasm();
+                self.cx.expose_unflatten_map();
+                format!("return unflattenMap(takeArrayJsValueFromWasm(ret), {});",
+                        export.plain_object)
+            }
+            Some(shared::TYPE_VECTOR_U8) => {
+                self.cx.expose_take_array_u8_from_wasm();
+                let ret = if let Some(ref mime) = export.blob {
+                    self.cx.declare_dom_type("Blob");
+                    dst_ts.push_str(": Blob");
+                    format!("new Blob([realRet], {{ type: {:?} }})", mime)
+                } else if export.array_buffer {
+                    dst_ts.push_str(": ArrayBuffer");
+                    format!("realRet.buffer")
+                } else {
+                    dst_ts.push_str(": Uint8Array");
+                    format!("realRet")
+                };
+                format!("
+                    const realRet = takeArrayU8FromWasm(ret);
+                    return {};
+                ", ret)
+            }
             Some(shared::TYPE_STRING) => {
                 dst_ts.push_str(": string");
-                self.cx.expose_get_string_from_wasm();
-                self.cx.required_internal_exports.insert("__wbindgen_boxed_str_ptr");
-                self.cx.required_internal_exports.insert("__wbindgen_boxed_str_len");
-                self.cx.required_internal_exports.insert("__wbindgen_boxed_str_free");
-                format!("
-                    const ptr = wasm.__wbindgen_boxed_str_ptr(ret);
-                    const len = wasm.__wbindgen_boxed_str_len(ret);
-                    const realRet = getStringFromWasm(ptr, len);
-                    wasm.__wbindgen_boxed_str_free(ret);
-                    return realRet;
-                ")
+                self.cx.expose_take_string_from_wasm();
+                format!("return takeStringFromWasm(ret);")
             }
             Some(shared::TYPE_JS_REF) |
-            Some(shared::TYPE_BORROWED_STR) => panic!(),
-            Some(t) if (t as u32) & shared::TYPE_CUSTOM_REF_FLAG != 0 => panic!(),
+            Some(shared::TYPE_BORROWED_STR) |
+            Some(shared::TYPE_STATIC_STR) => unreachable!(),
+            Some(ref e) if self.cx.string_enums.contains_key(e) => {
+                let name = &self.cx.string_enums[e];
+                dst_ts.push_str(": ");
+                dst_ts.push_str(name);
+                format!("return __wbg_{}_values[ret];", name)
+            }
+            Some(ref e) if self.cx.enums.contains_key(e) => {
+                dst_ts.push_str(": ");
+                dst_ts.push_str(&self.cx.enums[e]);
+                format!("return ret;")
+            }
+            Some(ref e) if self.cx.tagged_enums.contains_key(e) => {
+                dst_ts.push_str(": ");
+                dst_ts.push_str(&self.cx.tagged_enums[e]);
+                self.cx.expose_take_object();
+                format!("return takeObject(ret);")
+            }
+            Some(t) if (t as u32) & shared::TYPE_CUSTOM_REF_FLAG != 0 => unreachable!(),
             Some(ref custom) => {
                 let name = &self.cx.custom_type_names[custom];
                 dst_ts.push_str(": ");
                 dst_ts.push_str(name);
-                if self.cx.config.debug {
+                if export.constructor {
+                    format!("this.ptr = ret;")
+                } else if self.cx.wraps_instances(name) {
                     format!("\
-                        return new {name}(ret, token);
+                        return __wbg_wrap_{name}(ret);
                     ", name = name)
                 } else {
                     format!("\
@@ -851,20 +3356,77 @@ impl<'a, 'b> SubContext<'a, 'b> {
                 }
             }
         };
+        // Whether an optional value was returned is flagged through the
+        // first global argument slot, or for strings and classes by a null
+        // pointer.
+        let returns_class = match function.ret {
+            Some(ty) => self.cx.custom_type_names.contains_key(&ty),
+            None => false,
+        };
+        let convert_ret = if export.optional_ret &&
+            (function.ret == Some(shared::TYPE_STRING) || returns_class)
+        {
+            dst_ts.push_str(" | undefined");
+            format!("\
+                if (ret === 0)
+                    return undefined;
+                {}
+            ", convert_ret)
+        } else if export.optional_ret {
+            dst_ts.push_str(" | undefined");
+            self.cx.expose_uint32_memory();
+            self.cx.expose_global_argument_ptr();
+            format!("\
+                if (getUint32Memory()[globalArgumentPtr() / 4] === 0)
+                    return undefined;
+                {}
+            ", convert_ret)
+        } else {
+            convert_ret
+        };
+        // Reading a `Blob` is asynchronous, so if any of the byte arguments
+        // may be one the whole call is deferred until they've all been read
+        // and the wrapper returns a promise instead.
+        if export.read_blobs {
+            let ret_ts = dst_ts.split_off(ret_ts_start);
+            dst_ts.push_str(&format!(": Promise<{}>", &ret_ts[2..]));
+        }
+        let ts_ret = dst_ts[ret_ts_start + 2..].to_string();
         dst_ts.push_str(";");
-        dst.push_str(" {\n        ");
-        dst.push_str(&arg_conversions);
+        // Errors are flagged through the global argument slots, in which case
+        // the returned value is just a placeholder.
+        let convert_ret = match export.error {
+            Some(err) => {
+                self.cx.expose_uint32_memory();
+                self.cx.expose_global_argument_ptr();
+                self.cx.expose_take_object();
+                let thrown = match self.cx.tagged_enums.get(&err) {
+                    Some(name) => format!("__wbg_error_{}(takeObject(err))", name),
+                    None => format!("takeObject(err)"),
+                };
+                format!("\
+                    if (getUint32Memory()[globalArgumentPtr() / 4 + 1] !== 0) {{
+                        const err = getUint32Memory()[globalArgumentPtr() / 4 + 2];
+                        throw {};
+                    }}
+                    {}
+                ", thrown, convert_ret)
+            }
+            None => convert_ret,
+        };
+        let mut body = String::new();
+        body.push_str(&arg_conversions);
         if destructors.len() == 0 {
-            dst.push_str(&format!("\
+            body.push_str(&format!("\
                 const ret = wasm.{}({passed});
                 {convert_ret}
             ",
-                f = wasm_name,
+                wasm_name,
                 passed = passed_args,
                 convert_ret = convert_ret,
             ));
         } else {
-            dst.push_str(&format!("\
+            body.push_str(&format!("\
                 try {{
                     const ret = wasm.{f}({passed});
                     {convert_ret}
@@ -878,18 +3440,132 @@ impl<'a, 'b> SubContext<'a, 'b> {
                 convert_ret = convert_ret,
             ));
         }
+        dst.push_str(" {\n        ");
+        if export.read_blobs {
+            dst.push_str(&format!("\
+                return Promise.all([{reads}]).then(([{args}]) => {{
+                    {body}
+                }});
+            ",
+                reads = blobs.iter()
+                    .map(|b| format!("readBlob({})", b))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                args = blobs.join(", "),
+                body = body,
+            ));
+        } else {
+            dst.push_str(&body);
+        }
         dst.push_str("}");
-        (format!("{} {}", prefix, dst), format!("{} {}", prefix, dst_ts))
+        GeneratedFunction {
+            js: format!("{} {}", prefix, dst),
+            ts: format!("{} {}", prefix, dst_ts),
+            ts_params: ts_params,
+            ts_ret: ts_ret,
+        }
+    }
+
+    fn generate_stream_function(&mut self,
+                                prefix: &str,
+                                js_name: &str,
+                                wasm_name: &str,
+                                is_method: bool,
+                                export: &shared::Export) -> GeneratedFunction {
+        let receiver = if is_method { "this.ptr" } else { "" };
+        if export.readable_stream {
+            self.cx.expose_take_array_u8_from_wasm();
+            // An empty chunk from Rust marks the end of the stream
+            let js = format!("\
+                {prefix} {name}() {{
+                    return new ReadableStream({{
+                        pull: controller => {{
+                            const chunk = takeArrayU8FromWasm(wasm.{f}({receiver}));
+                            if (chunk.length === 0)
+                                controller.close();
+                            else
+                                controller.enqueue(chunk);
+                        }},
+                    }});
+                }}
+            ",
+                prefix = prefix,
+                name = js_name,
+                f = wasm_name,
+                receiver = receiver,
+            );
+            self.cx.declare_dom_type("ReadableStream");
+            let ts = format!("{} {}(): ReadableStream<Uint8Array>;",
+                             prefix, js_name);
+            GeneratedFunction {
+                js: js,
+                ts: ts,
+                ts_params: String::new(),
+                ts_ret: String::from("ReadableStream<Uint8Array>"),
+            }
+        } else {
+            self.cx.expose_pass_array8_to_wasm();
+            let js = format!("\
+                {prefix} {name}(stream) {{
+                    const reader = stream.getReader();
+                    const pump = () => reader.read().then(({{ done, value }}) => {{
+                        if (done)
+                            return;
+                        const [ptr, len] = passArray8ToWasm(value);
+                        wasm.{f}({receiver}{comma}ptr, len);
+                        return pump();
+                    }});
+                    return pump();
+                }}
+            ",
+                prefix = prefix,
+                name = js_name,
+                f = wasm_name,
+                receiver = receiver,
+                comma = if is_method { ", " } else { "" },
+            );
+            self.cx.declare_dom_type("ReadableStream");
+            let ts = format!("{} {}(stream: ReadableStream<Uint8Array>): Promise<void>;",
+                             prefix, js_name);
+            GeneratedFunction {
+                js: js,
+                ts: ts,
+                ts_params: String::from("stream: ReadableStream<Uint8Array>"),
+                ts_ret: String::from("Promise<void>"),
+            }
+        }
+    }
+
+    /// Exposes and returns the function used to decode the string argument
+    /// `i` of an import.
+    fn get_string_fn(&mut self, import: &shared::Import, i: usize) -> &'static str {
+        if import.function.ascii.contains(&i) {
+            self.cx.expose_get_ascii_from_wasm();
+            "getAsciiFromWasm"
+        } else {
+            self.cx.expose_get_string_from_wasm();
+            "getStringFromWasm"
+        }
     }
 
     pub fn generate_import(&mut self, import: &shared::Import) {
         if let Some(ref module) = import.module {
-            let name_to_import = import.class.as_ref().unwrap_or(&import.function.name);
+            let name_to_import = match import.global {
+                Some(ref path) => path.split('.').next().unwrap(),
+                None => &import.class.as_ref().unwrap_or(&import.function.name)[..],
+            };
 
-            if self.cx.imported_names.insert(name_to_import.clone()) {
-                self.cx.imports.push_str(&format!("
-                    import {{ {} }} from '{}';
-                ", name_to_import, module));
+            let catch_class = match import.catch_class {
+                Some(ref class) if !import.catch_global => Some(&class[..]),
+                _ => None,
+            };
+            let names = Some(name_to_import).into_iter().chain(catch_class);
+            for name in names {
+                if self.cx.imported_names.insert(name.to_string()) {
+                    self.cx.imports.push_str(&format!("
+                        import {{ {} }} from '{}';
+                    ", name, module));
+                }
             }
         }
 
@@ -912,6 +3588,57 @@ impl<'a, 'b> SubContext<'a, 'b> {
         let mut extra = String::new();
 
         for (i, arg) in import.function.arguments.iter().enumerate() {
+            // Optional arguments are preceded by a flag saying whether
+            // they're present, and are `undefined` if not.
+            if import.function.optional.contains(&i) {
+                // Missing strings are passed as a null pointer instead.
+                if *arg == shared::TYPE_STRING || *arg == shared::TYPE_BORROWED_STR {
+                    let get = self.get_string_fn(import, i);
+                    abi_args.push(format!("ptr{}", i));
+                    abi_args.push(format!("len{}", i));
+                    extra.push_str(&format!("
+                        let arg{1} = ptr{1} === 0 ? undefined : {0}(ptr{1}, len{1});
+                    ", get, i));
+                    if *arg == shared::TYPE_STRING {
+                        extra.push_str(&format!("
+                            if (ptr{0} !== 0)
+                                wasm.__wbindgen_free(ptr{0}, len{0}, 1);
+                        ", i));
+                        self.cx.required_internal_exports.insert("__wbindgen_free");
+                    }
+                    invoc_args.push(format!("arg{}", i));
+                    continue
+                }
+                let value = match *arg {
+                    shared::TYPE_NUMBER => format!("arg{}", i),
+                    shared::TYPE_BOOLEAN => format!("arg{} != 0", i),
+                    shared::TYPE_CHAR => format!("String.fromCodePoint(arg{})", i),
+                    shared::TYPE_DATE => format!("new Date(arg{})", i),
+                    shared::TYPE_JS_OWNED => {
+                        self.cx.expose_take_object();
+                        format!("takeObject(arg{})", i)
+                    }
+                    e if self.cx.string_enums.contains_key(&e) => {
+                        let name = &self.cx.string_enums[&e];
+                        format!("__wbg_{}_values[arg{}]", name, i)
+                    }
+                    e if self.cx.enums.contains_key(&e) => format!("arg{}", i),
+                    e if self.cx.tagged_enums.contains_key(&e) => {
+                        self.cx.expose_take_object();
+                        format!("takeObject(arg{})", i)
+                    }
+                    _ => unreachable!(),
+                };
+                invoc_args.push(format!("some{} === 0 ? undefined : {}", i, value));
+                abi_args.push(format!("some{}", i));
+                abi_args.push(format!("arg{}", i));
+                continue
+            }
+            if let Some(hook) = self.cx.type_hook(*arg) {
+                invoc_args.push(hook.import_argument(*arg, &format!("arg{}", i)));
+                abi_args.push(format!("arg{}", i));
+                continue
+            }
             match *arg {
                 shared::TYPE_NUMBER => {
                     invoc_args.push(format!("arg{}", i));
@@ -921,20 +3648,50 @@ impl<'a, 'b> SubContext<'a, 'b> {
                     invoc_args.push(format!("arg{} != 0", i));
                     abi_args.push(format!("arg{}", i));
                 }
+                shared::TYPE_CHAR => {
+                    invoc_args.push(format!("String.fromCodePoint(arg{})", i));
+                    abi_args.push(format!("arg{}", i));
+                }
+                shared::TYPE_DATE => {
+                    invoc_args.push(format!("new Date(arg{})", i));
+                    abi_args.push(format!("arg{}", i));
+                }
+                shared::TYPE_U64 |
+                shared::TYPE_I64 => {
+                    let get = self.cx.expose_get_int64_from_wasm(*arg == shared::TYPE_I64);
+                    invoc_args.push(format!("{}(lo{1}, hi{1})", get, i));
+                    abi_args.push(format!("lo{}", i));
+                    abi_args.push(format!("hi{}", i));
+                }
+                shared::TYPE_U128 |
+                shared::TYPE_I128 => {
+                    let get = self.cx.expose_get_int128_from_wasm(*arg == shared::TYPE_I128);
+                    let limbs = (0..4)
+                        .map(|j| format!("limb{}_{}", i, j))
+                        .collect::<Vec<_>>();
+                    invoc_args.push(format!("{}({})", get, limbs.join(", ")));
+                    abi_args.extend(limbs);
+                }
                 shared::TYPE_BORROWED_STR => {
-                    self.cx.expose_get_string_from_wasm();
-                    invoc_args.push(format!("getStringFromWasm(ptr{0}, len{0})", i));
+                    let get = self.get_string_fn(import, i);
+                    invoc_args.push(format!("{}(ptr{1}, len{1})", get, i));
+                    abi_args.push(format!("ptr{}", i));
+                    abi_args.push(format!("len{}", i));
+                }
+                shared::TYPE_STATIC_STR => {
+                    self.cx.expose_get_static_string_from_wasm();
+                    invoc_args.push(format!("getStaticStringFromWasm(ptr{0}, len{0})", i));
                     abi_args.push(format!("ptr{}", i));
                     abi_args.push(format!("len{}", i));
                 }
                 shared::TYPE_STRING => {
-                    self.cx.expose_get_string_from_wasm();
+                    let get = self.get_string_fn(import, i);
                     abi_args.push(format!("ptr{}", i));
                     abi_args.push(format!("len{}", i));
                     extra.push_str(&format!("
-                        let arg{0} = getStringFromWasm(ptr{0}, len{0});
-                        wasm.__wbindgen_free(ptr{0}, len{0});
-                    ", i));
+                        let arg{1} = {0}(ptr{1}, len{1});
+                        wasm.__wbindgen_free(ptr{1}, len{1}, 1);
+                    ", get, i));
                     invoc_args.push(format!("arg{}", i));
                     self.cx.required_internal_exports.insert("__wbindgen_free");
                 }
@@ -948,29 +3705,115 @@ impl<'a, 'b> SubContext<'a, 'b> {
                     invoc_args.push(format!("getObject(arg{})", i));
                     abi_args.push(format!("arg{}", i));
                 }
-                _ => {
-                    panic!("unsupported type in import");
+                e if self.cx.string_enums.contains_key(&e) => {
+                    let name = &self.cx.string_enums[&e];
+                    invoc_args.push(format!("__wbg_{}_values[arg{}]", name, i));
+                    abi_args.push(format!("arg{}", i));
+                }
+                e if self.cx.enums.contains_key(&e) => {
+                    invoc_args.push(format!("arg{}", i));
+                    abi_args.push(format!("arg{}", i));
+                }
+                e if self.cx.tagged_enums.contains_key(&e) => {
+                    self.cx.expose_take_object();
+                    invoc_args.push(format!("takeObject(arg{})", i));
+                    abi_args.push(format!("arg{}", i));
                 }
+                _ => unreachable!(),
             }
         }
 
         let invoc_args = invoc_args.join(", ");
+        let shim = name;
         let name = &import.function.name;
-        let invoc = match import.class {
+        // Methods are looked up once when the module is loaded rather than
+        // walking the prototype chain on every call.
+        let target = format!("__wbg_{}_target", shim);
+        let mut hoisted = String::new();
+        let mut invoc = match import.class {
             Some(ref class) if import.method => {
-                format!("{}.prototype.{}.call({})", class, name, invoc_args)
+                hoisted = format!("const {} = {}.prototype.{};\n",
+                                  target, class, name);
+                format!("{}.call({})", target, invoc_args)
             }
             Some(ref class) if import.js_new => {
                 format!("new {}({})", class, invoc_args)
             }
             Some(ref class) => {
-                format!("{}.{}({})", class, name, invoc_args)
+                hoisted = format!("const {} = {}.{};\n", target, class, name);
+                let args = if invoc_args.len() > 0 {
+                    format!("{}, {}", class, invoc_args)
+                } else {
+                    class.clone()
+                };
+                format!("{}.call({})", target, args)
+            }
+            None if import.global.is_some() => {
+                let path = import.global.as_ref().unwrap();
+                if import.function.arguments.len() == 0 {
+                    path.clone()
+                } else {
+                    format!("{} = {}", path, invoc_args)
+                }
             }
+            None if import.this_arg => format!("{}.call({})", name, invoc_args),
             None => format!("{}({})", name, invoc_args),
         };
+        // Nullish results of optional imports are flagged through an extra
+        // out-pointer instead of going through the usual conversion.
+        let mut check_none = String::new();
+        if import.optional_ret {
+            self.cx.expose_uint32_memory();
+            check_none = format!("
+                const val = {};
+                if (val === undefined || val === null) {{
+                    getUint32Memory()[noneptr / 4] = 1;
+                    return 0;
+                }}
+            ", invoc);
+            invoc = String::from("val");
+        }
+        let ret_hook = match import.function.ret {
+            Some(ty) => self.cx.type_hook(ty),
+            None => None,
+        };
         let invoc = match import.function.ret {
+            Some(ty) if ret_hook.is_some() => {
+                format!("return {};", ret_hook.unwrap().import_return(ty, &invoc))
+            }
             Some(shared::TYPE_NUMBER) => format!("return {};", invoc),
+            Some(ref e) if self.cx.enums.contains_key(e) => format!("return {};", invoc),
+            Some(ref e) if self.cx.tagged_enums.contains_key(e) => {
+                self.cx.expose_add_heap_object();
+                format!("return addHeapObject({});", invoc)
+            }
             Some(shared::TYPE_BOOLEAN) => format!("return {} ? 1 : 0;", invoc),
+            Some(shared::TYPE_CHAR) => format!("return {}.codePointAt(0);", invoc),
+            Some(shared::TYPE_DATE) => format!("return {}.getTime();", invoc),
+            Some(shared::TYPE_U64) |
+            Some(shared::TYPE_I64) => {
+                self.cx.expose_pass_int64_to_wasm();
+                self.cx.expose_uint32_memory();
+                abi_args.push("wasmretptr".to_string());
+                format!("
+                    const [retlo, rethi] = passInt64ToWasm({});
+                    getUint32Memory()[wasmretptr / 4] = rethi;
+                    return retlo;
+                ", invoc)
+            }
+            Some(shared::TYPE_U128) |
+            Some(shared::TYPE_I128) => {
+                self.cx.expose_pass_int128_to_wasm();
+                self.cx.expose_uint32_memory();
+                abi_args.push("wasmretptr".to_string());
+                format!("
+                    const limbs = passInt128ToWasm({});
+                    const mem = getUint32Memory();
+                    for (let i = 1; i < 4; i++)
+                        mem[wasmretptr / 4 + i - 1] = limbs[i];
+                    return limbs[0];
+                ", invoc)
+            }
             Some(shared::TYPE_JS_OWNED) => {
                 self.cx.expose_add_heap_object();
                 format!("return addHeapObject({});", invoc)
@@ -985,23 +3828,40 @@ impl<'a, 'b> SubContext<'a, 'b> {
                     return retptr;
                 ", invoc)
             }
+            Some(ref e) if self.cx.string_enums.contains_key(e) => {
+                self.cx.expose_enum_index();
+                let name = &self.cx.string_enums[e];
+                format!("return enumIndex(__wbg_{0}_values, {1}, '{0}');", name, invoc)
+            }
             None => invoc,
-            _ => unimplemented!(),
+            _ => unreachable!(),
+        };
+        let invoc = if import.optional_ret {
+            abi_args.push("noneptr".to_string());
+            format!("{}{}", check_none, invoc)
+        } else {
+            invoc
         };
 
         let invoc = if import.catch {
             self.cx.expose_uint32_memory();
             self.cx.expose_add_heap_object();
             abi_args.push("exnptr".to_string());
+            // Exceptions that aren't of the declared error type are flagged
+            // with a `2` instead, for Rust to throw again.
+            let discriminant = match import.catch_class {
+                Some(ref class) => format!("e instanceof {} ? 1 : 2", class),
+                None => String::from("1"),
+            };
             format!("
                 try {{
                     {}
                 }} catch (e) {{
                     const view = getUint32Memory();
-                    view[exnptr / 4] = 1;
+                    view[exnptr / 4] = {};
                     view[exnptr / 4 + 1] = addHeapObject(e);
                 }}
-            ", invoc)
+            ", invoc, discriminant)
         } else {
             invoc
         };
@@ -1011,8 +3871,152 @@ impl<'a, 'b> SubContext<'a, 'b> {
         dst.push_str(&extra);
         dst.push_str(&format!("{}\n}}", invoc));
 
+        self.cx.globals.push_str(&hoisted);
         self.cx.globals.push_str("export ");
         self.cx.globals.push_str(&dst);
         self.cx.globals.push_str("\n");
     }
 }
+
+/// Formats Rust doc comments as a `/** ... */` block for the `.d.ts` file.
+/// How slices of one of Rust's numeric types are represented in JS.
+/// The JS and TypeScript generated for an exported function.
+struct GeneratedFunction {
+    js: String,
+    /// The whole TypeScript declaration, such as
+    /// `function foo(arg0: number): string;`.
+    ts: String,
+    /// The TypeScript for the arguments, without the parentheses.
+    ts_params: String,
+    /// The TypeScript for the return type.
+    ts_ret: String,
+}
+
+struct SliceArray {
+    /// The typed array the slice shows up as.
+    array: &'static str,
+    /// The size of each element in bytes.
+    size: usize,
+    /// The function returning all of wasm memory as an `array`.
+    memory: &'static str,
+    /// The function copying an `array` into a fresh allocation in wasm.
+    pass: &'static str,
+    /// The function copying a returned vector out of wasm and freeing it.
+    take: &'static str,
+}
+
+/// Returns how the numeric slice type `ty` is represented in JS, or `None` if
+/// `ty` isn't a slice of numbers.
+fn slice_array(ty: shared::Type) -> Option<SliceArray> {
+    Some(match ty {
+        shared::TYPE_SLICE_I8 => SliceArray {
+            array: "Int8Array",
+            size: 1,
+            memory: "getInt8Memory",
+            pass: "passSliceI8ToWasm",
+            take: "takeArrayI8FromWasm",
+        },
+        shared::TYPE_SLICE_U8 => SliceArray {
+            array: "Uint8Array",
+            size: 1,
+            memory: "getUint8Memory",
+            pass: "passSliceU8ToWasm",
+            take: "takeArrayU8FromWasm",
+        },
+        shared::TYPE_SLICE_U8_CLAMPED => SliceArray {
+            array: "Uint8ClampedArray",
+            size: 1,
+            memory: "getUint8ClampedMemory",
+            pass: "passSliceU8ClampedToWasm",
+            take: "takeArrayU8ClampedFromWasm",
+        },
+        shared::TYPE_SLICE_I16 => SliceArray {
+            array: "Int16Array",
+            size: 2,
+            memory: "getInt16Memory",
+            pass: "passSliceI16ToWasm",
+            take: "takeArrayI16FromWasm",
+        },
+        shared::TYPE_SLICE_U16 => SliceArray {
+            array: "Uint16Array",
+            size: 2,
+            memory: "getUint16Memory",
+            pass: "passSliceU16ToWasm",
+            take: "takeArrayU16FromWasm",
+        },
+        shared::TYPE_SLICE_I32 => SliceArray {
+            array: "Int32Array",
+            size: 4,
+            memory: "getInt32Memory",
+            pass: "passSliceI32ToWasm",
+            take: "takeArrayI32FromWasm",
+        },
+        shared::TYPE_SLICE_U32 => SliceArray {
+            array: "Uint32Array",
+            size: 4,
+            memory: "getUint32Memory",
+            pass: "passSliceU32ToWasm",
+            take: "takeArrayU32FromWasm",
+        },
+        shared::TYPE_SLICE_F32 => SliceArray {
+            array: "Float32Array",
+            size: 4,
+            memory: "getFloat32Memory",
+            pass: "passSliceF32ToWasm",
+            take: "takeArrayF32FromWasm",
+        },
+        shared::TYPE_SLICE_F64 => SliceArray {
+            array: "Float64Array",
+            size: 8,
+            memory: "getFloat64Memory",
+            pass: "passSliceF64ToWasm",
+            take: "takeArrayF64FromWasm",
+        },
+        shared::TYPE_SLICE_I64 => SliceArray {
+            array: "BigInt64Array",
+            size: 8,
+            memory: "getBigInt64Memory",
+            pass: "passSliceI64ToWasm",
+            take: "takeArrayI64FromWasm",
+        },
+        shared::TYPE_SLICE_U64 => SliceArray {
+            array: "BigUint64Array",
+            size: 8,
+            memory: "getBigUint64Memory",
+            pass: "passSliceU64ToWasm",
+            take: "takeArrayU64FromWasm",
+        },
+        _ => return None,
+    })
+}
+
+/// Returns the slice type corresponding to the numeric vector type `ty`, or
+/// `None` if `ty` isn't a vector of numbers other than bytes, which have
+/// their own handling.
+fn vector_slice(ty: shared::Type) -> Option<shared::Type> {
+    Some(match ty {
+        shared::TYPE_VECTOR_I8 => shared::TYPE_SLICE_I8,
+        shared::TYPE_VECTOR_U8_CLAMPED => shared::TYPE_SLICE_U8_CLAMPED,
+        shared::TYPE_VECTOR_I16 => shared::TYPE_SLICE_I16,
+        shared::TYPE_VECTOR_U16 => shared::TYPE_SLICE_U16,
+        shared::TYPE_VECTOR_I32 => shared::TYPE_SLICE_I32,
+        shared::TYPE_VECTOR_U32 => shared::TYPE_SLICE_U32,
+        shared::TYPE_VECTOR_F32 => shared::TYPE_SLICE_F32,
+        shared::TYPE_VECTOR_F64 => shared::TYPE_SLICE_F64,
+        shared::TYPE_VECTOR_I64 => shared::TYPE_SLICE_I64,
+        shared::TYPE_VECTOR_U64 => shared::TYPE_SLICE_U64,
+        _ => return None,
+    })
+}
+
+fn format_doc_comments(comments: &[String]) -> String {
+    if comments.len() == 0 {
+        return String::new()
+    }
+    let mut ret = String::from("/**\n");
+    for comment in comments {
+        ret.push_str(&format!("* {}\n", comment.replace("*/", "* /")));
+    }
+    ret.push_str("*/\n");
+    ret
+}
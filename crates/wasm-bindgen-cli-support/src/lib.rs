@@ -1,27 +1,53 @@
 #[macro_use]
 extern crate failure;
-extern crate parity_wasm;
+pub extern crate parity_wasm;
 extern crate wasm_bindgen_shared as shared;
+#[macro_use]
 extern crate serde_json;
 extern crate wasm_gc;
 
 use std::char;
+use std::collections::BTreeSet;
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::slice;
 
 use failure::Error;
 use parity_wasm::elements::*;
 
+mod hooks;
 mod js;
 pub mod wasm2es6js;
 
+pub use hooks::{Intrinsic, Pass, TypeHook};
+
 pub struct Bindgen {
     path: Option<PathBuf>,
     nodejs: bool,
-    debug: bool,
+    debug_assertions: bool,
+    protect_constructors: bool,
     typescript: bool,
+    manifest: bool,
+    check_interface: Option<PathBuf>,
+    slab_initial_size: usize,
+    slab_growth: SlabGrowth,
+    map_heap: bool,
+    test_harness: bool,
+    type_hooks: Vec<Box<dyn TypeHook>>,
+    passes: Vec<Box<dyn Pass>>,
+}
+
+/// How the table of JS objects referenced from wasm grows once every slot in
+/// it is in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlabGrowth {
+    /// Add one slot at a time, the default.
+    Single,
+    /// Add this many slots at a time.
+    Chunk(usize),
+    /// Double the size of the table, adding at least one slot.
+    Double,
 }
 
 impl Bindgen {
@@ -29,8 +55,17 @@ impl Bindgen {
         Bindgen {
             path: None,
             nodejs: false,
-            debug: false,
+            debug_assertions: false,
+            protect_constructors: false,
             typescript: false,
+            manifest: false,
+            check_interface: None,
+            slab_initial_size: 0,
+            slab_growth: SlabGrowth::Single,
+            map_heap: false,
+            test_harness: false,
+            type_hooks: Vec::new(),
+            passes: Vec::new(),
         }
     }
 
@@ -44,8 +79,28 @@ impl Bindgen {
         self
     }
 
+    /// Enables or disables all of the debugging aids in the generated JS.
+    ///
+    /// This is a shorthand for both `debug_assertions` and
+    /// `protect_constructors`.
     pub fn debug(&mut self, debug: bool) -> &mut Bindgen {
-        self.debug = debug;
+        self.debug_assertions = debug;
+        self.protect_constructors = debug;
+        self
+    }
+
+    /// Whether to include runtime assertions in the generated JS, such as
+    /// checking the types of arguments and the consistency of the object
+    /// heap.
+    pub fn debug_assertions(&mut self, debug_assertions: bool) -> &mut Bindgen {
+        self.debug_assertions = debug_assertions;
+        self
+    }
+
+    /// Whether the constructors of exported classes should refuse to be called
+    /// from outside the generated JS.
+    pub fn protect_constructors(&mut self, protect: bool) -> &mut Bindgen {
+        self.protect_constructors = protect;
         self
     }
 
@@ -54,6 +109,82 @@ impl Bindgen {
         self
     }
 
+    /// Whether to write a `*.manifest.json` file next to the JS, listing
+    /// what's exported and imported along with the cargo features which
+    /// enabled any conditionally compiled items.
+    pub fn manifest(&mut self, manifest: bool) -> &mut Bindgen {
+        self.manifest = manifest;
+        self
+    }
+
+    /// Fails generation if the hash of the exported interface differs from
+    /// the one in the file at `path`, as written by `manifest`.
+    ///
+    /// This catches accidental changes to the JS API, such as a renamed
+    /// function or a changed argument type, between releases.
+    pub fn check_interface<P: AsRef<Path>>(&mut self, path: P) -> &mut Bindgen {
+        self.check_interface = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// The number of slots to preallocate in the table of JS objects
+    /// referenced from wasm, defaulting to zero.
+    pub fn slab_initial_size(&mut self, size: usize) -> &mut Bindgen {
+        self.slab_initial_size = size;
+        self
+    }
+
+    /// How the table of JS objects referenced from wasm grows when it's full.
+    ///
+    /// Applications which pass many objects to wasm in bursts may want to grow
+    /// in larger steps to avoid repeatedly resizing the table.
+    pub fn slab_growth(&mut self, growth: SlabGrowth) -> &mut Bindgen {
+        if let SlabGrowth::Chunk(0) = growth {
+            panic!("slab growth chunks must be at least one slot");
+        }
+        self.slab_growth = growth;
+        self
+    }
+
+    /// Whether to keep JS objects referenced from wasm in a `Map` keyed by
+    /// ids which are never reused, instead of the default slab.
+    ///
+    /// This is slower but turns a use-after-free of a `JsValue` into an
+    /// "unknown heap id" error rather than silently reading whichever object
+    /// was allocated into the same slot afterwards.
+    pub fn map_heap(&mut self, map_heap: bool) -> &mut Bindgen {
+        self.map_heap = map_heap;
+        self
+    }
+
+    /// Whether to export an `assertHeapAndStackEmpty` function which throws
+    /// if any JS objects are still referenced from wasm, listing where each
+    /// leaked object was allocated.
+    ///
+    /// This is meant for test harnesses checking for leaks once a test has
+    /// finished, and only has an effect along with `debug_assertions` or
+    /// `map_heap`.
+    pub fn test_harness(&mut self, test_harness: bool) -> &mut Bindgen {
+        self.test_harness = test_harness;
+        self
+    }
+
+    /// Registers a hook used to generate glue for a custom type descriptor.
+    ///
+    /// See the `TypeHook` trait for more information.
+    pub fn type_hook<T: TypeHook + 'static>(&mut self, hook: T) -> &mut Bindgen {
+        self.type_hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Registers a pass to run over the generated output.
+    ///
+    /// See the `Pass` trait for more information.
+    pub fn pass<T: Pass + 'static>(&mut self, pass: T) -> &mut Bindgen {
+        self.passes.push(Box::new(pass));
+        self
+    }
+
     pub fn generate<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
         self._generate(path.as_ref())
     }
@@ -67,9 +198,24 @@ impl Bindgen {
         let mut module = parity_wasm::deserialize_file(input).map_err(|e| {
             format_err!("{:?}", e)
         })?;
-        let programs = extract_programs(&mut module);
+        let programs = extract_programs(&mut module)?;
+        for program in programs.iter() {
+            validate_program(program)?;
+        }
+        let hash = interface_hash(&programs);
+        if let Some(ref path) = self.check_interface {
+            let mut expected = String::new();
+            File::open(path)
+                .and_then(|mut f| f.read_to_string(&mut expected))
+                .map_err(|e| format_err!("failed to read `{}`: {}", path.display(), e))?;
+            if expected.trim() != hash {
+                bail!("the exported interface has changed: expected hash {} \
+                       from `{}` but found {}",
+                      expected.trim(), path.display(), hash);
+            }
+        }
 
-        let (js, ts) = {
+        let (mut js, mut ts) = {
             let mut cx = js::Context {
                 globals: String::new(),
                 imports: String::new(),
@@ -78,13 +224,23 @@ impl Bindgen {
                 required_internal_exports: Default::default(),
                 imports_to_rewrite: Default::default(),
                 custom_type_names: Default::default(),
+                string_enums: Default::default(),
+                enums: Default::default(),
+                tagged_enums: Default::default(),
+                error_enums: Default::default(),
                 imported_names: Default::default(),
                 exported_classes: Default::default(),
+                referenced_classes: Default::default(),
+                constructor_classes: Default::default(),
+                exposed_type_hooks: Default::default(),
                 config: &self,
                 module: &mut module,
             };
             for program in programs.iter() {
-                cx.add_custom_type_names(program);
+                cx.add_custom_type_names(program)?;
+            }
+            for program in programs.iter() {
+                cx.check_types(program)?;
             }
             for program in programs.iter() {
                 js::SubContext {
@@ -94,6 +250,11 @@ impl Bindgen {
             }
             cx.finalize(stem)
         };
+        for pass in self.passes.iter() {
+            pass.module(&mut module)?;
+            pass.js(&mut js)?;
+            pass.typescript(&mut ts)?;
+        }
 
         let js_path = out_dir.join(stem).with_extension("js");
         File::create(&js_path).unwrap()
@@ -105,6 +266,14 @@ impl Bindgen {
                 .write_all(ts.as_bytes()).unwrap();
         }
 
+        if self.manifest {
+            let manifest_path = out_dir.join(format!("{}.manifest.json", stem));
+            let manifest = serde_json::to_string_pretty(&manifest(&programs, &hash))?;
+            File::create(&manifest_path)?.write_all(manifest.as_bytes())?;
+            let hash_path = out_dir.join(format!("{}.interface.hash", stem));
+            File::create(&hash_path)?.write_all(hash.as_bytes())?;
+        }
+
         let wasm_path = out_dir.join(format!("{}_wasm", stem)).with_extension("wasm");
         let wasm_bytes = parity_wasm::serialize(module).map_err(|e| {
             format_err!("{:?}", e)
@@ -117,7 +286,109 @@ impl Bindgen {
     }
 }
 
-fn extract_programs(module: &mut Module) -> Vec<shared::Program> {
+/// Describes the bindings in `programs`, along with the features which
+/// conditionally compiled items depended on and so must have been enabled.
+fn manifest(programs: &[shared::Program], hash: &str) -> serde_json::Value {
+    let mut features = BTreeSet::new();
+    let mut exports = BTreeSet::new();
+    let mut imports = BTreeSet::new();
+    for program in programs {
+        features.extend(program.features.iter().cloned());
+        for export in program.exports.iter() {
+            let name = export.js_name.as_ref().unwrap_or(&export.function.name);
+            exports.insert(match export.class {
+                Some(ref class) => format!("{}.{}", class, name),
+                None => name.clone(),
+            });
+        }
+        for import in program.imports.iter() {
+            let module = import.module.as_ref().map(|s| &s[..]).unwrap_or("");
+            imports.insert(format!("{}:{}", module, import.function.name));
+        }
+    }
+    json!({
+        "features": features,
+        "exports": exports,
+        "imports": imports,
+        "interface_hash": hash,
+    })
+}
+
+/// Hashes everything about `programs` which affects the API seen by JS, such
+/// that it's the same regardless of the order items were defined in.
+///
+/// Imports aren't included as they're provided by the same package.
+fn interface_hash(programs: &[shared::Program]) -> String {
+    fn types(types: &[shared::Type]) -> String {
+        types.iter()
+            .map(|&t| (t as u32).to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    let mut items = BTreeSet::new();
+    for program in programs {
+        for e in program.exports.iter() {
+            let f = &e.function;
+            items.insert(format!(
+                "export {:?} {:?} {} method={} constant={} constructor={} \
+                 iterator={} getter={:?} setter={:?} args={} ret={:?} \
+                 optional={:?} optional_args={} optional_ret={} tuple_ret={} \
+                 error={:?} array_buffer={} plain_object={} blob={:?} \
+                 read_blobs={} readable_stream={} stream_sink={}",
+                e.class, e.js_name, f.name, e.method, e.constant, e.constructor,
+                e.iterator, e.getter, e.setter, types(&f.arguments),
+                f.ret.map(|t| t as u32), f.optional, e.optional_args,
+                e.optional_ret, types(&e.tuple_ret), e.error.map(|t| t as u32),
+                e.array_buffer, e.plain_object, e.blob, e.read_blobs,
+                e.readable_stream, e.stream_sink,
+            ));
+        }
+        for s in program.structs.iter() {
+            items.insert(format!("struct {} unit={} free={:?} implements={:?}",
+                                 s.name, s.unit, s.free_method,
+                                 s.typescript_implements));
+        }
+        for e in program.string_enums.iter() {
+            items.insert(format!("string_enum {} {:?}", e.name, e.variants));
+        }
+        for e in program.enums.iter() {
+            let variants = e.variants.iter()
+                .map(|v| format!("{}={}", v.name, v.value))
+                .collect::<Vec<_>>();
+            items.insert(format!("enum {} {:?}", e.name, variants));
+        }
+        for e in program.tagged_enums.iter() {
+            let variants = e.variants.iter()
+                .map(|v| {
+                    let fields = v.fields.iter()
+                        .map(|f| format!("{}:{}", f.name, f.typescript))
+                        .collect::<Vec<_>>();
+                    format!("{}{:?}", v.kind, fields)
+                })
+                .collect::<Vec<_>>();
+            items.insert(format!("tagged_enum {} {:?}", e.name, variants));
+        }
+        for v in program.raw_views.iter() {
+            let fields = v.fields.iter()
+                .map(|f| format!("{}:{}@{}", f.name, f.kind, f.offset))
+                .collect::<Vec<_>>();
+            items.insert(format!("raw_view {} {} {:?}", v.name, v.size, fields));
+        }
+    }
+
+    // 64-bit FNV-1a, which unlike `DefaultHasher` is the same everywhere.
+    let mut hash = 0xcbf29ce484222325u64;
+    for item in items.iter() {
+        for &b in item.as_bytes().iter().chain(&[b'\n']) {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+fn extract_programs(module: &mut Module) -> Result<Vec<shared::Program>, Error> {
     let data = module.sections_mut()
         .iter_mut()
         .filter_map(|s| {
@@ -131,7 +402,7 @@ fn extract_programs(module: &mut Module) -> Vec<shared::Program> {
     let mut ret = Vec::new();
     let data = match data {
         Some(data) => data,
-        None => return ret,
+        None => return Ok(ret),
     };
 
     'outer:
@@ -159,19 +430,146 @@ fn extract_programs(module: &mut Module) -> Vec<shared::Program> {
             }
             // TODO: shouldn't take the rest of the value
             let json = value.iter()
-                .map(|i| char::from_u32(i.0).unwrap())
-                .collect::<String>();
-            let p = match serde_json::from_str(&json) {
-                Ok(f) => f,
-                Err(e) => {
-                    panic!("failed to decode what looked like wasm-bindgen data: {}", e)
-                }
-            };
+                .map(|i| char::from_u32(i.0))
+                .collect::<Option<String>>()
+                .ok_or_else(|| {
+                    format_err!("invalid character in what looked like \
+                                 wasm-bindgen data")
+                })?;
+            let p = serde_json::from_str(&json).map_err(|e| {
+                format_err!("failed to decode what looked like wasm-bindgen data: {}", e)
+            })?;
             ret.push(p);
         }
         data.entries_mut().remove(i);
     }
-    return ret
+    Ok(ret)
+}
+
+/// Rejects metadata whose names would end up as something other than plain
+/// identifiers in the generated JS, since they're spliced in verbatim.
+fn validate_program(program: &shared::Program) -> Result<(), Error> {
+    let mut names = Vec::new();
+    for export in program.exports.iter() {
+        names.push(&export.function.name);
+        names.extend(export.class.iter());
+        names.extend(export.js_name.iter());
+    }
+    for import in program.imports.iter() {
+        names.push(&import.function.name);
+        names.extend(import.class.iter());
+        names.extend(import.catch_class.iter());
+        if let Some(ref module) = import.module {
+            if module.contains(|c: char| c == '"' || c == '\\' || c.is_control()) {
+                bail!("invalid module name `{}`", module);
+            }
+        }
+        if let Some(ref global) = import.global {
+            for part in global.split('.') {
+                check_identifier(part)?;
+            }
+        }
+    }
+    for s in program.structs.iter() {
+        names.push(&s.name);
+        if let Some(ref implements) = s.typescript_implements {
+            check_type_reference(implements)?;
+        }
+        names.extend(s.free_method.iter());
+    }
+    for custom in program.custom_type_names.iter() {
+        names.push(&custom.name);
+    }
+    for view in program.raw_views.iter() {
+        names.push(&view.name);
+        for field in view.fields.iter() {
+            names.push(&field.name);
+            match &field.kind[..] {
+                "Uint8" | "Int8" | "Uint16" | "Int16" | "Uint32" | "Int32" |
+                "Float32" | "Float64" => {}
+                kind => bail!("unknown kind `{}` for field `{}`", kind, field.name),
+            }
+        }
+    }
+    for e in program.string_enums.iter() {
+        names.push(&e.name);
+    }
+    for e in program.enums.iter() {
+        names.push(&e.name);
+        names.extend(e.variants.iter().map(|v| &v.name));
+    }
+    for e in program.tagged_enums.iter() {
+        names.push(&e.name);
+        for v in e.variants.iter() {
+            names.extend(v.fields.iter().map(|f| &f.name));
+            for f in v.fields.iter() {
+                match &f.typescript[..] {
+                    "number" | "boolean" | "string" => {}
+                    ts => bail!("unknown type `{}` for field `{}`", ts, f.name),
+                }
+            }
+        }
+    }
+    for name in names {
+        check_identifier(name)?;
+    }
+    Ok(())
+}
+
+fn check_identifier(name: &str) -> Result<(), Error> {
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(c) => c == '_' || c == '$' || c.is_alphabetic(),
+        None => false,
+    };
+    if !valid || !chars.all(|c| c == '_' || c == '$' || c.is_alphanumeric()) {
+        bail!("`{}` is not a valid identifier", name);
+    }
+    Ok(())
+}
+
+/// Checks that `ty` is a TypeScript type reference such as `Foo`,
+/// `ns.Foo`, or `Comparable<Foo>`, optionally followed by `[]`s.
+fn check_type_reference(ty: &str) -> Result<(), Error> {
+    fn parse(s: &str) -> Option<&str> {
+        let mut s = s.trim_start();
+        loop {
+            let end = s.find(|c: char| {
+                c != '_' && c != '$' && !c.is_alphanumeric()
+            }).unwrap_or(s.len());
+            if check_identifier(&s[..end]).is_err() {
+                return None
+            }
+            s = s[end..].trim_start();
+            if !s.starts_with('.') {
+                break
+            }
+            s = s[1..].trim_start();
+        }
+        if s.starts_with('<') {
+            s = &s[1..];
+            loop {
+                s = parse(s)?.trim_start();
+                if s.starts_with(',') {
+                    s = &s[1..];
+                } else if s.starts_with('>') {
+                    s = &s[1..];
+                    break
+                } else {
+                    return None
+                }
+            }
+        }
+        s = s.trim_start();
+        while s.starts_with("[]") {
+            s = s[2..].trim_start();
+        }
+        Some(s)
+    }
+    match parse(ty) {
+        Some("") => Ok(()),
+        _ => bail!("`{}` is not a valid TypeScript type", ty),
+    }
 }
 
 #[repr(packed)]
@@ -78,8 +78,7 @@ impl Output {
                     if i > 0 {
                         args.push_str(", ");
                     }
-                    args.push((b'a' + (i as u8)) as char);
-                    args.push_str(": number");
+                    args.push_str(&format!("arg{}: number", i));
                 }
 
                 exports.push_str(&format!("
@@ -0,0 +1,376 @@
+//! Snapshots of the JS and TypeScript generated for the programs in
+//! `tests/fixtures`.
+//!
+//! Each fixture is the JSON metadata the macro would embed in a wasm module.
+//! It's wrapped in a minimal module, run through `Bindgen`, and the output is
+//! compared against `tests/snapshots`. Running with `WASM_BINDGEN_BLESS=1`
+//! writes out any snapshots which are missing or differ instead of failing,
+//! so changes to the generated code can be reviewed as a diff.
+
+extern crate wasm_bindgen_cli_support as cli;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let out_root = env::temp_dir().join("wasm-bindgen-snapshots");
+    let bless = env::var("WASM_BINDGEN_BLESS").is_ok();
+
+    let mut fixtures = fs::read_dir(dir.join("fixtures")).unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("json"))
+        .collect::<Vec<_>>();
+    fixtures.sort();
+    assert!(fixtures.len() > 0, "no fixtures found");
+
+    if bless {
+        fs::create_dir_all(dir.join("snapshots")).unwrap();
+    }
+    let mut failures = Vec::new();
+    for fixture in fixtures.iter() {
+        let name = fixture.file_stem().unwrap().to_str().unwrap();
+        let out_dir = out_root.join(name);
+        drop(fs::remove_dir_all(&out_dir));
+        fs::create_dir_all(&out_dir).unwrap();
+
+        let input = out_dir.join(format!("{}.wasm", name));
+        File::create(&input).unwrap()
+            .write_all(&module_with_metadata(&read(fixture))).unwrap();
+        cli::Bindgen::new()
+            .input_path(&input)
+            .typescript(true)
+            .manifest(true)
+            .generate(&out_dir)
+            .expect("failed to run bindgen");
+
+        for ext in ["js", "d.ts", "manifest.json"].iter() {
+            let actual = read(&out_dir.join(format!("{}.{}", name, ext)));
+            let snapshot = dir.join("snapshots").join(format!("{}.{}", name, ext));
+            let matches = snapshot.exists() && read(&snapshot) == actual;
+            if matches {
+                continue
+            }
+            if bless {
+                File::create(&snapshot).unwrap()
+                    .write_all(actual.as_bytes()).unwrap();
+            } else {
+                failures.push((snapshot, actual));
+            }
+        }
+    }
+
+    for &(ref snapshot, ref actual) in failures.iter() {
+        let problem = if snapshot.exists() { "differs" } else { "is missing" };
+        println!("{} {}, generated output was:\n{}",
+                 snapshot.display(), problem, actual);
+    }
+    if failures.len() > 0 {
+        panic!("{} snapshots are missing or differ, rerun with \
+                WASM_BINDGEN_BLESS=1 to update them", failures.len());
+    }
+}
+
+#[test]
+fn check_interface() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let out_dir = env::temp_dir().join("wasm-bindgen-check-interface");
+    drop(fs::remove_dir_all(&out_dir));
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let generate = |fixture: &str, hash: Option<&PathBuf>| {
+        let input = out_dir.join("check.wasm");
+        let json = read(&dir.join(format!("{}.json", fixture)));
+        File::create(&input).unwrap()
+            .write_all(&module_with_metadata(&json)).unwrap();
+        let mut b = cli::Bindgen::new();
+        b.input_path(&input).manifest(true);
+        if let Some(hash) = hash {
+            b.check_interface(hash);
+        }
+        b.generate(&out_dir)
+    };
+
+    generate("classes", None).unwrap();
+    let hash = out_dir.join("previous.hash");
+    fs::copy(out_dir.join("check.interface.hash"), &hash).unwrap();
+
+    generate("classes", Some(&hash)).unwrap();
+    let err = generate("numbers", Some(&hash)).unwrap_err();
+    assert!(err.to_string().contains("exported interface has changed"));
+}
+
+#[test]
+fn interface_hash() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let out_dir = env::temp_dir().join("wasm-bindgen-interface-hash");
+    drop(fs::remove_dir_all(&out_dir));
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let hash = |json: &str| {
+        let input = out_dir.join("hash.wasm");
+        File::create(&input).unwrap()
+            .write_all(&module_with_metadata(json)).unwrap();
+        cli::Bindgen::new()
+            .input_path(&input)
+            .manifest(true)
+            .generate(&out_dir)
+            .expect("failed to run bindgen");
+        read(&out_dir.join("hash.interface.hash"))
+    };
+
+    let numbers = read(&dir.join("numbers.json"));
+    let base = hash(&numbers);
+    assert_eq!(base, hash(&numbers));
+
+    // `scale` taking its last argument as required rather than optional
+    let required = numbers.replacen(r#""optional_args": 1"#, r#""optional_args": 0"#, 1);
+    assert_ne!(base, hash(&required));
+
+    // `polar` returning a boolean as the second element of its tuple
+    let tuple = numbers.replacen(r#""tuple_ret": ["^", "^"]"#, r#""tuple_ret": ["^", "a"]"#, 1);
+    assert_ne!(base, hash(&tuple));
+
+    // `add` returning a map as a plain object rather than a `Map`
+    let map = numbers.replacen(r#""ret": "^""#, r#""ret": "~""#, 1);
+    let object = map.replacen(r#""plain_object": false"#, r#""plain_object": true"#, 1);
+    assert_ne!(hash(&map), hash(&object));
+}
+
+/// Converts points to and from the `[x, y]` arrays the wasm side exchanges.
+struct PointHook;
+
+impl cli::TypeHook for PointHook {
+    fn handles(&self, descriptor: char) -> bool {
+        descriptor == '\u{10f000}'
+    }
+
+    fn typescript(&self, _descriptor: char) -> String {
+        String::from("Point")
+    }
+
+    fn export_argument(&self, _descriptor: char, arg: &str) -> String {
+        format!("encodePoint({})", arg)
+    }
+
+    fn export_return(&self, _descriptor: char, ret: &str) -> String {
+        format!("decodePoint({})", ret)
+    }
+
+    fn globals(&self) -> String {
+        String::from("function encodePoint(p) { return p.x; }\n\
+                      function decodePoint(x) { return { x }; }\n")
+    }
+}
+
+#[test]
+fn type_hook() {
+    let out_dir = env::temp_dir().join("wasm-bindgen-type-hook");
+    drop(fs::remove_dir_all(&out_dir));
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let generate = |descriptor: &str| {
+        let json = r#"{
+            "exports": [{
+                "class": null, "method": false, "constant": false,
+                "constructor": false, "iterator": false, "array_buffer": false,
+                "plain_object": false, "blob": null, "read_blobs": false,
+                "readable_stream": false, "stream_sink": false, "optional_args": 0,
+                "optional_ret": false, "tuple_ret": [], "error": null, "js_name": null,
+                "getter": null, "setter": null, "comments": [],
+                "function": {
+                    "name": "flip", "arguments": ["D"], "ret": "D",
+                    "ascii": [], "optional": []
+                }
+            }],
+            "imports": [], "custom_type_names": [], "structs": [], "raw_views": [],
+            "string_enums": [], "enums": [], "tagged_enums": [], "features": []
+        }"#.replace("D", descriptor);
+        let input = out_dir.join("hook.wasm");
+        File::create(&input).unwrap()
+            .write_all(&module_with_metadata(&json)).unwrap();
+        cli::Bindgen::new()
+            .input_path(&input)
+            .typescript(true)
+            .type_hook(PointHook)
+            .generate(&out_dir)
+    };
+
+    generate("\\udbfc\\udc00").unwrap();
+    let js = read(&out_dir.join("hook.js"));
+    assert!(js.contains("function encodePoint(p)"));
+    assert!(js.contains("encodePoint(arg0)"));
+    assert!(js.contains("decodePoint("));
+    let ts = read(&out_dir.join("hook.d.ts"));
+    assert!(ts.contains("export function flip(arg0: Point): Point;"), "{}", ts);
+
+    // Descriptors below the range left for hooks belong to exported types, so
+    // they're never handed to a hook even if it claims them.
+    let err = generate("\\u0100").unwrap_err();
+    assert!(err.to_string().contains("unknown type descriptor"));
+}
+
+#[test]
+fn typescript_implements() {
+    let out_dir = env::temp_dir().join("wasm-bindgen-typescript-implements");
+    drop(fs::remove_dir_all(&out_dir));
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let generate = |implements: &str| {
+        let json = r#"{
+            "exports": [{
+                "class": "Version", "method": true, "constant": false,
+                "constructor": false, "iterator": false, "array_buffer": false,
+                "plain_object": false, "blob": null, "read_blobs": false,
+                "readable_stream": false, "stream_sink": false, "optional_args": 0,
+                "optional_ret": false, "tuple_ret": [], "error": null, "js_name": null,
+                "getter": null, "setter": null, "comments": [],
+                "function": {
+                    "name": "major", "arguments": [], "ret": "^",
+                    "ascii": [], "optional": []
+                }
+            }],
+            "imports": [], "custom_type_names": [], "raw_views": [],
+            "structs": [{
+                "name": "Version", "comments": [], "typescript_implements": I,
+                "unit": false, "free_method": null
+            }],
+            "string_enums": [], "enums": [], "tagged_enums": [], "features": []
+        }"#.replace("I", implements);
+        let input = out_dir.join("implements.wasm");
+        File::create(&input).unwrap()
+            .write_all(&module_with_metadata(&json)).unwrap();
+        cli::Bindgen::new()
+            .input_path(&input)
+            .typescript(true)
+            .generate(&out_dir)
+    };
+
+    generate(r#""Comparable<Version>""#).unwrap();
+    let ts = read(&out_dir.join("implements.d.ts"));
+    assert!(ts.contains("export class Version implements Comparable<Version> {"), "{}", ts);
+
+    generate(r#""Iterable<Map<string, number[]>>""#).unwrap();
+    let ts = read(&out_dir.join("implements.d.ts"));
+    assert!(ts.contains("implements Iterable<Map<string, number[]>> {"), "{}", ts);
+
+    for bad in [r#""Comparable<Version""#, r#""{}""#, r#""A; B""#].iter() {
+        let err = generate(bad).unwrap_err();
+        assert!(err.to_string().contains("is not a valid TypeScript type"), "{}", err);
+    }
+}
+
+#[test]
+fn invalid_signatures() {
+    let out_dir = env::temp_dir().join("wasm-bindgen-invalid-signatures");
+    drop(fs::remove_dir_all(&out_dir));
+    fs::create_dir_all(&out_dir).unwrap();
+
+    // Generates an export of `Counter::f` taking `args` and returning `ret`,
+    // with the default value of one field replaced as in `attr`.
+    let generate = |attr: (&str, &str), args: &str, ret: &str| {
+        let json = r#"{
+            "exports": [{
+                "class": "Counter", "method": true, "constant": false,
+                "constructor": false, "iterator": false, "array_buffer": false,
+                "plain_object": false, "blob": null, "read_blobs": false,
+                "readable_stream": false, "stream_sink": false, "optional_args": 0,
+                "optional_ret": false, "tuple_ret": [], "error": null, "js_name": null,
+                "getter": null, "setter": null, "comments": [],
+                "function": {
+                    "name": "f", "arguments": ARGS, "ret": RET,
+                    "ascii": [], "optional": []
+                }
+            }],
+            "imports": [], "custom_type_names": [{"descriptor": "\u0086", "name": "Counter"}],
+            "structs": [{
+                "name": "Counter", "comments": [], "typescript_implements": null,
+                "unit": false, "free_method": null
+            }],
+            "raw_views": [], "string_enums": [], "enums": [], "tagged_enums": [],
+            "features": []
+        }"#.replacen(attr.0, attr.1, 1).replace("ARGS", args).replace("RET", ret);
+        let input = out_dir.join("invalid.wasm");
+        File::create(&input).unwrap()
+            .write_all(&module_with_metadata(&json)).unwrap();
+        cli::Bindgen::new()
+            .input_path(&input)
+            .typescript(true)
+            .generate(&out_dir)
+            .map_err(|e| e.to_string())
+    };
+
+    let setter = (r#""setter": null"#, r#""setter": "count""#);
+    generate(setter, r#"["^"]"#, "null").unwrap();
+    let ts = read(&out_dir.join("invalid.d.ts"));
+    assert!(ts.contains("count: number;\n"), "{}", ts);
+    let getter = (r#""getter": null"#, r#""getter": "count""#);
+    generate(getter, "[]", r#""^""#).unwrap();
+    let ts = read(&out_dir.join("invalid.d.ts"));
+    assert!(ts.contains("readonly count: number;\n"), "{}", ts);
+
+    let cases = [
+        (setter, "[]", "null", "must take exactly one argument"),
+        (getter, r#"["^"]"#, r#""^""#, "can't take any arguments"),
+        ((r#""constructor": false"#, r#""constructor": true"#), "[]", r#""^""#,
+         "must return an instance of it"),
+        ((r#""optional_args": 0"#, r#""optional_args": 1"#), "[]", "null",
+         "more optional arguments"),
+        ((r#""tuple_ret": []"#, r#""tuple_ret": ["`"]"#), "[]", "null",
+         "unsupported element type"),
+        ((r#""blob": null"#, r#""blob": "application/json""#), "[]", r#""`""#,
+         "`blob` attribute"),
+        ((r#""stream_sink": false"#, r#""stream_sink": true"#), r#"["^"]"#, "null",
+         "`stream_sink` functions"),
+        (("", ""), "[]", r#""_""#, "returns an unsupported type"),
+    ];
+    for &(attrs, args, ret, msg) in cases.iter() {
+        let err = generate(attrs, args, ret).unwrap_err();
+        assert!(err.contains(msg), "expected `{}` in: {}", msg, err);
+    }
+}
+
+fn read(path: &PathBuf) -> String {
+    let mut ret = String::new();
+    File::open(path).unwrap().read_to_string(&mut ret).unwrap();
+    ret
+}
+
+/// Builds a wasm module with one page of memory and a data segment holding
+/// `json` in the same form the macro emits, one `u32` per character.
+fn module_with_metadata(json: &str) -> Vec<u8> {
+    let mut value = Vec::new();
+    for c in "wbg:".chars().chain(json.trim().chars()) {
+        let c = c as u32;
+        value.extend_from_slice(&[c as u8, (c >> 8) as u8, (c >> 16) as u8, (c >> 24) as u8]);
+    }
+
+    let mut data = Vec::new();
+    leb128(&mut data, 1); // one segment
+    data.push(0); // memory 0
+    data.extend_from_slice(&[0x41, 0x00, 0x0b]); // i32.const 0; end
+    leb128(&mut data, value.len() as u32);
+    data.extend_from_slice(&value);
+
+    let mut module = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+    module.extend_from_slice(&[0x05, 0x03, 0x01, 0x00, 0x01]); // memory section
+    module.push(0x0b); // data section
+    leb128(&mut module, data.len() as u32);
+    module.extend_from_slice(&data);
+    module
+}
+
+fn leb128(dst: &mut Vec<u8>, mut val: u32) {
+    loop {
+        let byte = (val & 0x7f) as u8;
+        val >>= 7;
+        if val == 0 {
+            dst.push(byte);
+            return
+        }
+        dst.push(byte | 0x80);
+    }
+}
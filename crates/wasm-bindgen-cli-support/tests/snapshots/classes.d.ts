@@ -0,0 +1,17 @@
+/* tslint:disable */
+export enum Status { Ok = 200, Error = 500 }
+/**
+* A counter.
+*/
+export class Counter {
+                private ptr: number;
+            constructor(ptr: number);
+free(): void;
+isFreed(): boolean;
+[Symbol.dispose](): void;
+/**
+* Bumps the count, returning the new value.
+*/
+ bump(): number;
+static limit(): number;
+}
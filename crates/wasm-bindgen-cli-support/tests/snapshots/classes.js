@@ -0,0 +1,41 @@
+
+            /* tslint:disable */
+            import * as wasm from './classes_wasm'; // imports from wasm file
+            
+
+            
+            export const Status = Object.freeze({ Ok: 200, Error: 500 });
+        export class Counter {
+                    constructor(ptr) {
+                        this.ptr = ptr;
+                    }
+                
+                free() {
+                    const ptr = this.ptr;
+                    if (ptr === 0) {
+                        return;
+                    }
+                    this.ptr = 0;
+                    wasm.__wbg_counter_free(ptr);
+                }
+
+                isFreed() {
+                    return this.ptr === 0;
+                }
+             bump() {
+        const ret = wasm.counter_bump(this.ptr);
+                return ret;
+            }
+static limit() {
+        const ret = wasm.counter_limit();
+                return ret;
+            }
+}
+
+                if (typeof Symbol.dispose === 'symbol') {
+                    Counter.prototype[Symbol.dispose] = function() {
+                        this.free();
+                    };
+                }
+            
+        
\ No newline at end of file
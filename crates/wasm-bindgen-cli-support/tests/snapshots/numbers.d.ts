@@ -0,0 +1,10 @@
+/* tslint:disable */
+/**
+* Adds two numbers.
+*/
+export function add(arg0: number, arg1: number): number;
+export function scale(arg0: number, arg1?: number | null): number;
+/**
+* Converts a point to polar coordinates.
+*/
+export function polar(arg0: number, arg1: number): [number, number];
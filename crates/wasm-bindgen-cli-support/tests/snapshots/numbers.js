@@ -0,0 +1,31 @@
+
+            /* tslint:disable */
+            import * as wasm from './numbers_wasm'; // imports from wasm file
+            
+
+            export const add = wasm.add;
+export function scale(arg0, arg1) {
+        const ret = wasm.scale(arg0, arg1 == null ? 0 : 1, arg1 == null ? 0 : arg1);
+                return ret;
+            }
+
+            let cachedFloat64Memory = null;
+            function getFloat64Memory() {
+                if (cachedFloat64Memory === null ||
+                    cachedFloat64Memory.buffer !== wasm.memory.buffer)
+                    cachedFloat64Memory = new Float64Array(wasm.memory.buffer);
+                return cachedFloat64Memory;
+            }
+        
+            let cachedTupleRetPtr = null;
+            function tupleRetPtr() {
+                if (cachedTupleRetPtr === null)
+                    cachedTupleRetPtr = wasm.__wbindgen_tuple_ret_ptr();
+                return cachedTupleRetPtr;
+            }
+        export function polar(arg0, arg1) {
+        const ret = wasm.polar(arg0, arg1);
+                return [getFloat64Memory()[tupleRetPtr() / 8 + 0], getFloat64Memory()[tupleRetPtr() / 8 + 1]];
+            }
+
+        
\ No newline at end of file
@@ -0,0 +1,2 @@
+/* tslint:disable */
+export function greet(arg0: string): string;
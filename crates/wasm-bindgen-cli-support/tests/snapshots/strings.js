@@ -0,0 +1,98 @@
+
+            /* tslint:disable */
+            import * as wasm from './strings_wasm'; // imports from wasm file
+            
+                        import { log } from './logger';
+                    
+
+            
+            let cachedUint8Memory = null;
+            function getUint8Memory() {
+                if (cachedUint8Memory === null ||
+                    cachedUint8Memory.buffer !== wasm.memory.buffer)
+                    cachedUint8Memory = new Uint8Array(wasm.memory.buffer);
+                return cachedUint8Memory;
+            }
+        
+            let cachedEncoder = null;
+            function textEncoder() {
+                if (cachedEncoder)
+                    return cachedEncoder;
+                cachedEncoder = new TextEncoder('utf-8');
+                return cachedEncoder;
+            }
+        
+            function passStringToWasm(arg) {
+                if (typeof(arg) !== 'string')
+                    throw new Error('expected a string argument');
+                let len = arg.length;
+                let ptr = wasm.__wbindgen_malloc(len, 1);
+                const mem = getUint8Memory();
+                let offset = 0;
+                for (; offset < len; offset++) {
+                    const code = arg.charCodeAt(offset);
+                    if (code > 0x7f)
+                        break;
+                    mem[ptr + offset] = code;
+                }
+                if (offset !== len) {
+                    const rest = arg.slice(offset);
+                    const cap = offset + rest.length * 3;
+                    ptr = wasm.__wbindgen_realloc(ptr, len, cap, 1);
+                    const view = getUint8Memory().subarray(ptr + offset, ptr + cap);
+                    offset += encodeStringInto(rest, view);
+                    ptr = wasm.__wbindgen_realloc(ptr, cap, offset, 1);
+                    len = offset;
+                }
+                return [ptr, len];
+            }
+
+            function encodeStringInto(arg, view) {
+                
+                const encoder = textEncoder();
+                if (typeof(encoder.encodeInto) === 'function')
+                    return encoder.encodeInto(arg, view).written;
+                const buf = encoder.encode(arg);
+                view.set(buf);
+                return buf.length;
+            
+            }
+        
+            let cachedDecoder = null;
+            function textDecoder() {
+                if (cachedDecoder)
+                    return cachedDecoder;
+                cachedDecoder = new TextDecoder('utf-8');
+                return cachedDecoder;
+            }
+        
+                function getStringFromWasm(ptr, len) {
+                    const mem = getUint8Memory();
+                    const slice = mem.slice(ptr, ptr + len);
+                    const ret = textDecoder().decode(slice);
+                    return ret;
+                }
+            
+            function takeStringFromWasm(ret) {
+                const ptr = wasm.__wbindgen_boxed_str_ptr(ret);
+                const len = wasm.__wbindgen_boxed_str_len(ret);
+                const realRet = getStringFromWasm(ptr, len);
+                wasm.__wbindgen_boxed_str_free(ret);
+                return realRet;
+            }
+        export function greet(arg0) {
+        const [ptr0, len0] = passStringToWasm(arg0);
+                    try {
+                    const ret = wasm.greet(ptr0, len0);
+                    return takeStringFromWasm(ret);
+                } finally {
+                    
+wasm.__wbindgen_free(ptr0, len0, 1);
+
+                }
+            }
+export function __wbg_f_log(ptr0, len0) {
+log(getStringFromWasm(ptr0, len0))
+}
+
+        
\ No newline at end of file
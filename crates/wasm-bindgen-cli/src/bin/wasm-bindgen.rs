@@ -21,6 +21,11 @@ Options:
     --nodejs                Generate output for node.js, not the browser
     --typescript            Output a TypeScript definition file
     --debug                 Include otherwise-extraneous debug checks in output
+    --debug-assertions      Include runtime assertions in the output
+    --protect-constructors  Prevent exported classes from being constructed in JS
+    --map-heap              Never reuse heap indices, to catch use-after-free
+    --manifest              Output a JSON manifest of the bindings
+    --check-interface FILE  Fail if the interface hash differs from FILE's
 ";
 
 #[derive(Debug, Deserialize)]
@@ -29,6 +34,11 @@ struct Args {
     flag_typescript: bool,
     flag_out_dir: Option<PathBuf>,
     flag_debug: bool,
+    flag_debug_assertions: bool,
+    flag_protect_constructors: bool,
+    flag_map_heap: bool,
+    flag_manifest: bool,
+    flag_check_interface: Option<PathBuf>,
     arg_input: PathBuf,
 }
 
@@ -41,7 +51,18 @@ fn main() {
     b.input_path(&args.arg_input)
      .nodejs(args.flag_nodejs)
      .debug(args.flag_debug)
+     .map_heap(args.flag_map_heap)
+     .manifest(args.flag_manifest)
      .typescript(args.flag_typescript);
+    if args.flag_debug_assertions {
+        b.debug_assertions(true);
+    }
+    if args.flag_protect_constructors {
+        b.protect_constructors(true);
+    }
+    if let Some(ref path) = args.flag_check_interface {
+        b.check_interface(path);
+    }
 
     let out_dir = match args.flag_out_dir {
         Some(ref p) => p,
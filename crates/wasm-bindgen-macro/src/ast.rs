@@ -1,3 +1,4 @@
+use std::cmp;
 use std::collections::BTreeSet;
 
 use proc_macro2::Span;
@@ -11,17 +12,35 @@ pub struct Program {
     pub imports: Vec<Import>,
     pub imported_types: Vec<(syn::Visibility, syn::Ident)>,
     pub structs: Vec<Struct>,
+    pub string_enums: Vec<StringEnum>,
+    pub enums: Vec<Enum>,
+    pub tagged_enums: Vec<TaggedEnum>,
+    /// The `#[cfg]` attributes everything in this program is compiled under.
+    pub cfgs: Vec<syn::Attribute>,
 }
 
 pub struct Export {
     pub class: Option<syn::Ident>,
+    // the name of the class in JS, if it differs from `class`
+    pub js_class: Option<String>,
     pub method: bool,
     pub mutable: bool,
+    pub constant: bool,
+    pub iterator: bool,
+    pub optional_args: usize,
+    pub optional_ret: bool,
+    pub error: Option<syn::Type>,
+    pub tuple_ret: Vec<syn::Type>,
     pub function: Function,
 }
 
 pub struct Import {
     pub module: Option<String>,
+    pub catch_err: Option<syn::Type>,
+    // whether `catch_err` is looked up in the global scope rather than
+    // imported from `module`
+    pub catch_global: bool,
+    pub optional_ret: bool,
     pub kind: ImportKind,
     pub function: Function,
 }
@@ -37,6 +56,8 @@ pub struct Function {
     pub name: syn::Ident,
     pub arguments: Vec<Type>,
     pub ret: Option<Type>,
+    pub ascii: Vec<usize>,
+    pub optional: Vec<usize>,
     pub opts: BindgenAttrs,
     pub rust_attrs: Vec<syn::Attribute>,
     pub rust_decl: Box<syn::FnDecl>,
@@ -45,18 +66,118 @@ pub struct Function {
 
 pub struct Struct {
     pub name: syn::Ident,
+    pub js_name: String,
+    pub comments: Vec<String>,
+    pub typescript_implements: Option<String>,
+    pub raw_view: Option<RawView>,
+    pub unit: bool,
+    pub free_method: Option<String>,
+    pub fields: Vec<StructField>,
+    // the hidden method behind `toString` in JS, if the struct is `display`
+    pub to_string: Option<syn::Ident>,
+}
+
+/// A `pub` field of an exported struct, which JS sees as a property through
+/// hidden accessor methods.
+pub struct StructField {
+    pub name: syn::Ident,
+    pub ty: syn::Type,
+    pub attrs: Vec<syn::Attribute>,
+    pub getter: syn::Ident,
+    // `None` if the field can't be set from JS
+    pub setter: Option<syn::Ident>,
+}
+
+pub struct StringEnum {
+    pub name: syn::Ident,
+    pub comments: Vec<String>,
+    pub variants: Vec<(syn::Ident, String)>,
+}
+
+pub struct Enum {
+    pub name: syn::Ident,
+    pub comments: Vec<String>,
+    pub variants: Vec<(syn::Ident, u32)>,
+}
+
+pub struct TaggedEnum {
+    pub name: syn::Ident,
+    pub comments: Vec<String>,
+    pub variants: Vec<TaggedVariant>,
+}
+
+pub struct TaggedVariant {
+    pub name: syn::Ident,
+    pub kind: String,
+    pub fields: Vec<(syn::Ident, syn::Type, FieldKind)>,
+}
+
+/// The types which can be stored in the fields of a `TaggedEnum`.
+#[derive(Copy, Clone)]
+pub enum FieldKind {
+    Number,
+    Boolean,
+    String,
+}
+
+pub struct RawView {
+    pub size: u32,
+    pub fields: Vec<RawField>,
+}
+
+pub struct RawField {
+    pub name: syn::Ident,
+    pub offset: u32,
+    pub kind: &'static str,
+    pub little_endian: bool,
+    pub comments: Vec<String>,
 }
 
 pub enum Type {
     // special
     BorrowedStr,
+    StaticStr,
     String,
+    BoxedStr,
+    Vector(VectorType, bool),
+    BoxedSlice(VectorType),
+    // `i64` if signed, otherwise `u64`
+    Int64(bool),
+    // `i128` if signed, otherwise `u128`
+    Int128(bool),
+    VecString,
+    // a `HashMap` or `BTreeMap` with `String` keys, along with its full type
+    Map(syn::Type, MapValue),
 
     ByRef(syn::Type),
     ByMutRef(syn::Type),
     ByValue(syn::Type),
 }
 
+#[derive(Copy, Clone)]
+pub enum VectorType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+    I64,
+    U64,
+    // `u8` data wrapped in `wasm_bindgen::Clamped`
+    ClampedU8,
+    // only owned, as `Vec<JsValue>` or `Box<[JsValue]>`
+    JsValue,
+}
+
+#[derive(Copy, Clone)]
+pub enum MapValue {
+    String,
+    JsValue,
+}
+
 impl Program {
     pub fn push_item(&mut self,
                      item: syn::Item,
@@ -78,17 +199,69 @@ impl Program {
                     }
                 }
                 f.to_tokens(tokens);
-                self.exports.push(Export {
+                let mut function = Function::from(f, opts);
+                if function.opts.constructor() {
+                    panic!("`constructor` can only be used on functions in an impl");
+                }
+                let export = Export {
                     class: None,
+                    js_class: None,
                     method: false,
                     mutable: false,
-                    function: Function::from(f, opts),
-                });
+                    constant: false,
+                    iterator: false,
+                    optional_args: function.extract_optional_args(),
+                    error: function.extract_error(),
+                    optional_ret: function.extract_optional_ret(),
+                    tuple_ret: function.extract_tuple_ret(),
+                    function,
+                };
+                export.check_attrs();
+                self.exports.push(export);
             }
             syn::Item::Struct(mut s) => {
                 let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut s.attrs));
+                let strukt = Struct::from(&mut s, opts);
                 s.to_tokens(tokens);
-                self.structs.push(Struct::from(s, opts));
+                for field in strukt.fields.iter() {
+                    self.push_struct_field(&strukt, field);
+                }
+                if let Some(to_string) = strukt.to_string {
+                    self.push_struct_to_string(&strukt, to_string);
+                }
+                self.structs.push(strukt);
+            }
+            syn::Item::Enum(mut e) => {
+                let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut e.attrs));
+                let is_tagged_enum = e.variants.iter().any(|v| {
+                    match v.fields {
+                        syn::Fields::Unit => false,
+                        _ => true,
+                    }
+                });
+                let is_string_enum = match e.variants.iter().next() {
+                    Some(v) => match v.discriminant {
+                        Some((_, syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(_),
+                            ..
+                        }))) => true,
+                        _ => false,
+                    },
+                    None => false,
+                };
+                if is_tagged_enum {
+                    let tagged_enum = TaggedEnum::from(&e, opts);
+                    e.to_tokens(tokens);
+                    self.tagged_enums.push(tagged_enum);
+                } else if is_string_enum {
+                    let string_enum = StringEnum::from(&mut e, opts);
+                    e.to_tokens(tokens);
+                    self.string_enums.push(string_enum);
+                } else {
+                    let enum_ = Enum::from(&e, opts);
+                    e.to_tokens(tokens);
+                    self.enums.push(enum_);
+                }
             }
             syn::Item::Impl(mut i) => {
                 let opts = opts.unwrap_or_else(|| BindgenAttrs::find(&mut i.attrs));
@@ -100,20 +273,27 @@ impl Program {
                 self.push_foreign_mod(f, opts);
             }
             _ => panic!("#[wasm_bindgen] can only be applied to a function, \
-                         struct, impl, or extern block"),
+                         struct, enum, impl, or extern block"),
         }
     }
 
-    pub fn push_impl(&mut self, item: syn::ItemImpl, _opts: BindgenAttrs) {
+    pub fn push_impl(&mut self, item: syn::ItemImpl, opts: BindgenAttrs) {
         if item.defaultness.is_some() {
             panic!("default impls are not supported");
         }
         if item.unsafety.is_some() {
             panic!("unsafe impls are not supported");
         }
-        if item.trait_.is_some() {
-            panic!("trait impls are not supported");
-        }
+        let is_iterator = match item.trait_ {
+            Some((None, ref path, _)) => {
+                match extract_path_ident(path) {
+                    Some(ref ident) if ident.as_ref() == "Iterator" => true,
+                    _ => panic!("trait impls other than `Iterator` are not supported"),
+                }
+            }
+            Some(_) => panic!("trait impls other than `Iterator` are not supported"),
+            None => false,
+        };
         if item.generics.params.len() > 0 {
             panic!("generic impls aren't supported");
         }
@@ -126,14 +306,80 @@ impl Program {
             }
             _ => panic!("unsupported self type in impl"),
         };
+        let js_class = opts.js_class().map(|s| s.to_string());
+        if is_iterator {
+            return self.push_iterator_impl(name, js_class, item.items)
+        }
         for item in item.items.into_iter() {
-            self.push_impl_item(name, item);
+            self.push_impl_item(name, js_class.clone(), item);
+        }
+    }
+
+    /// Exports the `next` method of an `Iterator` impl, which JS wraps up in
+    /// the iteration protocol.
+    fn push_iterator_impl(&mut self,
+                          class: syn::Ident,
+                          js_class: Option<String>,
+                          items: Vec<syn::ImplItem>) {
+        let mut item_ty = None;
+        let mut next = None;
+        for item in items {
+            match item {
+                syn::ImplItem::Type(ref t) if t.ident.as_ref() == "Item" => {
+                    item_ty = Some(t.ty.clone());
+                }
+                syn::ImplItem::Method(m) => {
+                    if m.sig.ident.as_ref() == "next" {
+                        next = Some(m);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let item_ty = item_ty.expect("`Iterator` impl is missing `type Item`");
+        let mut next = next.expect("`Iterator` impl is missing `next`");
+
+        let opts = BindgenAttrs::find(&mut next.attrs);
+        let (mut function, mutable) = Function::from_decl(next.sig.ident,
+                                                          Box::new(next.sig.decl),
+                                                          next.attrs,
+                                                          opts,
+                                                          syn::Visibility::Inherited,
+                                                          true);
+        // `next` is usually declared as returning `Option<Self::Item>`, so
+        // the item type is taken from the impl instead.
+        function.ret = Some(Type::from(&item_ty));
+        match function.ret {
+            Some(Type::ByValue(syn::Type::Tuple(_))) => {
+                panic!("iterators over tuples are not supported")
+            }
+            Some(Type::ByValue(_)) | Some(Type::String) => {}
+            _ => panic!("unsupported `Iterator` item type"),
         }
+        if mutable != Some(true) {
+            panic!("`next` must take `&mut self`");
+        }
+        self.exports.push(Export {
+            class: Some(class),
+            js_class,
+            method: true,
+            mutable: true,
+            constant: false,
+            iterator: true,
+            optional_args: 0,
+            error: None,
+            optional_ret: true,
+            tuple_ret: Vec::new(),
+            function,
+        });
     }
 
-    fn push_impl_item(&mut self, class: syn::Ident, item: syn::ImplItem) {
+    fn push_impl_item(&mut self,
+                      class: syn::Ident,
+                      js_class: Option<String>,
+                      item: syn::ImplItem) {
         let mut method = match item {
-            syn::ImplItem::Const(_) => panic!("const definitions aren't supported"),
+            syn::ImplItem::Const(c) => return self.push_impl_const(class, js_class, c),
             syn::ImplItem::Type(_) => panic!("type definitions in impls aren't supported"),
             syn::ImplItem::Method(m) => m,
             syn::ImplItem::Macro(_) => panic!("macros in impls aren't supported"),
@@ -154,17 +400,196 @@ impl Program {
         }
 
         let opts = BindgenAttrs::find(&mut method.attrs);
-
-        let (function, mutable) = Function::from_decl(method.sig.ident,
-                                                      Box::new(method.sig.decl),
-                                                      method.attrs,
-                                                      opts,
-                                                      method.vis,
-                                                      true);
-        self.exports.push(Export {
+        let (mut function, mutable) = Function::from_decl(method.sig.ident,
+                                                          Box::new(method.sig.decl),
+                                                          method.attrs,
+                                                          opts,
+                                                          method.vis,
+                                                          true);
+        let getter = function.opts.getter().is_some();
+        let setter = function.opts.setter().is_some();
+        if getter || setter {
+            if getter && setter {
+                panic!("a method can't be both a getter and a setter");
+            }
+            if mutable.is_none() {
+                panic!("getters and setters must be methods taking `self`");
+            }
+            if getter &&
+                (function.arguments.len() > 0 || function.ret.is_none())
+            {
+                panic!("getters must take no arguments and return a value");
+            }
+            if setter &&
+                (function.arguments.len() != 1 || function.ret.is_some())
+            {
+                panic!("setters must take a single argument and return nothing");
+            }
+        }
+        if function.opts.constructor() {
+            if mutable.is_some() {
+                panic!("constructors can't take `self`");
+            }
+            if function.ret.is_none() {
+                panic!("constructors must return an instance of the class");
+            }
+        }
+        let export = Export {
             class: Some(class),
+            js_class,
             method: mutable.is_some(),
             mutable: mutable.unwrap_or(false),
+            constant: false,
+            iterator: false,
+            optional_args: function.extract_optional_args(),
+            error: function.extract_error(),
+            optional_ret: function.extract_optional_ret(),
+            tuple_ret: function.extract_tuple_ret(),
+            function,
+        };
+        export.check_attrs();
+        self.exports.push(export);
+    }
+
+    /// Exports an associated constant as a getter with no arguments, which
+    /// shows up as a static property of the JS class.
+    fn push_impl_const(&mut self,
+                       class: syn::Ident,
+                       js_class: Option<String>,
+                       mut c: syn::ImplItemConst) {
+        match c.vis {
+            syn::Visibility::Public(_) => {}
+            _ => return,
+        }
+        if c.defaultness.is_some() {
+            panic!("default constants are not supported");
+        }
+
+        let opts = BindgenAttrs::find(&mut c.attrs);
+
+        let decl = syn::FnDecl {
+            fn_token: Default::default(),
+            generics: Default::default(),
+            paren_token: Default::default(),
+            inputs: Default::default(),
+            variadic: None,
+            output: syn::ReturnType::Type(Default::default(), Box::new(c.ty)),
+        };
+        let function = Function::from_decl(c.ident,
+                                           Box::new(decl),
+                                           c.attrs,
+                                           opts,
+                                           c.vis,
+                                           false).0;
+        self.exports.push(Export {
+            class: Some(class),
+            js_class,
+            method: false,
+            mutable: false,
+            constant: true,
+            iterator: false,
+            optional_args: 0,
+            optional_ret: false,
+            error: None,
+            tuple_ret: Vec::new(),
+            function,
+        });
+    }
+
+    /// Exports the hidden accessors of a `pub` struct field, which show up as
+    /// a property of the JS class.
+    fn push_struct_field(&mut self, strukt: &Struct, field: &StructField) {
+        let decl = |output| {
+            Box::new(syn::FnDecl {
+                fn_token: Default::default(),
+                generics: Default::default(),
+                paren_token: Default::default(),
+                inputs: Default::default(),
+                variadic: None,
+                output,
+            })
+        };
+        let name = field.name.to_string();
+
+        let opts = BindgenAttrs { attrs: vec![BindgenAttr::Getter(Some(name.clone()))] };
+        let output = syn::ReturnType::Type(Default::default(), Box::new(field.ty.clone()));
+        let mut function = Function::from_decl(field.getter,
+                                               decl(output),
+                                               field.attrs.clone(),
+                                               opts,
+                                               syn::Visibility::Inherited,
+                                               false).0;
+        self.exports.push(Export {
+            class: Some(strukt.name),
+            js_class: Some(strukt.js_name.clone()),
+            method: true,
+            mutable: false,
+            constant: false,
+            iterator: false,
+            optional_args: 0,
+            error: None,
+            optional_ret: function.extract_optional_ret(),
+            tuple_ret: function.extract_tuple_ret(),
+            function,
+        });
+
+        let setter = match field.setter {
+            Some(setter) => setter,
+            None => return,
+        };
+        let opts = BindgenAttrs { attrs: vec![BindgenAttr::Setter(Some(name))] };
+        let mut function = Function::from_decl(setter,
+                                               decl(syn::ReturnType::Default),
+                                               field.attrs.clone(),
+                                               opts,
+                                               syn::Visibility::Inherited,
+                                               false).0;
+        function.arguments.push(Type::from(&field.ty));
+        self.exports.push(Export {
+            class: Some(strukt.name),
+            js_class: Some(strukt.js_name.clone()),
+            method: true,
+            mutable: true,
+            constant: false,
+            iterator: false,
+            optional_args: function.extract_optional_args(),
+            error: None,
+            optional_ret: false,
+            tuple_ret: Vec::new(),
+            function,
+        });
+    }
+
+    /// Exports the hidden method formatting a `display` struct, which shows up
+    /// as `toString` on the JS class.
+    fn push_struct_to_string(&mut self, strukt: &Struct, name: syn::Ident) {
+        let decl = syn::FnDecl {
+            fn_token: Default::default(),
+            generics: Default::default(),
+            paren_token: Default::default(),
+            inputs: Default::default(),
+            variadic: None,
+            output: syn::ReturnType::Default,
+        };
+        let opts = BindgenAttrs { attrs: vec![BindgenAttr::JsName("toString".to_string())] };
+        let mut function = Function::from_decl(name,
+                                               Box::new(decl),
+                                               Vec::new(),
+                                               opts,
+                                               syn::Visibility::Inherited,
+                                               false).0;
+        function.ret = Some(Type::String);
+        self.exports.push(Export {
+            class: Some(strukt.name),
+            js_class: Some(strukt.js_name.clone()),
+            method: true,
+            mutable: false,
+            constant: false,
+            iterator: false,
+            optional_args: 0,
+            error: None,
+            optional_ret: false,
+            tuple_ret: Vec::new(),
             function,
         });
     }
@@ -175,6 +600,15 @@ impl Program {
             None => {}
             _ => panic!("only foreign mods with the `C` ABI are allowed"),
         }
+        let types = f.items.iter()
+            .filter_map(|item| {
+                match *item {
+                    syn::ForeignItem::Type(ref t) => Some(t.ident),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        let start = self.imports.len();
         for item in f.items.into_iter() {
             match item {
                 syn::ForeignItem::Fn(f) => self.push_foreign_fn(f, &opts),
@@ -182,6 +616,15 @@ impl Program {
                 _ => panic!("only foreign functions/types allowed for now"),
             }
         }
+        // Error types declared in the same block come from its module, while
+        // any others (like `DOMException`) are taken to be globals.
+        if opts.module().is_some() {
+            for import in self.imports[start..].iter_mut() {
+                if let Some(class) = import.catch_class() {
+                    import.catch_global = !types.contains(&class);
+                }
+            }
+        }
     }
 
     pub fn push_foreign_fn(&mut self,
@@ -195,18 +638,33 @@ impl Program {
                                            opts,
                                            f.vis,
                                            false).0;
+        let mut catch_err = None;
         if wasm.opts.catch() {
             // TODO: this assumes a whole bunch:
             //
             // * The outer type is actually a `Result`
-            // * The error type is a `JsValue`
             // * The actual type is the first type parameter
+            // * The error type is the second type parameter
             //
             // should probably fix this one day...
+            catch_err = Some(extract_second_ty_param(wasm.ret.as_ref())
+                .expect("can't `catch` without returning a Result"));
             wasm.ret = extract_first_ty_param(wasm.ret.as_ref())
                 .expect("can't `catch` without returning a Result");
         }
 
+        // Imports returning `Option<T>` map `undefined` and `null` to `None`
+        let optional = match wasm.ret {
+            Some(Type::ByValue(ref t)) => extract_option(t),
+            _ => None,
+        };
+        let optional_ret = optional.is_some();
+        if let Some(t) = optional {
+            wasm.ret = Some(t);
+        }
+        // ... and pass `undefined` for `None` arguments
+        wasm.extract_optional_args();
+
         let kind = if wasm.opts.method() {
             let class = wasm.arguments.get(0)
                 .expect("methods must have at least one argument");
@@ -216,9 +674,24 @@ impl Program {
                 Type::ByMutRef(_) => {
                     panic!("first method argument cannot be mutable ref")
                 }
-                Type::String | Type::BorrowedStr => {
+                Type::String |
+                Type::BoxedStr |
+                Type::BorrowedStr |
+                Type::StaticStr => {
                     panic!("method receivers cannot be strings")
                 }
+                Type::Vector(..) | Type::BoxedSlice(_) => {
+                    panic!("method receivers cannot be slices")
+                }
+                Type::Int64(_) | Type::Int128(_) => {
+                    panic!("method receivers cannot be integers")
+                }
+                Type::VecString => {
+                    panic!("method receivers cannot be vectors")
+                }
+                Type::Map(..) => {
+                    panic!("method receivers cannot be maps")
+                }
             };
             let class_name = match *class {
                 syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
@@ -263,8 +736,34 @@ impl Program {
             ImportKind::Normal
         };
 
+        if wasm.opts.this_arg() {
+            match kind {
+                ImportKind::Normal => {}
+                _ => panic!("`this_arg` can only be used on free functions"),
+            }
+            if wasm.arguments.len() == 0 {
+                panic!("`this_arg` functions must have at least one argument");
+            }
+        }
+
+        if wasm.opts.global().is_some() {
+            match kind {
+                ImportKind::Normal => {}
+                _ => panic!("`global` can only be used on free functions"),
+            }
+            match (wasm.arguments.len(), &wasm.ret) {
+                (0, &Some(_)) | (1, &None) => {}
+                _ => panic!("`global` functions must either take no arguments \
+                             and return the value or take the new value and \
+                             return nothing"),
+            }
+        }
+
         self.imports.push(Import {
             module: module_opts.module().map(|s| s.to_string()),
+            catch_err,
+            catch_global: true,
+            optional_ret,
             kind,
             function: wasm,
         });
@@ -287,7 +786,10 @@ impl Program {
             ("imports", &|a| a.list(&self.imports, Import::wbg_literal)),
             ("custom_type_names", &|a| {
                 let names = self.exports.iter()
-                    .filter_map(|e| e.class)
+                    .filter_map(|e| e.class_name())
+                    .chain(self.structs.iter()
+                        .filter(|s| s.raw_view.is_none())
+                        .map(|s| &s.js_name[..]))
                     .collect::<BTreeSet<_>>();
                 a.list(&names, |s, a| {
                     let val = shared::name_to_descriptor(s.as_ref());
@@ -297,6 +799,38 @@ impl Program {
                     ]);
                 })
             }),
+            ("structs", &|a| {
+                let structs = self.structs.iter()
+                    .filter(|s| s.raw_view.is_none());
+                a.list(structs, |s, a| {
+                    a.fields(&[
+                        ("name", &|a| a.str(&s.js_name)),
+                        ("comments", &|a| a.list(&s.comments, |c, a| a.str(c))),
+                        ("typescript_implements", &|a| {
+                            match s.typescript_implements {
+                                Some(ref s) => a.str(s),
+                                None => a.append("null"),
+                            }
+                        }),
+                        ("unit", &|a| a.bool(s.unit)),
+                        ("free_method", &|a| {
+                            match s.free_method {
+                                Some(ref s) => a.str(s),
+                                None => a.append("null"),
+                            }
+                        }),
+                    ]);
+                })
+            }),
+            ("string_enums", &|a| a.list(&self.string_enums, StringEnum::wbg_literal)),
+            ("enums", &|a| a.list(&self.enums, Enum::wbg_literal)),
+            ("tagged_enums", &|a| a.list(&self.tagged_enums, TaggedEnum::wbg_literal)),
+            ("features", &|a| a.list(&cfg_features(&self.cfgs), |s, a| a.str(s))),
+            ("raw_views", &|a| {
+                let views = self.structs.iter()
+                    .filter(|s| s.raw_view.is_some());
+                a.list(views, Struct::wbg_raw_view_literal)
+            }),
         ]);
         return a.cnt
     }
@@ -341,7 +875,7 @@ impl Function {
         }
 
         let mut mutable = None;
-        let arguments = decl.inputs.iter()
+        let captured = decl.inputs.iter()
             .filter_map(|arg| {
                 match *arg {
                     syn::FnArg::Captured(ref c) => Some(c),
@@ -356,9 +890,30 @@ impl Function {
                     _ => panic!("arguments cannot be `self` or ignored"),
                 }
             })
+            .collect::<Vec<_>>();
+        let arguments = captured.iter()
             .map(|arg| Type::from(&arg.ty))
             .collect::<Vec<_>>();
 
+        let ascii = opts.ascii()
+            .into_iter()
+            .map(|name| {
+                let idx = captured.iter()
+                    .position(|arg| {
+                        match arg.pat {
+                            syn::Pat::Ident(ref p) => p.ident.as_ref() == name,
+                            _ => false,
+                        }
+                    })
+                    .unwrap_or_else(|| panic!("no argument named `{}`", name));
+                match arguments[idx] {
+                    Type::BorrowedStr | Type::String | Type::BoxedStr => {}
+                    _ => panic!("`ascii` argument `{}` must be a string", name),
+                }
+                idx
+            })
+            .collect();
+
         let ret = match decl.output {
             syn::ReturnType::Default => None,
             syn::ReturnType::Type(_, ref t) => Some(Type::from(t)),
@@ -368,6 +923,8 @@ impl Function {
             name,
             arguments,
             ret,
+            ascii,
+            optional: Vec::new(),
             opts,
             rust_vis: vis,
             rust_decl: decl,
@@ -375,6 +932,97 @@ impl Function {
         }, mutable)
     }
 
+    /// Strips the `Option` off `Option<T>` arguments, recording them in
+    /// `optional` and returning how many trailing ones there are, which JS
+    /// callers may leave out.
+    fn extract_optional_args(&mut self) -> usize {
+        for (i, arg) in self.arguments.iter_mut().enumerate() {
+            let inner = match *arg {
+                Type::ByValue(ref t) => extract_option(t),
+                _ => None,
+            };
+            match inner {
+                Some(t @ Type::ByValue(_)) |
+                Some(t @ Type::String) |
+                Some(t @ Type::BorrowedStr) => *arg = t,
+                Some(_) => panic!("unsupported type for an optional argument"),
+                None => continue,
+            }
+            self.optional.push(i);
+        }
+        let len = self.arguments.len();
+        self.optional.iter()
+            .rev()
+            .zip((0..len).rev())
+            .take_while(|&(a, b)| *a == b)
+            .count()
+    }
+
+    /// Strips the `Option` off a returned `Option<T>`, returning whether
+    /// there was one.
+    fn extract_optional_ret(&mut self) -> bool {
+        let inner = match self.ret {
+            Some(Type::ByValue(ref t)) => extract_option(t),
+            _ => None,
+        };
+        match inner {
+            Some(Type::ByValue(syn::Type::Tuple(_))) => {
+                panic!("optional tuples can't be returned")
+            }
+            Some(t @ Type::ByValue(_)) |
+            Some(t @ Type::String) => {
+                self.ret = Some(t);
+                true
+            }
+            Some(_) => panic!("unsupported type for an optional return value"),
+            None => false,
+        }
+    }
+
+    /// Takes a returned tuple such as `(f64, f64)` out of `ret`, returning
+    /// the types of its elements, which JS receives as an array.
+    fn extract_tuple_ret(&mut self) -> Vec<syn::Type> {
+        let elems = match self.ret {
+            Some(Type::ByValue(syn::Type::Tuple(ref t))) if t.elems.len() > 0 => {
+                t.elems.iter().cloned().collect::<Vec<_>>()
+            }
+            _ => return Vec::new(),
+        };
+        // keep in sync with the size of `TUPLE_RET` in the `__rt` module
+        if elems.len() > 8 {
+            panic!("returned tuples can have at most 8 elements");
+        }
+        for elem in elems.iter() {
+            match Type::from(elem) {
+                Type::ByValue(_) => {}
+                _ => panic!("unsupported type in a returned tuple"),
+            }
+        }
+        self.ret = None;
+        elems
+    }
+
+    /// Strips the `Result` off a returned `Result<T, E>`, returning the error
+    /// type which is thrown in JS instead of being returned.
+    fn extract_error(&mut self) -> Option<syn::Type> {
+        let is_result = match self.ret {
+            Some(Type::ByValue(syn::Type::Path(syn::TypePath { qself: None, ref path }))) => {
+                path.segments.last()
+                    .map(|s| s.into_value().ident.as_ref() == "Result")
+                    .unwrap_or(false)
+            }
+            _ => false,
+        };
+        if !is_result {
+            return None
+        }
+        let err = extract_second_ty_param(self.ret.as_ref())
+            .expect("`Result` return types must name their error type");
+        self.ret = extract_first_ty_param(self.ret.as_ref())
+            .expect("unsupported `Result` return type");
+        Some(err)
+    }
+
     fn wbg_literal(&self, a: &mut LiteralBuilder) {
         a.fields(&[
             ("name", &|a| a.str(self.name.as_ref())),
@@ -385,6 +1033,8 @@ impl Function {
                     None => a.append("null"),
                 }
             }),
+            ("ascii", &|a| a.list(&self.ascii, |i, a| a.append(&i.to_string()))),
+            ("optional", &|a| a.list(&self.optional, |i, a| a.append(&i.to_string()))),
         ]);
     }
 }
@@ -403,6 +1053,22 @@ pub fn extract_path_ident(path: &syn::Path) -> Option<syn::Ident> {
     path.segments.first().map(|v| v.value().ident)
 }
 
+/// Whether `ty` is a primitive signed integer, which `WasmBoundary` passes as
+/// the bit pattern of a `u32`.
+pub fn is_signed_int(ty: &syn::Type) -> bool {
+    let path = match *ty {
+        syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
+        _ => return false,
+    };
+    match extract_path_ident(path) {
+        Some(i) => match i.as_ref() {
+            "i8" | "i16" | "i32" | "isize" => true,
+            _ => false,
+        },
+        None => false,
+    }
+}
+
 impl Type {
     pub fn from(ty: &syn::Type) -> Type {
         match *ty {
@@ -411,10 +1077,24 @@ impl Type {
                     syn::Type::Path(syn::TypePath { qself: None, ref path }) => {
                         let ident = extract_path_ident(path);
                         match ident.as_ref().map(|s| s.as_ref()) {
-                            Some("str") => return Type::BorrowedStr,
+                            Some("str") => {
+                                let is_static = r.lifetime.as_ref()
+                                    .map(|l| l.to_string() == "'static")
+                                    .unwrap_or(false);
+                                return if is_static {
+                                    Type::StaticStr
+                                } else {
+                                    Type::BorrowedStr
+                                }
+                            }
                             _ => {}
                         }
                     }
+                    syn::Type::Slice(ref slice) => {
+                        if let Some(ty) = VectorType::from(&slice.elem) {
+                            return Type::Vector(ty, false)
+                        }
+                    }
                     _ => {}
                 }
                 return if r.mutability.is_some() {
@@ -427,8 +1107,27 @@ impl Type {
                 let ident = extract_path_ident(path);
                 match ident.as_ref().map(|s| s.as_ref()) {
                     Some("String") => return Type::String,
+                    Some("i64") => return Type::Int64(true),
+                    Some("u64") => return Type::Int64(false),
+                    Some("i128") => return Type::Int128(true),
+                    Some("u128") => return Type::Int128(false),
                     _ => {}
                 }
+                if let Some(ty) = VectorType::from_vec(path) {
+                    return Type::Vector(ty, true)
+                }
+                if let Some(ty) = extract_vec_string(path) {
+                    return ty
+                }
+                if let Some(value) = extract_map(path) {
+                    return Type::Map(ty.clone(), value)
+                }
+                if let Some(ty) = extract_box(path) {
+                    return ty
+                }
+                if let Some(ty) = extract_clamped(path) {
+                    return ty
+                }
             }
             _ => {}
         }
@@ -436,10 +1135,28 @@ impl Type {
         Type::ByValue(ty.clone())
     }
 
+    pub fn is_clamped(&self) -> bool {
+        match *self {
+            Type::Vector(VectorType::ClampedU8, _) |
+            Type::BoxedSlice(VectorType::ClampedU8) => true,
+            _ => false,
+        }
+    }
+
     fn wbg_literal(&self, a: &mut LiteralBuilder) {
         match *self {
             Type::BorrowedStr => a.char(shared::TYPE_BORROWED_STR),
-            Type::String => a.char(shared::TYPE_STRING),
+            Type::StaticStr => a.char(shared::TYPE_STATIC_STR),
+            Type::String | Type::BoxedStr => a.char(shared::TYPE_STRING),
+            Type::Vector(ty, owned) => a.char(ty.descriptor(owned)),
+            Type::BoxedSlice(ty) => a.char(ty.descriptor(true)),
+            Type::Int64(true) => a.char(shared::TYPE_I64),
+            Type::Int64(false) => a.char(shared::TYPE_U64),
+            Type::Int128(true) => a.char(shared::TYPE_I128),
+            Type::Int128(false) => a.char(shared::TYPE_U128),
+            Type::VecString => a.char(shared::TYPE_VECTOR_STRING),
+            Type::Map(_, MapValue::String) => a.char(shared::TYPE_MAP_STRING),
+            Type::Map(_, MapValue::JsValue) => a.char(shared::TYPE_MAP_JSVALUE),
             Type::ByValue(ref t) => {
                 a.as_char(my_quote! {
                     <#t as ::wasm_bindgen::convert::WasmBoundary>::DESCRIPTOR
@@ -456,6 +1173,108 @@ impl Type {
     }
 }
 
+impl VectorType {
+    fn from(ty: &syn::Type) -> Option<VectorType> {
+        let path = match *ty {
+            syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
+            _ => return None,
+        };
+        match extract_path_ident(path)?.as_ref() {
+            "i8" => Some(VectorType::I8),
+            "u8" => Some(VectorType::U8),
+            "i16" => Some(VectorType::I16),
+            "u16" => Some(VectorType::U16),
+            "i32" => Some(VectorType::I32),
+            "u32" => Some(VectorType::U32),
+            "f32" => Some(VectorType::F32),
+            "f64" => Some(VectorType::F64),
+            "i64" => Some(VectorType::I64),
+            "u64" => Some(VectorType::U64),
+            _ => None,
+        }
+    }
+
+    fn from_owned(ty: &syn::Type) -> Option<VectorType> {
+        if let Some(ty) = VectorType::from(ty) {
+            return Some(ty)
+        }
+        let path = match *ty {
+            syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
+            _ => return None,
+        };
+        match extract_path_ident(path)?.as_ref() {
+            "JsValue" => Some(VectorType::JsValue),
+            _ => None,
+        }
+    }
+
+    fn from_vec(path: &syn::Path) -> Option<VectorType> {
+        if path.leading_colon.is_some() || path.segments.len() != 1 {
+            return None
+        }
+        let seg = path.segments.first()?.into_value();
+        if seg.ident.as_ref() != "Vec" {
+            return None
+        }
+        let generics = match seg.arguments {
+            syn::PathArguments::AngleBracketed(ref t) => t,
+            _ => return None,
+        };
+        if generics.args.len() != 1 {
+            return None
+        }
+        match *generics.args.first()?.into_value() {
+            syn::GenericArgument::Type(ref t) => VectorType::from_owned(t),
+            _ => None,
+        }
+    }
+
+    fn descriptor(&self, owned: bool) -> char {
+        match (*self, owned) {
+            (VectorType::I8, true) => shared::TYPE_VECTOR_I8,
+            (VectorType::U8, true) => shared::TYPE_VECTOR_U8,
+            (VectorType::I16, true) => shared::TYPE_VECTOR_I16,
+            (VectorType::U16, true) => shared::TYPE_VECTOR_U16,
+            (VectorType::I32, true) => shared::TYPE_VECTOR_I32,
+            (VectorType::U32, true) => shared::TYPE_VECTOR_U32,
+            (VectorType::F32, true) => shared::TYPE_VECTOR_F32,
+            (VectorType::F64, true) => shared::TYPE_VECTOR_F64,
+            (VectorType::I64, true) => shared::TYPE_VECTOR_I64,
+            (VectorType::U64, true) => shared::TYPE_VECTOR_U64,
+            (VectorType::ClampedU8, true) => shared::TYPE_VECTOR_U8_CLAMPED,
+            (VectorType::JsValue, _) => shared::TYPE_VECTOR_JSVALUE,
+            (VectorType::I8, false) => shared::TYPE_SLICE_I8,
+            (VectorType::U8, false) => shared::TYPE_SLICE_U8,
+            (VectorType::I16, false) => shared::TYPE_SLICE_I16,
+            (VectorType::U16, false) => shared::TYPE_SLICE_U16,
+            (VectorType::I32, false) => shared::TYPE_SLICE_I32,
+            (VectorType::U32, false) => shared::TYPE_SLICE_U32,
+            (VectorType::F32, false) => shared::TYPE_SLICE_F32,
+            (VectorType::F64, false) => shared::TYPE_SLICE_F64,
+            (VectorType::I64, false) => shared::TYPE_SLICE_I64,
+            (VectorType::U64, false) => shared::TYPE_SLICE_U64,
+            (VectorType::ClampedU8, false) => shared::TYPE_SLICE_U8_CLAMPED,
+        }
+    }
+
+    pub fn abi_element(&self) -> Tokens {
+        match *self {
+            VectorType::I8 => my_quote! { i8 },
+            VectorType::U8 |
+            VectorType::ClampedU8 => my_quote! { u8 },
+            VectorType::I16 => my_quote! { i16 },
+            VectorType::U16 => my_quote! { u16 },
+            VectorType::I32 => my_quote! { i32 },
+            VectorType::U32 => my_quote! { u32 },
+            VectorType::F32 => my_quote! { f32 },
+            VectorType::F64 => my_quote! { f64 },
+            VectorType::I64 => my_quote! { i64 },
+            VectorType::U64 => my_quote! { u64 },
+            VectorType::JsValue => my_quote! { ::wasm_bindgen::JsValue },
+        }
+    }
+}
+
 impl Export {
     pub fn rust_symbol(&self) -> syn::Ident {
         let mut generated_name = format!("__wasm_bindgen_generated");
@@ -468,11 +1287,53 @@ impl Export {
         syn::Ident::from(generated_name)
     }
 
+    /// Rejects attributes which don't fit the signature of the function.
+    fn check_attrs(&self) {
+        let opts = &self.function.opts;
+        let is_bytes = |t: &Type| {
+            match *t {
+                Type::Vector(VectorType::U8, true) => true,
+                _ => false,
+            }
+        };
+        if opts.blob().is_some() && !self.function.ret.as_ref().map_or(false, &is_bytes) {
+            panic!("the `blob` attribute can only be used on functions \
+                    returning `Vec<u8>`");
+        }
+        if opts.read_blobs() && !self.function.arguments.iter().any(&is_bytes) {
+            panic!("`read_blobs` functions must take a `Vec<u8>` argument");
+        }
+        if opts.readable_stream() &&
+            (self.function.arguments.len() > 0 ||
+             !self.function.ret.as_ref().map_or(false, &is_bytes) ||
+             self.optional_ret)
+        {
+            panic!("`readable_stream` functions must take no arguments \
+                    and return `Vec<u8>`");
+        }
+        if opts.stream_sink() &&
+            (self.function.arguments.len() != 1 ||
+             !is_bytes(&self.function.arguments[0]) ||
+             self.function.ret.is_some())
+        {
+            panic!("`stream_sink` functions must take a single `Vec<u8>` \
+                    argument and return nothing");
+        }
+    }
+
+    /// The name of the JS class this is exported on, if any.
+    pub fn class_name(&self) -> Option<&str> {
+        match self.js_class {
+            Some(ref s) => Some(s),
+            None => self.class.as_ref().map(|c| c.as_ref()),
+        }
+    }
+
     pub fn export_name(&self) -> syn::LitStr {
-        let name = match self.class {
+        let name = match self.class_name() {
             Some(class) => {
                 shared::struct_function_export_name(
-                    class.as_ref(),
+                    class,
                     self.function.name.as_ref(),
                 )
             }
@@ -486,18 +1347,89 @@ impl Export {
     fn wbg_literal(&self, a: &mut LiteralBuilder) {
         a.fields(&[
             ("class", &|a| {
-                match self.class {
-                    Some(ref s) => a.str(s.as_ref()),
+                match self.class_name() {
+                    Some(s) => a.str(s),
                     None => a.append("null"),
                 }
             }),
             ("method", &|a| a.bool(self.method)),
+            ("constant", &|a| a.bool(self.constant)),
+            ("iterator", &|a| a.bool(self.iterator)),
+            ("constructor", &|a| a.bool(self.function.opts.constructor())),
+            ("array_buffer", &|a| a.bool(self.function.opts.array_buffer())),
+            ("plain_object", &|a| a.bool(self.function.opts.plain_object())),
+            ("blob", &|a| {
+                match self.function.opts.blob() {
+                    Some(s) => a.str(s),
+                    None => a.append("null"),
+                }
+            }),
+            ("read_blobs", &|a| a.bool(self.function.opts.read_blobs())),
+            ("readable_stream", &|a| a.bool(self.function.opts.readable_stream())),
+            ("stream_sink", &|a| a.bool(self.function.opts.stream_sink())),
+            ("optional_args", &|a| a.append(&self.optional_args.to_string())),
+            ("optional_ret", &|a| a.bool(self.optional_ret)),
+            ("tuple_ret", &|a| {
+                a.list(&self.tuple_ret, |t, a| Type::ByValue(t.clone()).wbg_literal(a))
+            }),
+            ("error", &|a| {
+                match self.error {
+                    Some(ref t) => Type::ByValue(t.clone()).wbg_literal(a),
+                    None => a.append("null"),
+                }
+            }),
+            ("js_name", &|a| {
+                match self.function.opts.js_name() {
+                    Some(s) => a.str(s),
+                    None => a.append("null"),
+                }
+            }),
+            ("getter", &|a| {
+                match self.function.opts.getter() {
+                    Some(Some(name)) => a.str(name),
+                    Some(None) => a.str(self.function.name.as_ref()),
+                    None => a.append("null"),
+                }
+            }),
+            // `set_foo` defines the setter of the property `foo` unless the
+            // property is named
+            ("setter", &|a| {
+                let name = self.function.name.as_ref();
+                match self.function.opts.setter() {
+                    Some(Some(name)) => a.str(name),
+                    Some(None) if name.starts_with("set_") => a.str(&name[4..]),
+                    Some(None) => a.str(name),
+                    None => a.append("null"),
+                }
+            }),
+            ("comments", &|a| {
+                let comments = doc_comments(&self.function.rust_attrs);
+                a.list(&comments, |c, a| a.str(c))
+            }),
             ("function", &|a| self.function.wbg_literal(a)),
         ]);
     }
 }
 
 impl Import {
+    /// Returns the JS class that exceptions must be an instance of to be
+    /// caught, or `None` if any exception is caught as a `JsValue`.
+    pub fn catch_class(&self) -> Option<syn::Ident> {
+        let ty = self.catch_err.as_ref()?;
+        let ident = match *ty {
+            syn::Type::Path(syn::TypePath { qself: None, ref path }) => {
+                path.segments.last().map(|s| s.into_value().ident)
+            }
+            _ => None,
+        };
+        match ident {
+            Some(ref i) if i.as_ref() == "JsValue" => None,
+            Some(i) => Some(i),
+            None => panic!("`catch` error types must be `JsValue` or an \
+                            imported type"),
+        }
+    }
+
     fn wbg_literal(&self, a: &mut LiteralBuilder) {
         let mut method = false;
         let mut js_new = false;
@@ -526,9 +1458,24 @@ impl Import {
                 }
             }),
             ("catch", &|a| a.bool(self.function.opts.catch())),
+            ("catch_class", &|a| {
+                match self.catch_class() {
+                    Some(s) => a.str(s.as_ref()),
+                    None => a.append("null"),
+                }
+            }),
+            ("catch_global", &|a| a.bool(self.catch_global)),
+            ("optional_ret", &|a| a.bool(self.optional_ret)),
             ("method", &|a| a.bool(method)),
             ("js_new", &|a| a.bool(js_new)),
             ("statik", &|a| a.bool(statik)),
+            ("this_arg", &|a| a.bool(self.function.opts.this_arg())),
+            ("global", &|a| {
+                match self.function.opts.global() {
+                    Some(s) => a.str(s),
+                    None => a.append("null"),
+                }
+            }),
             ("function", &|a| self.function.wbg_literal(a)),
             ("class", &|a| {
                 match class_name {
@@ -541,14 +1488,450 @@ impl Import {
 }
 
 impl Struct {
-    fn from(s: syn::ItemStruct, _opts: BindgenAttrs) -> Struct {
-        Struct { name: s.ident }
+    fn from(s: &mut syn::ItemStruct, opts: BindgenAttrs) -> Struct {
+        let raw_view = if opts.raw_view() {
+            Some(RawView::from(s, opts.big_endian()))
+        } else {
+            None
+        };
+        let fields = match s.fields {
+            syn::Fields::Named(ref mut f) if raw_view.is_none() => {
+                f.named.iter_mut()
+                    .filter_map(|field| {
+                        let opts = BindgenAttrs::find(&mut field.attrs);
+                        match field.vis {
+                            syn::Visibility::Public(_) => {}
+                            _ => return None,
+                        }
+                        if opts.skip() {
+                            return None
+                        }
+                        let name = field.ident.unwrap();
+                        // Tuples can only be returned, so tuple fields are
+                        // read-only.
+                        let setter = match field.ty {
+                            syn::Type::Tuple(_) => None,
+                            _ if opts.readonly() => None,
+                            _ => Some(syn::Ident::from(format!("__wbg_set_{}", name))),
+                        };
+                        Some(StructField {
+                            name,
+                            ty: field.ty.clone(),
+                            attrs: field.attrs.clone(),
+                            getter: syn::Ident::from(format!("__wbg_get_{}", name)),
+                            setter,
+                        })
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        if raw_view.is_some() && opts.js_name().is_some() {
+            panic!("`js_name` can't be used on raw views");
+        }
+        if raw_view.is_some() && opts.display() {
+            panic!("`display` can't be used on raw views");
+        }
+        Struct {
+            name: s.ident,
+            js_name: opts.js_name().unwrap_or(s.ident.as_ref()).to_string(),
+            comments: doc_comments(&s.attrs),
+            typescript_implements: opts.typescript_implements().map(|s| s.to_string()),
+            raw_view,
+            unit: match s.fields {
+                syn::Fields::Unit => true,
+                _ => false,
+            },
+            free_method: opts.free().map(|s| s.to_string()),
+            fields,
+            to_string: if opts.display() {
+                Some(syn::Ident::from("__wbg_to_string"))
+            } else {
+                None
+            },
+        }
     }
-}
 
-struct LiteralBuilder<'a> {
-    dst: &'a mut Tokens,
-    cnt: usize,
+    fn wbg_raw_view_literal(&self, a: &mut LiteralBuilder) {
+        let view = self.raw_view.as_ref().unwrap();
+        a.fields(&[
+            ("name", &|a| a.str(self.name.as_ref())),
+            ("size", &|a| a.append(&view.size.to_string())),
+            ("comments", &|a| a.list(&self.comments, |c, a| a.str(c))),
+            ("fields", &|a| a.list(&view.fields, |f, a| {
+                a.fields(&[
+                    ("name", &|a| a.str(f.name.as_ref())),
+                    ("offset", &|a| a.append(&f.offset.to_string())),
+                    ("kind", &|a| a.str(f.kind)),
+                    ("little_endian", &|a| a.bool(f.little_endian)),
+                    ("comments", &|a| a.list(&f.comments, |c, a| a.str(c))),
+                ]);
+            })),
+        ]);
+    }
+}
+
+impl StringEnum {
+    fn from(e: &mut syn::ItemEnum, _opts: BindgenAttrs) -> StringEnum {
+        match e.vis {
+            syn::Visibility::Public(_) => {}
+            _ => panic!("only public enums are allowed"),
+        }
+        if e.generics.params.len() > 0 {
+            panic!("enums cannot be generic");
+        }
+        // The string values are written as discriminants, which rustc would
+        // reject, so they're stripped from the enum we emit.
+        let variants = e.variants.iter_mut()
+            .map(|v| {
+                match v.fields {
+                    syn::Fields::Unit => {}
+                    _ => panic!("only C-style enums are supported"),
+                }
+                let value = match v.discriminant.take() {
+                    Some((_, syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(ref s),
+                        ..
+                    }))) => s.value(),
+                    _ => panic!("enum variants must have a string value, \
+                                 like `Foo = \"foo\"`"),
+                };
+                (v.ident, value)
+            })
+            .collect();
+        StringEnum { name: e.ident, comments: doc_comments(&e.attrs), variants }
+    }
+
+    fn wbg_literal(&self, a: &mut LiteralBuilder) {
+        a.fields(&[
+            ("name", &|a| a.str(self.name.as_ref())),
+            ("descriptor", &|a| a.char(shared::name_to_descriptor(self.name.as_ref()))),
+            ("variants", &|a| a.list(&self.variants, |v, a| a.str(&v.1))),
+            ("comments", &|a| a.list(&self.comments, |c, a| a.str(c))),
+        ]);
+    }
+}
+
+impl Enum {
+    fn from(e: &syn::ItemEnum, _opts: BindgenAttrs) -> Enum {
+        match e.vis {
+            syn::Visibility::Public(_) => {}
+            _ => panic!("only public enums are allowed"),
+        }
+        if e.generics.params.len() > 0 {
+            panic!("enums cannot be generic");
+        }
+        // Values travel to JS as a `u32`, so any narrower `#[repr]` further
+        // limits what the discriminants may be.
+        let mut max = u32::max_value() as u64;
+        for attr in e.attrs.iter() {
+            let list = match attr.interpret_meta() {
+                Some(syn::Meta::List(list)) => list,
+                _ => continue,
+            };
+            if list.ident.as_ref() != "repr" {
+                continue
+            }
+            for nested in list.nested.iter() {
+                if let syn::NestedMeta::Meta(syn::Meta::Word(ref w)) = *nested {
+                    max = match w.as_ref() {
+                        "u8" => 0xff,
+                        "u16" => 0xffff,
+                        "u32" | "usize" => max,
+                        "i8" => 0x7f,
+                        "i16" => 0x7fff,
+                        "i32" | "isize" => 0x7fff_ffff,
+                        "C" => max,
+                        _ => panic!("unsupported #[repr] for an enum"),
+                    };
+                }
+            }
+        }
+        let mut next = 0u64;
+        let variants = e.variants.iter()
+            .map(|v| {
+                match v.fields {
+                    syn::Fields::Unit => {}
+                    _ => panic!("only C-style enums are supported"),
+                }
+                let value = match v.discriminant {
+                    Some((_, syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Int(ref i),
+                        ..
+                    }))) => i.value(),
+                    Some(_) => panic!("enum discriminants must be integer \
+                                       literals"),
+                    None => next,
+                };
+                if value > max {
+                    panic!("discriminant of `{}` doesn't fit in the enum's \
+                            representation", v.ident);
+                }
+                next = value + 1;
+                (v.ident, value as u32)
+            })
+            .collect();
+        Enum { name: e.ident, comments: doc_comments(&e.attrs), variants }
+    }
+
+    fn wbg_literal(&self, a: &mut LiteralBuilder) {
+        a.fields(&[
+            ("name", &|a| a.str(self.name.as_ref())),
+            ("descriptor", &|a| a.char(shared::name_to_descriptor(self.name.as_ref()))),
+            ("variants", &|a| a.list(&self.variants, |v, a| {
+                a.fields(&[
+                    ("name", &|a| a.str(v.0.as_ref())),
+                    ("value", &|a| a.append(&v.1.to_string())),
+                ]);
+            })),
+            ("comments", &|a| a.list(&self.comments, |c, a| a.str(c))),
+        ]);
+    }
+}
+
+impl TaggedEnum {
+    fn from(e: &syn::ItemEnum, _opts: BindgenAttrs) -> TaggedEnum {
+        match e.vis {
+            syn::Visibility::Public(_) => {}
+            _ => panic!("only public enums are allowed"),
+        }
+        if e.generics.params.len() > 0 {
+            panic!("enums cannot be generic");
+        }
+        let variants = e.variants.iter()
+            .map(|v| {
+                if v.discriminant.is_some() {
+                    panic!("enums with fields can't have discriminants");
+                }
+                let fields = match v.fields {
+                    syn::Fields::Unit => Vec::new(),
+                    syn::Fields::Named(ref f) => {
+                        f.named.iter()
+                            .map(|f| {
+                                let name = f.ident.unwrap();
+                                if name.as_ref() == "kind" {
+                                    panic!("`kind` is reserved for the variant \
+                                            of a tagged enum");
+                                }
+                                let kind = FieldKind::from(&f.ty)
+                                    .unwrap_or_else(|| {
+                                        panic!("unsupported type for field `{}`", name)
+                                    });
+                                (name, f.ty.clone(), kind)
+                            })
+                            .collect()
+                    }
+                    syn::Fields::Unnamed(_) => {
+                        panic!("enum variants with unnamed fields aren't \
+                                supported, use named fields instead")
+                    }
+                };
+                // `Circle` shows up in JS as `{ kind: "circle", ... }`
+                let name = v.ident.as_ref();
+                let kind = name[..1].to_lowercase() + &name[1..];
+                TaggedVariant { name: v.ident, kind, fields }
+            })
+            .collect();
+        TaggedEnum {
+            name: e.ident,
+            comments: doc_comments(&e.attrs),
+            variants,
+        }
+    }
+
+    fn wbg_literal(&self, a: &mut LiteralBuilder) {
+        a.fields(&[
+            ("name", &|a| a.str(self.name.as_ref())),
+            ("descriptor", &|a| a.char(shared::name_to_descriptor(self.name.as_ref()))),
+            ("variants", &|a| a.list(&self.variants, |v, a| {
+                a.fields(&[
+                    ("kind", &|a| a.str(&v.kind)),
+                    ("fields", &|a| a.list(&v.fields, |f, a| {
+                        a.fields(&[
+                            ("name", &|a| a.str(f.0.as_ref())),
+                            ("typescript", &|a| a.str(f.2.typescript())),
+                        ]);
+                    })),
+                ]);
+            })),
+            ("comments", &|a| a.list(&self.comments, |c, a| a.str(c))),
+        ]);
+    }
+}
+
+impl FieldKind {
+    fn from(ty: &syn::Type) -> Option<FieldKind> {
+        let path = match *ty {
+            syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
+            _ => return None,
+        };
+        match extract_path_ident(path)?.as_ref() {
+            "i8" | "u8" | "i16" | "u16" | "i32" | "u32" | "f32" | "f64" => {
+                Some(FieldKind::Number)
+            }
+            "bool" => Some(FieldKind::Boolean),
+            "String" => Some(FieldKind::String),
+            _ => None,
+        }
+    }
+
+    fn typescript(&self) -> &'static str {
+        match *self {
+            FieldKind::Number => "number",
+            FieldKind::Boolean => "boolean",
+            FieldKind::String => "string",
+        }
+    }
+}
+
+impl RawView {
+    fn from(s: &mut syn::ItemStruct, big_endian: bool) -> RawView {
+        let mut repr_c = false;
+        let mut packed = false;
+        for attr in s.attrs.iter() {
+            let list = match attr.interpret_meta() {
+                Some(syn::Meta::List(list)) => list,
+                _ => continue,
+            };
+            if list.ident.as_ref() != "repr" {
+                continue
+            }
+            for nested in list.nested.iter() {
+                if let syn::NestedMeta::Meta(syn::Meta::Word(ref w)) = *nested {
+                    match w.as_ref() {
+                        "C" => repr_c = true,
+                        "packed" => packed = true,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        if !repr_c {
+            panic!("raw_view structs must be #[repr(C)]");
+        }
+        if s.generics.params.len() > 0 {
+            panic!("raw_view structs cannot be generic");
+        }
+        let named = match s.fields {
+            syn::Fields::Named(ref mut f) => &mut f.named,
+            _ => panic!("raw_view structs must have named fields"),
+        };
+
+        // Lay the fields out the same way rustc does for `#[repr(C)]`, every
+        // supported field type being aligned to its own size.
+        let mut offset = 0;
+        let mut align = 1;
+        let mut fields = Vec::new();
+        for field in named.iter_mut() {
+            let opts = BindgenAttrs::find(&mut field.attrs);
+            let (kind, size) = raw_field_kind(&field.ty)
+                .expect("unsupported field type in raw_view struct, only \
+                         primitive integers and floats are supported");
+            if !packed {
+                offset = (offset + size - 1) / size * size;
+                align = cmp::max(align, size);
+            }
+            fields.push(RawField {
+                name: field.ident.unwrap(),
+                offset,
+                kind,
+                little_endian: !(big_endian || opts.big_endian()),
+                comments: doc_comments(&field.attrs),
+            });
+            offset += size;
+        }
+        let size = if packed {
+            offset
+        } else {
+            (offset + align - 1) / align * align
+        };
+        RawView { size, fields }
+    }
+}
+
+/// Returns the `#[cfg]` attributes in `attrs`.
+pub fn cfg_attrs(attrs: &[syn::Attribute]) -> Vec<syn::Attribute> {
+    attrs.iter()
+        .filter(|attr| attr.path.segments.len() == 1 &&
+                attr.path.segments[0].ident == "cfg")
+        .cloned()
+        .collect()
+}
+
+/// Returns the cargo features which `#[cfg]` attributes require to be enabled,
+/// such as `foo` and `bar` for `#[cfg(all(feature = "foo", feature = "bar"))]`.
+fn cfg_features(cfgs: &[syn::Attribute]) -> Vec<String> {
+    fn walk(meta: &syn::Meta, dst: &mut Vec<String>) {
+        match *meta {
+            syn::Meta::NameValue(ref nv) if nv.ident == "feature" => {
+                if let syn::Lit::Str(ref s) = nv.lit {
+                    dst.push(s.value());
+                }
+            }
+            syn::Meta::List(ref l) if l.ident == "cfg" || l.ident == "all" => {
+                for nested in l.nested.iter() {
+                    if let syn::NestedMeta::Meta(ref m) = *nested {
+                        walk(m, dst);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut features = Vec::new();
+    for attr in cfgs {
+        if let Some(meta) = attr.interpret_meta() {
+            walk(&meta, &mut features);
+        }
+    }
+    features
+}
+
+/// Collects the text of the `///` doc comments in `attrs`, one line each.
+fn doc_comments(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs.iter()
+        .filter_map(|attr| {
+            match attr.interpret_meta() {
+                Some(syn::Meta::NameValue(ref nv)) if nv.ident.as_ref() == "doc" => {
+                    match nv.lit {
+                        syn::Lit::Str(ref s) => Some(s.value()),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            }
+        })
+        .map(|s| {
+            let s = s.trim_right();
+            let s = if s.starts_with("///") { &s[3..] } else { s };
+            let s = if s.starts_with(' ') { &s[1..] } else { s };
+            s.to_string()
+        })
+        .collect()
+}
+
+fn raw_field_kind(ty: &syn::Type) -> Option<(&'static str, u32)> {
+    let path = match *ty {
+        syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
+        _ => return None,
+    };
+    let ret = match extract_path_ident(path)?.as_ref() {
+        "u8" => ("Uint8", 1),
+        "i8" => ("Int8", 1),
+        "u16" => ("Uint16", 2),
+        "i16" => ("Int16", 2),
+        "u32" => ("Uint32", 4),
+        "i32" => ("Int32", 4),
+        "f32" => ("Float32", 4),
+        "f64" => ("Float64", 8),
+        _ => return None,
+    };
+    Some(ret)
+}
+
+struct LiteralBuilder<'a> {
+    dst: &'a mut Tokens,
+    cnt: usize,
 }
 
 impl<'a> LiteralBuilder<'a> {
@@ -568,7 +1951,17 @@ impl<'a> LiteralBuilder<'a> {
 
     fn str(&mut self, s: &str) {
         self.append("\"");
-        self.append(s);
+        for c in s.chars() {
+            match c {
+                '"' => self.append("\\\""),
+                '\\' => self.append("\\\\"),
+                '\n' => self.append("\\n"),
+                c if (c as u32) < 0x20 => {
+                    self.append(&format!("\\u{:04x}", c as u32))
+                }
+                c => self.char_lit(c),
+            }
+        }
         self.append("\"");
     }
 
@@ -629,6 +2022,17 @@ pub struct BindgenAttrs {
 
 impl BindgenAttrs {
     pub fn find(attrs: &mut Vec<syn::Attribute>) -> BindgenAttrs {
+        // Nothing evaluates the condition of a `cfg_attr` before we get here,
+        // so it can't be used to pick options.
+        let cfg_attr = attrs.iter().any(|a| {
+            a.path.segments.len() == 1 &&
+                a.path.segments[0].ident == "cfg_attr" &&
+                a.tts.to_string().contains("wasm_bindgen")
+        });
+        if cfg_attr {
+            panic!("#[wasm_bindgen] options can't be set with `cfg_attr`, use \
+                    `cfg` on separate items instead");
+        }
         let pos = attrs.iter()
             .enumerate()
             .find(|&(_, ref m)| m.path.segments[0].ident == "wasm_bindgen")
@@ -662,6 +2066,16 @@ impl BindgenAttrs {
             })
     }
 
+    fn display(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::Display => true,
+                    _ => false,
+                }
+            })
+    }
+
     fn constructor(&self) -> bool {
         self.attrs.iter()
             .any(|a| {
@@ -672,6 +2086,52 @@ impl BindgenAttrs {
             })
     }
 
+    /// Returns `Some` if this is a getter, along with the name of its
+    /// property if one was given.
+    fn getter(&self) -> Option<Option<&str>> {
+        self.attrs.iter()
+            .filter_map(|a| {
+                match *a {
+                    BindgenAttr::Getter(ref s) => Some(s.as_ref().map(|s| &s[..])),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    /// Returns `Some` if this is a setter, along with the name of its
+    /// property if one was given.
+    fn setter(&self) -> Option<Option<&str>> {
+        self.attrs.iter()
+            .filter_map(|a| {
+                match *a {
+                    BindgenAttr::Setter(ref s) => Some(s.as_ref().map(|s| &s[..])),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    fn readonly(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::Readonly => true,
+                    _ => false,
+                }
+            })
+    }
+
+    fn skip(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::Skip => true,
+                    _ => false,
+                }
+            })
+    }
+
     fn method(&self) -> bool {
         self.attrs.iter()
             .any(|a| {
@@ -682,6 +2142,163 @@ impl BindgenAttrs {
             })
     }
 
+    pub fn array_buffer(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::ArrayBuffer => true,
+                    _ => false,
+                }
+            })
+    }
+
+    pub fn plain_object(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::PlainObject => true,
+                    _ => false,
+                }
+            })
+    }
+
+    fn blob(&self) -> Option<&str> {
+        self.attrs.iter()
+            .filter_map(|a| {
+                match *a {
+                    BindgenAttr::Blob(ref s) => Some(&s[..]),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    fn free(&self) -> Option<&str> {
+        self.attrs.iter()
+            .filter_map(|a| {
+                match *a {
+                    BindgenAttr::Free(ref s) => Some(&s[..]),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    fn js_name(&self) -> Option<&str> {
+        self.attrs.iter()
+            .filter_map(|a| {
+                match *a {
+                    BindgenAttr::JsName(ref s) => Some(&s[..]),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    fn js_class(&self) -> Option<&str> {
+        self.attrs.iter()
+            .filter_map(|a| {
+                match *a {
+                    BindgenAttr::JsClass(ref s) => Some(&s[..]),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    fn ascii(&self) -> Vec<&str> {
+        self.attrs.iter()
+            .filter_map(|a| {
+                match *a {
+                    BindgenAttr::Ascii(ref s) => Some(&s[..]),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    fn read_blobs(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::ReadBlobs => true,
+                    _ => false,
+                }
+            })
+    }
+
+    fn typescript_implements(&self) -> Option<&str> {
+        self.attrs.iter()
+            .filter_map(|a| {
+                match *a {
+                    BindgenAttr::TypescriptImplements(ref s) => Some(&s[..]),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    fn raw_view(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::RawView => true,
+                    _ => false,
+                }
+            })
+    }
+
+    fn big_endian(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::BigEndian => true,
+                    _ => false,
+                }
+            })
+    }
+
+    fn readable_stream(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::ReadableStream => true,
+                    _ => false,
+                }
+            })
+    }
+
+    fn stream_sink(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::StreamSink => true,
+                    _ => false,
+                }
+            })
+    }
+
+    fn global(&self) -> Option<&str> {
+        self.attrs.iter()
+            .filter_map(|a| {
+                match *a {
+                    BindgenAttr::Global(ref s) => Some(&s[..]),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    fn this_arg(&self) -> bool {
+        self.attrs.iter()
+            .any(|a| {
+                match *a {
+                    BindgenAttr::ThisArg => true,
+                    _ => false,
+                }
+            })
+    }
+
     fn static_receiver(&self) -> Option<&syn::Type> {
         self.attrs.iter()
             .filter_map(|a| {
@@ -710,21 +2327,83 @@ impl syn::synom::Synom for BindgenAttrs {
 }
 
 enum BindgenAttr {
+    ArrayBuffer,
+    Ascii(String),
+    BigEndian,
+    Blob(String),
     Catch,
     Constructor,
+    Display,
+    Free(String),
+    Getter(Option<String>),
+    Global(String),
+    JsClass(String),
+    JsName(String),
     Method,
+    PlainObject,
+    RawView,
+    ReadableStream,
+    ReadBlobs,
+    Readonly,
+    Setter(Option<String>),
+    Skip,
     Static(syn::Type),
+    StreamSink,
+    ThisArg,
+    TypescriptImplements(String),
     Module(String),
 }
 
 impl syn::synom::Synom for BindgenAttr {
     named!(parse -> Self, alt!(
+        call!(term, "array_buffer") => { |_| BindgenAttr::ArrayBuffer }
+        |
+        call!(term, "big_endian") => { |_| BindgenAttr::BigEndian }
+        |
         call!(term, "catch") => { |_| BindgenAttr::Catch }
         |
         call!(term, "constructor") => { |_| BindgenAttr::Constructor }
         |
+        call!(term, "display") => { |_| BindgenAttr::Display }
+        |
         call!(term, "method") => { |_| BindgenAttr::Method }
         |
+        call!(term, "plain_object") => { |_| BindgenAttr::PlainObject }
+        |
+        call!(term, "raw_view") => { |_| BindgenAttr::RawView }
+        |
+        call!(term, "read_blobs") => { |_| BindgenAttr::ReadBlobs }
+        |
+        call!(term, "readable_stream") => { |_| BindgenAttr::ReadableStream }
+        |
+        call!(term, "readonly") => { |_| BindgenAttr::Readonly }
+        |
+        call!(term, "skip") => { |_| BindgenAttr::Skip }
+        |
+        call!(term, "stream_sink") => { |_| BindgenAttr::StreamSink }
+        |
+        call!(term, "this_arg") => { |_| BindgenAttr::ThisArg }
+        |
+        do_parse!(
+            call!(term, "getter") >>
+            s: option!(do_parse!(
+                punct!(=) >>
+                s: syn!(syn::LitStr) >>
+                (s.value())
+            )) >>
+            (s)
+        )=> { BindgenAttr::Getter }
+        |
+        do_parse!(
+            call!(term, "setter") >>
+            s: option!(do_parse!(
+                punct!(=) >>
+                s: syn!(syn::LitStr) >>
+                (s.value())
+            )) >>
+            (s)
+        )=> { BindgenAttr::Setter }
+        |
         do_parse!(
             call!(term, "static") >>
             punct!(=) >>
@@ -732,6 +2411,55 @@ impl syn::synom::Synom for BindgenAttr {
             (s)
         )=> { BindgenAttr::Static }
         |
+        do_parse!(
+            call!(term, "ascii") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { BindgenAttr::Ascii }
+        |
+        do_parse!(
+            call!(term, "blob") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { BindgenAttr::Blob }
+        |
+        do_parse!(
+            call!(term, "free") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { BindgenAttr::Free }
+        |
+        do_parse!(
+            call!(term, "global") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { BindgenAttr::Global }
+        |
+        do_parse!(
+            call!(term, "js_class") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { BindgenAttr::JsClass }
+        |
+        do_parse!(
+            call!(term, "js_name") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { BindgenAttr::JsName }
+        |
+        do_parse!(
+            call!(term, "typescript_implements") >>
+            punct!(=) >>
+            s: syn!(syn::LitStr) >>
+            (s.value())
+        )=> { BindgenAttr::TypescriptImplements }
+        |
         do_parse!(
             call!(term, "module") >>
             punct!(=) >>
@@ -770,6 +2498,169 @@ fn extract_first_ty_param(ty: Option<&Type>) -> Option<Option<Type>> {
     Some(Some(Type::from(ty)))
 }
 
+fn extract_second_ty_param(ty: Option<&Type>) -> Option<syn::Type> {
+    let ty = match ty {
+        Some(&Type::ByValue(ref t)) => t,
+        _ => return None,
+    };
+    let path = match *ty {
+        syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
+        _ => return None,
+    };
+    let seg = path.segments.last()?.into_value();
+    let generics = match seg.arguments {
+        syn::PathArguments::AngleBracketed(ref t) => t,
+        _ => return None,
+    };
+    match *generics.args.iter().nth(1)? {
+        syn::GenericArgument::Type(ref t) => Some(t.clone()),
+        _ => None,
+    }
+}
+
+fn extract_vec_string(path: &syn::Path) -> Option<Type> {
+    if path.leading_colon.is_some() || path.segments.len() != 1 {
+        return None
+    }
+    let seg = path.segments.first()?.into_value();
+    if seg.ident.as_ref() != "Vec" {
+        return None
+    }
+    let generics = match seg.arguments {
+        syn::PathArguments::AngleBracketed(ref t) => t,
+        _ => return None,
+    };
+    if generics.args.len() != 1 {
+        return None
+    }
+    match *generics.args.first()?.into_value() {
+        syn::GenericArgument::Type(syn::Type::Path(syn::TypePath {
+            qself: None,
+            ref path,
+        })) => {
+            if extract_path_ident(path)?.as_ref() == "String" {
+                Some(Type::VecString)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Recognizes `HashMap<String, V>` and `BTreeMap<String, V>` where `V` is
+/// either `String` or `JsValue`, which JS sees as a `Map`.
+fn extract_map(path: &syn::Path) -> Option<MapValue> {
+    let seg = path.segments.iter().last()?;
+    match seg.ident.as_ref() {
+        "HashMap" | "BTreeMap" => {}
+        _ => return None,
+    }
+    let generics = match seg.arguments {
+        syn::PathArguments::AngleBracketed(ref t) => t,
+        _ => return None,
+    };
+    let mut args = generics.args.iter().map(|arg| {
+        match *arg {
+            syn::GenericArgument::Type(syn::Type::Path(syn::TypePath {
+                qself: None,
+                ref path,
+            })) => extract_path_ident(path),
+            _ => None,
+        }
+    });
+    match args.next()? {
+        Some(ref key) if key.as_ref() == "String" => {}
+        _ => return None,
+    }
+    match args.next()? {
+        Some(ref value) if value.as_ref() == "String" => Some(MapValue::String),
+        Some(ref value) if value.as_ref() == "JsValue" => Some(MapValue::JsValue),
+        _ => None,
+    }
+}
+
+/// Recognizes `Box<str>` and `Box<[T]>`, which cross the boundary just like
+/// `String` and `Vec<T>` but without any spare capacity.
+fn extract_box(path: &syn::Path) -> Option<Type> {
+    if path.leading_colon.is_some() || path.segments.len() != 1 {
+        return None
+    }
+    let seg = path.segments.first()?.into_value();
+    if seg.ident.as_ref() != "Box" {
+        return None
+    }
+    let generics = match seg.arguments {
+        syn::PathArguments::AngleBracketed(ref t) => t,
+        _ => return None,
+    };
+    if generics.args.len() != 1 {
+        return None
+    }
+    match *generics.args.first()?.into_value() {
+        syn::GenericArgument::Type(syn::Type::Path(syn::TypePath {
+            qself: None,
+            ref path,
+        })) => {
+            if extract_path_ident(path)?.as_ref() == "str" {
+                Some(Type::BoxedStr)
+            } else {
+                None
+            }
+        }
+        syn::GenericArgument::Type(syn::Type::Slice(ref slice)) => {
+            VectorType::from_owned(&slice.elem).map(Type::BoxedSlice)
+        }
+        _ => None,
+    }
+}
+
+fn extract_clamped(path: &syn::Path) -> Option<Type> {
+    let seg = path.segments.last()?.into_value();
+    if seg.ident.as_ref() != "Clamped" {
+        return None
+    }
+    let generics = match seg.arguments {
+        syn::PathArguments::AngleBracketed(ref t) => t,
+        _ => return None,
+    };
+    if generics.args.len() != 1 {
+        return None
+    }
+    let ty = match *generics.args.first()?.into_value() {
+        syn::GenericArgument::Type(ref t) => t,
+        _ => return None,
+    };
+    match Type::from(ty) {
+        Type::Vector(VectorType::U8, owned) => {
+            Some(Type::Vector(VectorType::ClampedU8, owned))
+        }
+        Type::BoxedSlice(VectorType::U8) => {
+            Some(Type::BoxedSlice(VectorType::ClampedU8))
+        }
+        _ => panic!("`Clamped` can only wrap `&[u8]`, `Vec<u8>` or `Box<[u8]>`"),
+    }
+}
+
+fn extract_option(ty: &syn::Type) -> Option<Type> {
+    let path = match *ty {
+        syn::Type::Path(syn::TypePath { qself: None, ref path }) => path,
+        _ => return None,
+    };
+    let seg = path.segments.last()?.into_value();
+    if seg.ident.as_ref() != "Option" {
+        return None
+    }
+    let generics = match seg.arguments {
+        syn::PathArguments::AngleBracketed(ref t) => t,
+        _ => return None,
+    };
+    match *generics.args.first()?.into_value() {
+        syn::GenericArgument::Type(ref t) => Some(Type::from(t)),
+        _ => None,
+    }
+}
+
 fn term<'a>(cursor: syn::buffer::Cursor<'a>, name: &str)
     -> syn::synom::PResult<'a, ()>
 {
@@ -10,6 +10,7 @@ extern crate proc_macro2;
 extern crate serde_json;
 extern crate wasm_bindgen_shared as shared;
 
+use std::mem;
 use std::sync::atomic::*;
 
 use proc_macro::TokenStream;
@@ -31,21 +32,114 @@ pub fn wasm_bindgen(attr: TokenStream, input: TokenStream) -> TokenStream {
 
     let mut ret = Tokens::new();
     let mut program = ast::Program::default();
-    program.push_item(item, Some(opts), &mut ret);
-    generate_wrappers(program, &mut ret);
+    program.cfgs = item_attrs(&item).map(ast::cfg_attrs).unwrap_or_default();
+    let cfgs = program.cfgs.clone();
+    cfg_items(&cfgs, &mut ret, |tokens| {
+        program.push_item(item, Some(opts), tokens);
+        generate_wrappers(program, tokens);
+    });
 
     // println!("{}", ret);
 
     ret.into()
 }
 
-// Generate wrappers for all the items that we've found
-fn generate_wrappers(program: ast::Program, tokens: &mut Tokens) {
+fn item_attrs(item: &syn::Item) -> Option<&[syn::Attribute]> {
+    match *item {
+        syn::Item::Fn(ref f) => Some(&f.attrs),
+        syn::Item::Struct(ref s) => Some(&s.attrs),
+        syn::Item::Enum(ref e) => Some(&e.attrs),
+        syn::Item::Impl(ref i) => Some(&i.attrs),
+        syn::Item::ForeignMod(ref f) => Some(&f.attrs),
+        syn::Item::Static(ref s) => Some(&s.attrs),
+        syn::Item::Const(ref c) => Some(&c.attrs),
+        syn::Item::Type(ref t) => Some(&t.attrs),
+        _ => None,
+    }
+}
+
+fn item_attrs_mut(item: &mut syn::Item) -> Option<&mut Vec<syn::Attribute>> {
+    match *item {
+        syn::Item::Fn(ref mut f) => Some(&mut f.attrs),
+        syn::Item::Struct(ref mut s) => Some(&mut s.attrs),
+        syn::Item::Enum(ref mut e) => Some(&mut e.attrs),
+        syn::Item::Impl(ref mut i) => Some(&mut i.attrs),
+        syn::Item::ForeignMod(ref mut f) => Some(&mut f.attrs),
+        syn::Item::Static(ref mut s) => Some(&mut s.attrs),
+        syn::Item::Const(ref mut c) => Some(&mut c.attrs),
+        syn::Item::Type(ref mut t) => Some(&mut t.attrs),
+        _ => None,
+    }
+}
+
+/// Emits the items generated by `f` with `cfgs` attached to each of them, so
+/// they're only compiled along with the items they were generated from.
+fn cfg_items<F>(cfgs: &[syn::Attribute], into: &mut Tokens, f: F)
+    where F: FnOnce(&mut Tokens)
+{
+    let mut tokens = Tokens::new();
+    f(&mut tokens);
+    if cfgs.len() == 0 {
+        return tokens.to_tokens(into)
+    }
+    let file = syn::parse::<syn::File>(tokens.into())
+        .expect("generated invalid items");
+    for mut item in file.items {
+        if let Some(attrs) = item_attrs_mut(&mut item) {
+            attrs.extend(cfgs.iter().cloned());
+        }
+        item.to_tokens(into);
+    }
+}
+
+// Generate wrappers for all the items that we've found, with any exports and
+// imports which are conditionally compiled split off into their own programs
+// so their metadata is only present when they are.
+fn generate_wrappers(mut program: ast::Program, tokens: &mut Tokens) {
+    let exports = mem::replace(&mut program.exports, Vec::new());
+    for export in exports {
+        let cfgs = ast::cfg_attrs(&export.function.rust_attrs);
+        if cfgs.len() == 0 {
+            program.exports.push(export);
+            continue
+        }
+        let mut sub = ast::Program::default();
+        sub.cfgs = program.cfgs.iter().chain(&cfgs).cloned().collect();
+        sub.exports.push(export);
+        cfg_items(&cfgs, tokens, |tokens| generate_program(sub, tokens));
+    }
+    let imports = mem::replace(&mut program.imports, Vec::new());
+    for import in imports {
+        let cfgs = ast::cfg_attrs(&import.function.rust_attrs);
+        if cfgs.len() == 0 {
+            program.imports.push(import);
+            continue
+        }
+        let mut sub = ast::Program::default();
+        sub.cfgs = program.cfgs.iter().chain(&cfgs).cloned().collect();
+        sub.imports.push(import);
+        cfg_items(&cfgs, tokens, |tokens| generate_program(sub, tokens));
+    }
+    generate_program(program, tokens);
+}
+
+fn generate_program(program: ast::Program, tokens: &mut Tokens) {
     for export in program.exports.iter() {
         bindgen_export(export, tokens);
     }
     for s in program.structs.iter() {
-        bindgen_struct(s, tokens);
+        if s.raw_view.is_none() {
+            bindgen_struct(s, tokens);
+        }
+    }
+    for e in program.string_enums.iter() {
+        bindgen_string_enum(e, tokens);
+    }
+    for e in program.enums.iter() {
+        bindgen_enum(e, tokens);
+    }
+    for e in program.tagged_enums.iter() {
+        bindgen_tagged_enum(e, tokens);
     }
     for i in program.imports.iter() {
         bindgen_import(i, tokens);
@@ -73,23 +167,178 @@ fn generate_wrappers(program: ast::Program, tokens: &mut Tokens) {
     }).to_tokens(tokens);
 }
 
+fn bindgen_string_enum(e: &ast::StringEnum, into: &mut Tokens) {
+    let name = &e.name;
+    let c = shared::name_to_descriptor(name.as_ref()) as u32;
+    let arms = e.variants.iter()
+        .enumerate()
+        .map(|(i, &(variant, _))| {
+            let i = i as u32;
+            my_quote! { #i => #name::#variant, }
+        })
+        .collect::<Vec<_>>();
+    (my_quote! {
+        impl ::wasm_bindgen::convert::WasmBoundary for #name {
+            type Js = u32;
+            const DESCRIPTOR: u32 = #c;
+
+            fn into_js(self) -> u32 {
+                self as u32
+            }
+
+            unsafe fn from_js(js: u32) -> Self {
+                match js {
+                    #(#arms)*
+                    _ => ::wasm_bindgen::throw("invalid enum value passed to rust"),
+                }
+            }
+        }
+    }).to_tokens(into);
+}
+
+fn bindgen_enum(e: &ast::Enum, into: &mut Tokens) {
+    let name = &e.name;
+    let c = shared::name_to_descriptor(name.as_ref()) as u32;
+    let arms = e.variants.iter()
+        .map(|&(variant, value)| {
+            my_quote! { #value => #name::#variant, }
+        })
+        .collect::<Vec<_>>();
+    (my_quote! {
+        impl ::wasm_bindgen::convert::WasmBoundary for #name {
+            type Js = u32;
+            const DESCRIPTOR: u32 = #c;
+
+            fn into_js(self) -> u32 {
+                self as u32
+            }
+
+            unsafe fn from_js(js: u32) -> Self {
+                match js {
+                    #(#arms)*
+                    _ => ::wasm_bindgen::throw("invalid enum value passed to rust"),
+                }
+            }
+        }
+    }).to_tokens(into);
+}
+
+fn bindgen_tagged_enum(e: &ast::TaggedEnum, into: &mut Tokens) {
+    let name = &e.name;
+    let c = shared::name_to_descriptor(name.as_ref()) as u32;
+    let mut into_arms = Vec::new();
+    let mut from_arms = Vec::new();
+    for v in e.variants.iter() {
+        let variant = &v.name;
+        let kind = &v.kind;
+        let names = v.fields.iter().map(|f| f.0).collect::<Vec<_>>();
+        let mut sets = Vec::new();
+        let mut gets = Vec::new();
+        for &(field, ref ty, field_kind) in v.fields.iter() {
+            let key = field.as_ref();
+            let (value, getter, convert) = match field_kind {
+                ast::FieldKind::Number => {
+                    (my_quote! { ::wasm_bindgen::JsValue::from(#field) },
+                     my_quote! { as_f64 },
+                     my_quote! { n as #ty })
+                }
+                ast::FieldKind::Boolean => {
+                    (my_quote! { ::wasm_bindgen::JsValue::from(#field) },
+                     my_quote! { as_bool },
+                     my_quote! { n })
+                }
+                ast::FieldKind::String => {
+                    (my_quote! { ::wasm_bindgen::JsValue::from(&#field) },
+                     my_quote! { as_string },
+                     my_quote! { n })
+                }
+            };
+            sets.push(my_quote! {
+                obj.set_property(#key, &#value);
+            });
+            let msg = format!("invalid `{}` field for `{}::{}`", key, name, variant);
+            gets.push(my_quote! {
+                obj.get_property(#key).#getter().map(|n| #convert).ok_or(#msg)?
+            });
+        }
+        if names.len() == 0 {
+            into_arms.push(my_quote! {
+                #name::#variant => {
+                    obj.set_property("kind", &::wasm_bindgen::JsValue::from(#kind));
+                }
+            });
+            from_arms.push(my_quote! {
+                Some(#kind) => Ok(#name::#variant),
+            });
+        } else {
+            let names2 = names.clone();
+            into_arms.push(my_quote! {
+                #name::#variant { #(#names),* } => {
+                    obj.set_property("kind", &::wasm_bindgen::JsValue::from(#kind));
+                    #(#sets)*
+                }
+            });
+            from_arms.push(my_quote! {
+                Some(#kind) => (|| -> Result<#name, &'static str> {
+                    Ok(#name::#variant { #(#names2: #gets),* })
+                })(),
+            });
+        }
+    }
+    let invalid = format!("invalid `kind` for `{}` passed to rust", name);
+    (my_quote! {
+        impl ::wasm_bindgen::convert::WasmBoundary for #name {
+            type Js = u32;
+            const DESCRIPTOR: u32 = #c;
+
+            fn into_js(self) -> u32 {
+                let obj = ::wasm_bindgen::JsValue::object();
+                match self {
+                    #(#into_arms)*
+                }
+                <::wasm_bindgen::JsValue as ::wasm_bindgen::convert::WasmBoundary>
+                    ::into_js(obj)
+            }
+
+            unsafe fn from_js(js: u32) -> Self {
+                let obj = <::wasm_bindgen::JsValue as ::wasm_bindgen::convert::WasmBoundary>
+                    ::from_js(js);
+                let kind = obj.get_property("kind").as_string();
+                let ret = match kind.as_ref().map(|s| &s[..]) {
+                    #(#from_arms)*
+                    _ => Err(#invalid),
+                };
+                // Throwing doesn't unwind, so make sure the object is
+                // released first.
+                drop(obj);
+                match ret {
+                    Ok(ret) => ret,
+                    Err(msg) => ::wasm_bindgen::throw(msg),
+                }
+            }
+        }
+    }).to_tokens(into);
+}
+
 fn bindgen_struct(s: &ast::Struct, into: &mut Tokens) {
     let name = &s.name;
-    let free_fn = syn::Ident::from(shared::free_function(s.name.as_ref()));
-    let c = shared::name_to_descriptor(name.as_ref()) as u32;
+    let free_fn = syn::Ident::from(shared::free_function(&s.js_name));
+    let c = shared::name_to_descriptor(&s.js_name) as u32;
     (my_quote! {
         impl ::wasm_bindgen::convert::WasmBoundary for #name {
             type Js = u32;
             const DESCRIPTOR: u32 = #c;
 
             fn into_js(self) -> u32 {
-                Box::into_raw(Box::new(::wasm_bindgen::__rt::WasmRefCell::new(self))) as u32
+                ::wasm_bindgen::__rt::std::boxed::Box::into_raw(
+                    ::wasm_bindgen::__rt::std::boxed::Box::new(
+                        ::wasm_bindgen::__rt::WasmRefCell::new(self))) as u32
             }
 
             unsafe fn from_js(js: u32) -> Self {
                 let js = js as *mut ::wasm_bindgen::__rt::WasmRefCell<#name>;
                 ::wasm_bindgen::__rt::assert_not_null(js);
-                let js = Box::from_raw(js);
+                let js = ::wasm_bindgen::__rt::std::boxed::Box::from_raw(js);
                 js.borrow_mut(); // make sure no one's borrowing
                 js.into_inner()
             }
@@ -119,6 +368,47 @@ fn bindgen_struct(s: &ast::Struct, into: &mut Tokens) {
             <#name as ::wasm_bindgen::convert::WasmBoundary>::from_js(ptr);
         }
     }).to_tokens(into);
+
+    if let Some(ref to_string) = s.to_string {
+        (my_quote! {
+            impl #name {
+                #[doc(hidden)]
+                pub fn #to_string(&self) -> ::wasm_bindgen::__rt::std::string::String {
+                    ::wasm_bindgen::__rt::std::string::ToString::to_string(self)
+                }
+            }
+        }).to_tokens(into);
+    }
+
+    // The accessors of `pub` fields are exported like any other method.
+    for field in s.fields.iter() {
+        let field_name = &field.name;
+        let ty = &field.ty;
+        let getter = &field.getter;
+        let cfgs = ast::cfg_attrs(&field.attrs);
+        let getter_cfgs = cfgs.iter();
+        (my_quote! {
+            impl #name {
+                #(#getter_cfgs)*
+                #[doc(hidden)]
+                pub fn #getter(&self) -> #ty {
+                    self.#field_name.clone()
+                }
+            }
+        }).to_tokens(into);
+        if let Some(ref setter) = field.setter {
+            let setter_cfgs = cfgs.iter();
+            (my_quote! {
+                impl #name {
+                    #(#setter_cfgs)*
+                    #[doc(hidden)]
+                    pub fn #setter(&mut self, value: #ty) {
+                        self.#field_name = value;
+                    }
+                }
+            }).to_tokens(into);
+        }
+    }
 }
 
 fn bindgen_export(export: &ast::Export, into: &mut Tokens) {
@@ -141,8 +431,66 @@ fn bindgen_export(export: &ast::Export, into: &mut Tokens) {
     }
 
     for (i, ty) in export.function.arguments.iter().enumerate() {
+        let optional = export.function.optional.contains(&i);
         let i = i + offset;
         let ident = syn::Ident::from(format!("arg{}", i));
+        if optional {
+            match *ty {
+                ast::Type::ByValue(ref t) => {
+                    let some = syn::Ident::from(format!("arg{}_some", i));
+                    args.push(my_quote! { #some: u32 });
+                    args.push(my_quote! {
+                        #ident: <#t as ::wasm_bindgen::convert::WasmBoundary>::Js
+                    });
+                    arg_conversions.push(my_quote! {
+                        let #ident = if #some != 0 {
+                            Some(unsafe {
+                                <#t as ::wasm_bindgen::convert::WasmBoundary>
+                                    ::from_js(#ident)
+                            })
+                        } else {
+                            None
+                        };
+                    });
+                }
+                // Missing strings are passed as a null pointer instead.
+                ast::Type::String => {
+                    let ptr = syn::Ident::from(format!("arg{}_ptr", i));
+                    let len = syn::Ident::from(format!("arg{}_len", i));
+                    args.push(my_quote! { #ptr: *mut u8 });
+                    args.push(my_quote! { #len: usize });
+                    arg_conversions.push(my_quote! {
+                        let #ident = if #ptr.is_null() {
+                            None
+                        } else {
+                            Some(unsafe {
+                                let vec = ::wasm_bindgen::__rt::std::vec::Vec::from_raw_parts(#ptr, #len, #len);
+                                ::wasm_bindgen::__rt::std::string::String::from_utf8_unchecked(vec)
+                            })
+                        };
+                    });
+                }
+                ast::Type::BorrowedStr => {
+                    let ptr = syn::Ident::from(format!("arg{}_ptr", i));
+                    let len = syn::Ident::from(format!("arg{}_len", i));
+                    args.push(my_quote! { #ptr: *const u8 });
+                    args.push(my_quote! { #len: usize });
+                    arg_conversions.push(my_quote! {
+                        let #ident = if #ptr.is_null() {
+                            None
+                        } else {
+                            Some(unsafe {
+                                let slice = ::wasm_bindgen::__rt::std::slice::from_raw_parts(#ptr, #len);
+                                ::wasm_bindgen::__rt::std::str::from_utf8_unchecked(slice)
+                            })
+                        };
+                    });
+                }
+                _ => unreachable!(),
+            }
+            converted_arguments.push(my_quote! { #ident });
+            continue
+        }
         match *ty {
             ast::Type::BorrowedStr => {
                 let ptr = syn::Ident::from(format!("arg{}_ptr", i));
@@ -151,11 +499,14 @@ fn bindgen_export(export: &ast::Export, into: &mut Tokens) {
                 args.push(my_quote! { #len: usize });
                 arg_conversions.push(my_quote! {
                     let #ident = unsafe {
-                        let slice = ::std::slice::from_raw_parts(#ptr, #len);
-                        ::std::str::from_utf8_unchecked(slice)
+                        let slice = ::wasm_bindgen::__rt::std::slice::from_raw_parts(#ptr, #len);
+                        ::wasm_bindgen::__rt::std::str::from_utf8_unchecked(slice)
                     };
                 });
             }
+            ast::Type::StaticStr => {
+                panic!("exported functions can't take `&'static str` arguments")
+            }
             ast::Type::String => {
                 let ptr = syn::Ident::from(format!("arg{}_ptr", i));
                 let len = syn::Ident::from(format!("arg{}_len", i));
@@ -163,8 +514,76 @@ fn bindgen_export(export: &ast::Export, into: &mut Tokens) {
                 args.push(my_quote! { #len: usize });
                 arg_conversions.push(my_quote! {
                     let #ident = unsafe {
-                        let vec = ::std::vec::Vec::from_raw_parts(#ptr, #len, #len);
-                        ::std::string::String::from_utf8_unchecked(vec)
+                        let vec = ::wasm_bindgen::__rt::std::vec::Vec::from_raw_parts(#ptr, #len, #len);
+                        ::wasm_bindgen::__rt::std::string::String::from_utf8_unchecked(vec)
+                    };
+                });
+            }
+            // JS allocates exactly `len` bytes, so converting to a box never
+            // needs to reallocate.
+            ast::Type::BoxedStr => {
+                let ptr = syn::Ident::from(format!("arg{}_ptr", i));
+                let len = syn::Ident::from(format!("arg{}_len", i));
+                args.push(my_quote! { #ptr: *mut u8 });
+                args.push(my_quote! { #len: usize });
+                arg_conversions.push(my_quote! {
+                    let #ident = unsafe {
+                        let vec = ::wasm_bindgen::__rt::std::vec::Vec::from_raw_parts(#ptr, #len, #len);
+                        ::wasm_bindgen::__rt::std::string::String::from_utf8_unchecked(vec)
+                            .into_boxed_str()
+                    };
+                });
+            }
+            // JS allocates with the alignment of the element type, so the
+            // memory can be adopted as a `Vec` of it. Empty arrays own no
+            // memory, whatever pointer came with them.
+            ast::Type::BoxedSlice(ty) => {
+                let ptr = syn::Ident::from(format!("arg{}_ptr", i));
+                let len = syn::Ident::from(format!("arg{}_len", i));
+                let elem = ty.abi_element();
+                args.push(my_quote! { #ptr: *mut #elem });
+                args.push(my_quote! { #len: usize });
+                arg_conversions.push(my_quote! {
+                    let #ident = unsafe {
+                        if #len == 0 {
+                            ::wasm_bindgen::__rt::std::vec::Vec::new()
+                        } else {
+                            ::wasm_bindgen::__rt::std::vec::Vec::from_raw_parts(#ptr, #len, #len)
+                        }.into_boxed_slice()
+                    };
+                });
+            }
+            ast::Type::Vector(ty, true) => {
+                let ptr = syn::Ident::from(format!("arg{}_ptr", i));
+                let len = syn::Ident::from(format!("arg{}_len", i));
+                let elem = ty.abi_element();
+                args.push(my_quote! { #ptr: *mut #elem });
+                args.push(my_quote! { #len: usize });
+                arg_conversions.push(my_quote! {
+                    let #ident = unsafe {
+                        if #len == 0 {
+                            ::wasm_bindgen::__rt::std::vec::Vec::new()
+                        } else {
+                            ::wasm_bindgen::__rt::std::vec::Vec::from_raw_parts(#ptr, #len, #len)
+                        }
+                    };
+                });
+            }
+            // Borrowed slices are copied into a temporary allocation by JS,
+            // which is freed once the call returns.
+            ast::Type::Vector(ty, false) => {
+                let ptr = syn::Ident::from(format!("arg{}_ptr", i));
+                let len = syn::Ident::from(format!("arg{}_len", i));
+                let elem = ty.abi_element();
+                args.push(my_quote! { #ptr: *const #elem });
+                args.push(my_quote! { #len: usize });
+                arg_conversions.push(my_quote! {
+                    let #ident: &[#elem] = unsafe {
+                        if #len == 0 {
+                            &[]
+                        } else {
+                            ::wasm_bindgen::__rt::std::slice::from_raw_parts(#ptr, #len)
+                        }
                     };
                 });
             }
@@ -203,15 +622,131 @@ fn bindgen_export(export: &ast::Export, into: &mut Tokens) {
                     let #ident = &mut *#ident;
                 });
             }
+            // 64-bit integers can't be passed to or from JS directly, so
+            // they're split into two halves.
+            ast::Type::Int64(signed) => {
+                let t = int64_type(signed);
+                let lo = syn::Ident::from(format!("arg{}_lo", i));
+                let hi = syn::Ident::from(format!("arg{}_hi", i));
+                args.push(my_quote! { #lo: u32 });
+                args.push(my_quote! { #hi: u32 });
+                arg_conversions.push(my_quote! {
+                    let #ident = ((#hi as u64) << 32 | #lo as u64) as #t;
+                });
+            }
+            // ... and 128-bit ones into four 32-bit limbs, least significant
+            // first.
+            ast::Type::Int128(signed) => {
+                let t = int128_type(signed);
+                let limbs = (0..4)
+                    .map(|j| syn::Ident::from(format!("arg{}_{}", i, j)))
+                    .collect::<Vec<_>>();
+                let limbs2 = limbs.clone();
+                let shifts = (0..4).map(|j| j * 32).collect::<Vec<u32>>();
+                args.extend(limbs.iter().map(|l| my_quote! { #l: u32 }));
+                arg_conversions.push(my_quote! {
+                    let #ident = (0 #(| (#limbs2 as u128) << #shifts)*) as #t;
+                });
+            }
+            ast::Type::VecString => {
+                let ptr = syn::Ident::from(format!("arg{}_ptr", i));
+                let len = syn::Ident::from(format!("arg{}_len", i));
+                args.push(my_quote! { #ptr: *const usize });
+                args.push(my_quote! { #len: usize });
+                arg_conversions.push(my_quote! {
+                    let #ident = unsafe {
+                        ::wasm_bindgen::__rt::strings_from_js(#ptr, #len)
+                    };
+                });
+            }
+            // Maps are passed as a list alternating between keys and values.
+            ast::Type::Map(ref t, ast::MapValue::String) => {
+                let ptr = syn::Ident::from(format!("arg{}_ptr", i));
+                let len = syn::Ident::from(format!("arg{}_len", i));
+                args.push(my_quote! { #ptr: *const usize });
+                args.push(my_quote! { #len: usize });
+                arg_conversions.push(my_quote! {
+                    let #ident: #t = unsafe {
+                        ::wasm_bindgen::__rt::string_map_from_js(#ptr, #len)
+                    };
+                });
+            }
+            ast::Type::Map(ref t, ast::MapValue::JsValue) => {
+                let ptr = syn::Ident::from(format!("arg{}_ptr", i));
+                let len = syn::Ident::from(format!("arg{}_len", i));
+                args.push(my_quote! { #ptr: *mut ::wasm_bindgen::JsValue });
+                args.push(my_quote! { #len: usize });
+                arg_conversions.push(my_quote! {
+                    let #ident: #t = unsafe {
+                        ::wasm_bindgen::__rt::js_map_from_js(#ptr, #len)
+                    };
+                });
+            }
+        }
+        if ty.is_clamped() {
+            converted_arguments.push(my_quote! { ::wasm_bindgen::Clamped(#ident) });
+        } else {
+            converted_arguments.push(my_quote! { #ident });
         }
-        converted_arguments.push(my_quote! { #ident });
     }
     let ret_ty;
     let convert_ret;
     match export.function.ret {
         Some(ast::Type::String) => {
-            ret_ty = my_quote! { -> *mut String };
-            convert_ret = my_quote! { Box::into_raw(Box::new(#ret)) };
+            ret_ty = my_quote! { -> *mut ::wasm_bindgen::__rt::std::string::String };
+            convert_ret = my_quote! {
+                ::wasm_bindgen::__rt::std::boxed::Box::into_raw(
+                    ::wasm_bindgen::__rt::std::boxed::Box::new(#ret))
+            };
+        }
+        Some(ast::Type::BoxedStr) => {
+            ret_ty = my_quote! { -> *mut ::wasm_bindgen::__rt::std::string::String };
+            convert_ret = my_quote! {
+                ::wasm_bindgen::__rt::std::boxed::Box::into_raw(
+                    ::wasm_bindgen::__rt::std::boxed::Box::new(#ret.into_string()))
+            };
+        }
+        Some(ast::Type::BoxedSlice(ty)) => {
+            let elem = ty.abi_element();
+            ret_ty = my_quote! { -> *mut #elem };
+            convert_ret = my_quote! {
+                let mut #ret = #ret;
+                ::wasm_bindgen::__rt::set_global_argument(0, #ret.len() as u32);
+                let ptr = #ret.as_mut_ptr();
+                ::wasm_bindgen::__rt::std::mem::forget(#ret);
+                ptr
+            };
+        }
+        Some(ast::Type::String) if export.optional_ret => {
+            ret_ty = my_quote! { -> *mut ::wasm_bindgen::__rt::std::string::String };
+            convert_ret = my_quote! {
+                match #ret {
+                    Some(ret) => {
+                        ::wasm_bindgen::__rt::std::boxed::Box::into_raw(
+                            ::wasm_bindgen::__rt::std::boxed::Box::new(ret))
+                    }
+                    None => ::wasm_bindgen::__rt::std::ptr::null_mut(),
+                }
+            };
+        }
+        // Whether an optional value is present goes through the first global
+        // argument slot, with a placeholder returned for `None`.
+        Some(ast::Type::ByValue(ref t)) if export.optional_ret => {
+            ret_ty = my_quote! {
+                -> <#t as ::wasm_bindgen::convert::WasmBoundary>::Js
+            };
+            convert_ret = my_quote! {
+                match #ret {
+                    Some(ret) => {
+                        ::wasm_bindgen::__rt::set_global_argument(0, 1);
+                        <#t as ::wasm_bindgen::convert::WasmBoundary>::into_js(ret)
+                    }
+                    None => {
+                        ::wasm_bindgen::__rt::set_global_argument(0, 0);
+                        unsafe { ::wasm_bindgen::__rt::std::mem::zeroed() }
+                    }
+                }
+            };
         }
         Some(ast::Type::ByValue(ref t)) => {
             ret_ty = my_quote! {
@@ -221,36 +756,165 @@ fn bindgen_export(export: &ast::Export, into: &mut Tokens) {
                 <#t as ::wasm_bindgen::convert::WasmBoundary>::into_js(#ret)
             };
         }
+        Some(ast::Type::Vector(ty, true)) => {
+            let elem = ty.abi_element();
+            ret_ty = my_quote! { -> *mut #elem };
+            convert_ret = my_quote! {
+                let mut #ret = #ret.into_boxed_slice();
+                ::wasm_bindgen::__rt::set_global_argument(0, #ret.len() as u32);
+                let ptr = #ret.as_mut_ptr();
+                ::wasm_bindgen::__rt::std::mem::forget(#ret);
+                ptr
+            };
+        }
+        // Borrowed slices returned from methods are handed to JS as a view of
+        // wasm memory rather than being copied out, so just return where the
+        // data lives and its length through the global argument slot.
+        Some(ast::Type::Vector(ty, false)) if export.method => {
+            let elem = ty.abi_element();
+            ret_ty = my_quote! { -> *const #elem };
+            convert_ret = my_quote! {
+                ::wasm_bindgen::__rt::set_global_argument(0, #ret.len() as u32);
+                #ret.as_ptr()
+            };
+        }
+        Some(ast::Type::VecString) => {
+            ret_ty = my_quote! { -> *mut usize };
+            convert_ret = my_quote! {
+                ::wasm_bindgen::__rt::strings_into_js(#ret)
+            };
+        }
+        Some(ast::Type::Map(_, ast::MapValue::String)) => {
+            ret_ty = my_quote! { -> *mut usize };
+            convert_ret = my_quote! {
+                ::wasm_bindgen::__rt::string_map_into_js(#ret)
+            };
+        }
+        Some(ast::Type::Map(_, ast::MapValue::JsValue)) => {
+            ret_ty = my_quote! { -> *mut ::wasm_bindgen::JsValue };
+            convert_ret = my_quote! {
+                ::wasm_bindgen::__rt::js_map_into_js(#ret)
+            };
+        }
+        // The high half goes through the first global argument slot.
+        Some(ast::Type::Int64(_)) => {
+            ret_ty = my_quote! { -> u32 };
+            convert_ret = my_quote! {
+                let #ret = #ret as u64;
+                ::wasm_bindgen::__rt::set_global_argument(0, (#ret >> 32) as u32);
+                #ret as u32
+            };
+        }
+        // As are the upper limbs of 128-bit integers, along with the two
+        // slots past the error.
+        Some(ast::Type::Int128(_)) => {
+            ret_ty = my_quote! { -> u32 };
+            convert_ret = my_quote! {
+                let #ret = #ret as u128;
+                ::wasm_bindgen::__rt::set_global_argument(0, (#ret >> 32) as u32);
+                ::wasm_bindgen::__rt::set_global_argument(3, (#ret >> 64) as u32);
+                ::wasm_bindgen::__rt::set_global_argument(4, (#ret >> 96) as u32);
+                #ret as u32
+            };
+        }
+        Some(ast::Type::Vector(..)) |
         Some(ast::Type::BorrowedStr) |
+        Some(ast::Type::StaticStr) |
         Some(ast::Type::ByMutRef(_)) |
         Some(ast::Type::ByRef(_)) => {
             panic!("can't return a borrowed ref");
         }
+        // Tuples are written element by element to a return area which JS
+        // reads them back from.
+        None if export.tuple_ret.len() > 0 => {
+            let tys = &export.tuple_ret;
+            let idxs = (0..tys.len()).collect::<Vec<_>>();
+            let elems = idxs.iter()
+                .map(|i| syn::Ident::from(format!("ret{}", i)))
+                .collect::<Vec<_>>();
+            // Signed integers are converted straight from the value, as
+            // `into_js` would hand over their bit pattern and lose the sign.
+            let values = tys.iter().zip(elems.iter()).map(|(ty, elem)| {
+                if ast::is_signed_int(ty) {
+                    my_quote! { #elem as f64 }
+                } else {
+                    my_quote! {
+                        <#ty as ::wasm_bindgen::convert::WasmBoundary>::into_js(#elem) as f64
+                    }
+                }
+            }).collect::<Vec<_>>();
+            ret_ty = my_quote! {};
+            convert_ret = my_quote! {
+                let (#(#elems,)*) = #ret;
+                #(
+                    ::wasm_bindgen::__rt::set_tuple_ret(#idxs, #values);
+                )*
+            };
+        }
         None => {
             ret_ty = my_quote! {};
             convert_ret = my_quote! {};
         }
     }
 
+    // `Clamped` buffers are returned just like the data they wrap.
+    let unclamp_ret = match export.function.ret {
+        Some(ref ty) if ty.is_clamped() => my_quote! { let #ret = #ret.0; },
+        _ => my_quote! {},
+    };
+
     let name = export.function.name;
     let receiver = match export.class {
         Some(_) if export.method => {
+            // Keep the borrow of `self` alive for the rest of the function so
+            // the return value may borrow from it.
             if export.mutable {
-                my_quote! { me.borrow_mut().#name }
+                arg_conversions.push(my_quote! {
+                    let mut me = me.borrow_mut();
+                });
             } else {
-                my_quote! { me.borrow().#name }
+                arg_conversions.push(my_quote! {
+                    let me = me.borrow();
+                });
             }
+            my_quote! { me.#name }
         }
         Some(class) => my_quote! { #class::#name },
         None => my_quote!{ #name },
     };
+    let mut call = if export.constant {
+        my_quote! { #receiver }
+    } else {
+        my_quote! { #receiver(#(#converted_arguments),*) }
+    };
+    // Errors are handed to JS through the global argument slots, along with a
+    // placeholder return value, for the JS wrapper to throw.
+    if let Some(ref err) = export.error {
+        call = my_quote! {
+            match #call {
+                Ok(ret) => {
+                    ::wasm_bindgen::__rt::set_global_argument(1, 0);
+                    ret
+                }
+                Err(e) => {
+                    ::wasm_bindgen::__rt::set_global_argument(1, 1);
+                    ::wasm_bindgen::__rt::set_global_argument(
+                        2,
+                        <#err as ::wasm_bindgen::convert::WasmBoundary>::into_js(e),
+                    );
+                    return unsafe { ::wasm_bindgen::__rt::std::mem::zeroed() }
+                }
+            }
+        };
+    }
 
     let tokens = my_quote! {
         #[export_name = #export_name]
         #[allow(non_snake_case)]
         pub extern fn #generated_name(#(#args),*) #ret_ty {
             #(#arg_conversions)*
-            let #ret = #receiver(#(#converted_arguments),*);
+            let #ret = #call;
+            #unclamp_ret
             #convert_ret
         }
     };
@@ -342,8 +1006,60 @@ fn bindgen_import(import: &ast::Import, tokens: &mut Tokens) {
         });
 
     for (i, (ty, name)) in import.function.arguments.iter().zip(names).enumerate() {
+        // `None` is passed as a flag saying the value is missing, followed
+        // by a placeholder value.
+        if import.function.optional.contains(&i) {
+            match *ty {
+                ast::Type::ByValue(ref t) => {
+                    let some = syn::Ident::from(format!("{}_some", name));
+                    abi_argument_names.push(some);
+                    abi_argument_names.push(name);
+                    abi_arguments.push(my_quote! { #some: u32 });
+                    abi_arguments.push(my_quote! {
+                        #name: <#t as ::wasm_bindgen::convert::WasmBoundary>::Js
+                    });
+                    arg_conversions.push(my_quote! {
+                        let (#some, #name) = match #name {
+                            Some(val) => {
+                                (1, <#t as ::wasm_bindgen::convert::WasmBoundary>::into_js(val))
+                            }
+                            None => (0, unsafe { ::wasm_bindgen::__rt::std::mem::zeroed() }),
+                        };
+                    });
+                }
+                // Missing strings are passed as a null pointer instead.
+                ast::Type::BorrowedStr |
+                ast::Type::String => {
+                    let ptr = syn::Ident::from(format!("{}_ptr", name));
+                    let len = syn::Ident::from(format!("{}_len", name));
+                    abi_argument_names.push(ptr);
+                    abi_argument_names.push(len);
+                    abi_arguments.push(my_quote! { #ptr: *const u8 });
+                    abi_arguments.push(my_quote! { #len: usize });
+                    let forget = match *ty {
+                        ast::Type::String => my_quote! {
+                            ::wasm_bindgen::__rt::std::mem::forget(s);
+                        },
+                        _ => my_quote! {},
+                    };
+                    arg_conversions.push(my_quote! {
+                        let (#ptr, #len) = match #name {
+                            Some(s) => {
+                                let ret = (s.as_ptr(), s.len());
+                                #forget
+                                ret
+                            }
+                            None => (::wasm_bindgen::__rt::std::ptr::null(), 0),
+                        };
+                    });
+                }
+                _ => unreachable!(),
+            }
+            continue
+        }
         match *ty {
-            ast::Type::BorrowedStr => {
+            ast::Type::BorrowedStr |
+            ast::Type::StaticStr => {
                 let ptr = syn::Ident::from(format!("{}_ptr", name));
                 let len = syn::Ident::from(format!("{}_len", name));
                 abi_argument_names.push(ptr);
@@ -373,6 +1089,33 @@ fn bindgen_import(import: &ast::Import, tokens: &mut Tokens) {
                 }
             }
             ast::Type::ByMutRef(_) => panic!("urgh mut"),
+            ast::Type::Int64(_) => {
+                let lo = syn::Ident::from(format!("{}_lo", name));
+                let hi = syn::Ident::from(format!("{}_hi", name));
+                abi_argument_names.push(lo);
+                abi_argument_names.push(hi);
+                abi_arguments.push(my_quote! { #lo: u32 });
+                abi_arguments.push(my_quote! { #hi: u32 });
+                arg_conversions.push(my_quote! {
+                    let #lo = #name as u64 as u32;
+                    let #hi = (#name as u64 >> 32) as u32;
+                });
+            }
+            ast::Type::Int128(_) => {
+                let limbs = (0..4)
+                    .map(|j| syn::Ident::from(format!("{}_{}", name, j)))
+                    .collect::<Vec<_>>();
+                let shifts = (0..4).map(|j| j * 32).collect::<Vec<u32>>();
+                abi_argument_names.extend(limbs.iter().cloned());
+                abi_arguments.extend(limbs.iter().map(|l| my_quote! { #l: u32 }));
+                arg_conversions.push(my_quote! {
+                    #(let #limbs = (#name as u128 >> #shifts) as u32;)*
+                });
+            }
+            ast::Type::Vector(..) |
+            ast::Type::VecString |
+            ast::Type::BoxedSlice(_) => panic!("slices aren't supported in imports yet"),
+            ast::Type::Map(..) => panic!("maps aren't supported in imports yet"),
             ast::Type::ByRef(ref t) => {
                 abi_argument_names.push(name);
                 abi_arguments.push(my_quote! { #name: u32 });
@@ -389,7 +1132,7 @@ fn bindgen_import(import: &ast::Import, tokens: &mut Tokens) {
                 }
             }
             // TODO: need to test this
-            ast::Type::String => {
+            ast::Type::String | ast::Type::BoxedStr => {
                 let ptr = syn::Ident::from(format!("{}_ptr", name));
                 let len = syn::Ident::from(format!("{}_len", name));
                 abi_argument_names.push(ptr);
@@ -399,7 +1142,7 @@ fn bindgen_import(import: &ast::Import, tokens: &mut Tokens) {
                 arg_conversions.push(my_quote! {
                     let #ptr = #name.as_ptr();
                     let #len = #name.len();
-                    ::std::mem::forget(#name);
+                    ::wasm_bindgen::__rt::std::mem::forget(#name);
                 });
             }
         }
@@ -428,12 +1171,69 @@ fn bindgen_import(import: &ast::Import, tokens: &mut Tokens) {
             });
             abi_ret = my_quote! { *mut u8 };
             convert_ret = my_quote! {
-                String::from_utf8_unchecked(
-                    Vec::from_raw_parts(#ret_ident, #name, #name)
+                ::wasm_bindgen::__rt::std::string::String::from_utf8_unchecked(
+                    ::wasm_bindgen::__rt::std::vec::Vec::from_raw_parts(#ret_ident, #name, #name)
                 )
             };
         }
+        Some(ast::Type::BoxedStr) => {
+            let name = syn::Ident::from("__ret_strlen");
+            let name_ptr = syn::Ident::from("__ret_strlen_ptr");
+            abi_argument_names.push(name_ptr);
+            abi_arguments.push(my_quote! { #name_ptr: *mut usize });
+            arg_conversions.push(my_quote! {
+                let mut #name = 0;
+                let mut #name_ptr = &mut #name as *mut usize;
+            });
+            abi_ret = my_quote! { *mut u8 };
+            convert_ret = my_quote! {
+                ::wasm_bindgen::__rt::std::string::String::from_utf8_unchecked(
+                    ::wasm_bindgen::__rt::std::vec::Vec::from_raw_parts(#ret_ident, #name, #name)
+                ).into_boxed_str()
+            };
+        }
+        Some(ast::Type::BoxedSlice(_)) |
+        Some(ast::Type::VecString) => {
+            panic!("slices aren't supported in imports yet")
+        }
+        Some(ast::Type::Map(..)) => panic!("maps aren't supported in imports yet"),
+        Some(ast::Type::Int64(signed)) => {
+            let t = int64_type(signed);
+            let name = syn::Ident::from("__ret_hi");
+            let name_ptr = syn::Ident::from("__ret_hi_ptr");
+            abi_argument_names.push(name_ptr);
+            abi_arguments.push(my_quote! { #name_ptr: *mut u32 });
+            arg_conversions.push(my_quote! {
+                let mut #name = 0;
+                let mut #name_ptr = &mut #name as *mut u32;
+            });
+            abi_ret = my_quote! { u32 };
+            convert_ret = my_quote! {
+                ((#name as u64) << 32 | #ret_ident as u64) as #t
+            };
+        }
+        // The upper limbs are written to an array of three
+        Some(ast::Type::Int128(signed)) => {
+            let t = int128_type(signed);
+            let name = syn::Ident::from("__ret_hi");
+            let name_ptr = syn::Ident::from("__ret_hi_ptr");
+            abi_argument_names.push(name_ptr);
+            abi_arguments.push(my_quote! { #name_ptr: *mut u32 });
+            arg_conversions.push(my_quote! {
+                let mut #name = [0u32; 3];
+                let mut #name_ptr = #name.as_mut_ptr();
+            });
+            abi_ret = my_quote! { u32 };
+            convert_ret = my_quote! {
+                ((#name[2] as u128) << 96 |
+                 (#name[1] as u128) << 64 |
+                 (#name[0] as u128) << 32 |
+                 #ret_ident as u128) as #t
+            };
+        }
         Some(ast::Type::BorrowedStr) |
+        Some(ast::Type::StaticStr) |
+        Some(ast::Type::Vector(..)) |
         Some(ast::Type::ByRef(_)) |
         Some(ast::Type::ByMutRef(_)) => panic!("can't return a borrowed ref"),
         None => {
@@ -442,6 +1242,24 @@ fn bindgen_import(import: &ast::Import, tokens: &mut Tokens) {
         }
     }
 
+    if import.optional_ret {
+        let none = syn::Ident::from("__ret_none");
+        let none_ptr = syn::Ident::from("__ret_none_ptr");
+        abi_argument_names.push(none_ptr);
+        abi_arguments.push(my_quote! { #none_ptr: *mut u32 });
+        arg_conversions.push(my_quote! {
+            let mut #none = 0;
+            let #none_ptr = &mut #none as *mut u32;
+        });
+        convert_ret = my_quote! {
+            if #none == 1 {
+                None
+            } else {
+                Some(#convert_ret)
+            }
+        };
+    }
+
     let mut exceptional_ret = my_quote! {};
     if import.function.opts.catch() {
         let exn_data = syn::Ident::from("exn_data");
@@ -452,13 +1270,24 @@ fn bindgen_import(import: &ast::Import, tokens: &mut Tokens) {
             let mut #exn_data = [0; 2];
             let #exn_data_ptr = #exn_data.as_mut_ptr();
         });
+        let err = import.catch_err.as_ref().unwrap();
         convert_ret = my_quote! { Ok(#convert_ret) };
         exceptional_ret = my_quote! {
             if #exn_data[0] == 1 {
-                return Err(<::wasm_bindgen::JsValue as
+                return Err(<#err as
                     ::wasm_bindgen::convert::WasmBoundary>::from_js(#exn_data[1]))
             }
         };
+        // Exceptions which aren't of the declared error type are flagged
+        // with a `2`, and can only keep propagating.
+        if import.catch_class().is_some() {
+            exceptional_ret = my_quote! {
+                #exceptional_ret
+                if #exn_data[0] == 2 {
+                    ::wasm_bindgen::__rt::rethrow(#exn_data[1]);
+                }
+            };
+        }
     }
 
     let name = import.function.name;
@@ -501,3 +1330,11 @@ fn bindgen_import(import: &ast::Import, tokens: &mut Tokens) {
         invocation.to_tokens(tokens);
     }
 }
+
+fn int64_type(signed: bool) -> syn::Ident {
+    syn::Ident::from(if signed { "i64" } else { "u64" })
+}
+
+fn int128_type(signed: bool) -> syn::Ident {
+    syn::Ident::from(if signed { "i128" } else { "u128" })
+}
@@ -10,15 +10,26 @@ pub struct Program {
     pub exports: Vec<Export>,
     pub imports: Vec<Import>,
     pub custom_type_names: Vec<CustomTypeName>,
+    pub structs: Vec<Struct>,
+    pub raw_views: Vec<RawView>,
+    pub string_enums: Vec<StringEnum>,
+    pub enums: Vec<Enum>,
+    pub tagged_enums: Vec<TaggedEnum>,
+    pub features: Vec<String>,
 }
 
 #[derive(Deserialize)]
 pub struct Import {
     pub module: Option<String>,
     pub catch: bool,
+    pub catch_class: Option<String>,
+    pub catch_global: bool,
+    pub optional_ret: bool,
     pub method: bool,
     pub js_new: bool,
     pub statik: bool,
+    pub this_arg: bool,
+    pub global: Option<String>,
     pub class: Option<String>,
     pub function: Function,
 }
@@ -27,6 +38,23 @@ pub struct Import {
 pub struct Export {
     pub class: Option<String>,
     pub method: bool,
+    pub constant: bool,
+    pub constructor: bool,
+    pub iterator: bool,
+    pub array_buffer: bool,
+    pub plain_object: bool,
+    pub blob: Option<String>,
+    pub read_blobs: bool,
+    pub readable_stream: bool,
+    pub stream_sink: bool,
+    pub optional_args: usize,
+    pub optional_ret: bool,
+    pub tuple_ret: Vec<Type>,
+    pub error: Option<Type>,
+    pub js_name: Option<String>,
+    pub getter: Option<String>,
+    pub setter: Option<String>,
+    pub comments: Vec<String>,
     pub function: Function,
 }
 
@@ -35,6 +63,76 @@ pub struct Function {
     pub name: String,
     pub arguments: Vec<Type>,
     pub ret: Option<Type>,
+    pub ascii: Vec<usize>,
+    pub optional: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct Struct {
+    pub name: String,
+    pub comments: Vec<String>,
+    pub typescript_implements: Option<String>,
+    pub unit: bool,
+    pub free_method: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RawView {
+    pub name: String,
+    pub size: u32,
+    pub comments: Vec<String>,
+    pub fields: Vec<RawField>,
+}
+
+#[derive(Deserialize)]
+pub struct RawField {
+    pub name: String,
+    pub offset: u32,
+    pub kind: String,
+    pub little_endian: bool,
+    pub comments: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct StringEnum {
+    pub name: String,
+    pub descriptor: char,
+    pub variants: Vec<String>,
+    pub comments: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Enum {
+    pub name: String,
+    pub descriptor: char,
+    pub variants: Vec<EnumVariant>,
+    pub comments: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct EnumVariant {
+    pub name: String,
+    pub value: u32,
+}
+
+#[derive(Deserialize)]
+pub struct TaggedEnum {
+    pub name: String,
+    pub descriptor: char,
+    pub variants: Vec<TaggedVariant>,
+    pub comments: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TaggedVariant {
+    pub kind: String,
+    pub fields: Vec<TaggedField>,
+}
+
+#[derive(Deserialize)]
+pub struct TaggedField {
+    pub name: String,
+    pub typescript: String,
 }
 
 #[derive(Deserialize)]
@@ -81,18 +179,56 @@ pub const TYPE_STRING: char = '\u{60}';
 pub const TYPE_BOOLEAN: char = '\u{61}';
 pub const TYPE_JS_OWNED: char = '\u{62}';
 pub const TYPE_JS_REF: char = '\u{63}';
+pub const TYPE_SLICE_F32: char = '\u{64}';
+pub const TYPE_SLICE_U8: char = '\u{65}';
+pub const TYPE_VECTOR_U8: char = '\u{66}';
+pub const TYPE_STATIC_STR: char = '\u{67}';
+pub const TYPE_SLICE_I64: char = '\u{68}';
+pub const TYPE_SLICE_U64: char = '\u{69}';
+pub const TYPE_SLICE_I8: char = '\u{6a}';
+pub const TYPE_SLICE_U16: char = '\u{6b}';
+pub const TYPE_SLICE_I16: char = '\u{6c}';
+pub const TYPE_SLICE_I32: char = '\u{6d}';
+pub const TYPE_SLICE_U32: char = '\u{6e}';
+pub const TYPE_SLICE_F64: char = '\u{6f}';
+pub const TYPE_VECTOR_I8: char = '\u{70}';
+pub const TYPE_VECTOR_I16: char = '\u{71}';
+pub const TYPE_VECTOR_U16: char = '\u{72}';
+pub const TYPE_VECTOR_I32: char = '\u{73}';
+pub const TYPE_VECTOR_U32: char = '\u{74}';
+pub const TYPE_VECTOR_F32: char = '\u{75}';
+pub const TYPE_VECTOR_F64: char = '\u{76}';
+pub const TYPE_VECTOR_I64: char = '\u{77}';
+pub const TYPE_VECTOR_U64: char = '\u{78}';
+pub const TYPE_CHAR: char = '\u{79}';
+pub const TYPE_U64: char = '\u{7a}';
+pub const TYPE_I64: char = '\u{7b}';
+pub const TYPE_VECTOR_JSVALUE: char = '\u{7c}';
+pub const TYPE_VECTOR_STRING: char = '\u{7d}';
+pub const TYPE_MAP_STRING: char = '\u{7e}';
+pub const TYPE_MAP_JSVALUE: char = '\u{7f}';
+pub const TYPE_DATE: char = '\u{80}';
+pub const TYPE_U128: char = '\u{81}';
+pub const TYPE_I128: char = '\u{82}';
+pub const TYPE_SLICE_U8_CLAMPED: char = '\u{83}';
+pub const TYPE_VECTOR_U8_CLAMPED: char = '\u{84}';
 
-pub const TYPE_CUSTOM_START: u32 = 0x64;
+// must be even, since the lowest bit flags references to custom types
+pub const TYPE_CUSTOM_START: u32 = 0x86;
 pub const TYPE_CUSTOM_REF_FLAG: u32 = 1;
 
+// Descriptors from here up to `char::MAX` are never given to exported types,
+// leaving a range which doesn't move as builtin types are added for the
+// `TypeHook`s of the CLI.
+pub const TYPE_HOOK_START: u32 = 0x10f000;
+
 pub fn name_to_descriptor(name: &str) -> char {
-    const CHAR_MAX: u32 = 0x10ffff;
     const CHAR_HOLE_START: u32 = 0xd800;
     const CHAR_HOLE_END: u32 = 0xe000;
     let mut h = fnv::FnvHasher::default();
     name.hash(&mut h);
     let val = h.finish();
-    let range = (CHAR_MAX - (CHAR_HOLE_END - CHAR_HOLE_START) - TYPE_CUSTOM_START) / 2;
+    let range = (TYPE_HOOK_START - (CHAR_HOLE_END - CHAR_HOLE_START) - TYPE_CUSTOM_START) / 2;
     let idx = (val % (range as u64)) as u32;
     let mut ret = TYPE_CUSTOM_START + idx * 2;
     if CHAR_HOLE_START <= ret && ret < CHAR_HOLE_END {
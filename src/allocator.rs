@@ -0,0 +1,248 @@
+//! Control over the memory which the generated JS allocates in wasm, for
+//! example to copy strings and slices passed to exported functions.
+//!
+//! By default this goes through Rust's global allocator, but a crate can
+//! install its own `Allocator` with `set_allocator`, either replacing the
+//! default or wrapping `Global` to observe what the bindings allocate.
+
+use std::boxed::Box;
+use std::cmp;
+use std::mem;
+use std::ptr;
+use std::string::String;
+use std::vec::Vec;
+
+/// The allocation functions used by the glue generated by `wasm-bindgen`.
+///
+/// Every request comes with the alignment of the elements being stored,
+/// which is one of 1, 2, 4 or 8, and `size` is always a multiple of it.
+pub trait Allocator {
+    /// Allocates `size` bytes aligned to `align`.
+    ///
+    /// Memory handed to Rust by value, such as a `String` or `Vec<f64>`
+    /// argument, is later released by Rust's global allocator, and vectors
+    /// returned to JS are released with `free`. Implementations which don't
+    /// defer to `Global` shouldn't be used with such arguments or return
+    /// values.
+    fn malloc(&self, size: usize, align: usize) -> *mut u8;
+
+    /// Frees `size` bytes at `ptr` previously returned from `malloc` with the
+    /// same `align`.
+    unsafe fn free(&self, ptr: *mut u8, size: usize, align: usize);
+
+    /// Resizes the `old_size` bytes at `ptr` previously returned from
+    /// `malloc` to `new_size` bytes, preserving their contents up to the
+    /// smaller of the two sizes.
+    ///
+    /// The default implementation allocates anew and copies, while `Global`
+    /// grows or shrinks in place when it can.
+    unsafe fn realloc(&self,
+                      ptr: *mut u8,
+                      old_size: usize,
+                      new_size: usize,
+                      align: usize) -> *mut u8
+    {
+        let ret = self.malloc(new_size, align);
+        ptr::copy_nonoverlapping(ptr, ret, cmp::min(old_size, new_size));
+        self.free(ptr, old_size, align);
+        ret
+    }
+
+    /// Frees a `String` boxed up to return it to JS, once JS has copied it.
+    unsafe fn free_boxed_str(&self, ptr: *mut String) {
+        drop(Box::from_raw(ptr));
+    }
+}
+
+/// The default `Allocator`, using Rust's global allocator.
+///
+/// Memory is allocated as a `Vec` of an integer type with the requested
+/// alignment, so it has the same layout as the `String` or `Vec<T>` which
+/// Rust later rebuilds from it. Empty allocations don't touch the global
+/// allocator at all, and are a dangling pointer aligned to `align`.
+pub struct Global;
+
+macro_rules! with_element {
+    ($align:expr, $f:ident($($arg:expr),*)) => {
+        match $align {
+            1 => $f::<u8>($($arg),*),
+            2 => $f::<u16>($($arg),*),
+            4 => $f::<u32>($($arg),*),
+            8 => $f::<u64>($($arg),*),
+            _ => super::throw("invalid malloc alignment"),
+        }
+    }
+}
+
+impl Allocator for Global {
+    fn malloc(&self, size: usize, align: usize) -> *mut u8 {
+        check_size(size, align);
+        with_element!(align, malloc_as(size))
+    }
+
+    unsafe fn free(&self, ptr: *mut u8, size: usize, align: usize) {
+        with_element!(align, free_as(ptr, size))
+    }
+
+    unsafe fn realloc(&self,
+                      ptr: *mut u8,
+                      old_size: usize,
+                      new_size: usize,
+                      align: usize) -> *mut u8
+    {
+        check_size(new_size, align);
+        with_element!(align, realloc_as(ptr, old_size, new_size))
+    }
+}
+
+fn malloc_as<T>(size: usize) -> *mut u8 {
+    let mut ret = Vec::<T>::with_capacity(size / mem::size_of::<T>());
+    let ptr = ret.as_mut_ptr();
+    mem::forget(ret);
+    ptr as *mut u8
+}
+
+unsafe fn free_as<T>(ptr: *mut u8, size: usize) {
+    drop(Vec::<T>::from_raw_parts(ptr as *mut T, 0, size / mem::size_of::<T>()));
+}
+
+unsafe fn realloc_as<T>(ptr: *mut u8, old_size: usize, new_size: usize) -> *mut u8 {
+    let old_cap = old_size / mem::size_of::<T>();
+    let new_cap = new_size / mem::size_of::<T>();
+    let len = cmp::min(old_cap, new_cap);
+    let mut v = Vec::<T>::from_raw_parts(ptr as *mut T, len, old_cap);
+    if new_cap > old_cap {
+        v.reserve_exact(new_cap - len);
+    } else {
+        v.shrink_to_fit();
+    }
+    let ret = v.as_mut_ptr();
+    mem::forget(v);
+    ret as *mut u8
+}
+
+fn check_size(size: usize, align: usize) {
+    // Any malloc request this big is bogus anyway. If this actually
+    // goes down to `Vec` we trigger a whole bunch of panicking
+    // machinery to get pulled in from libstd anyway as it'll verify
+    // the size passed in below.
+    //
+    // Head this all off by just aborting on too-big sizes. This
+    // avoids panicking (code bloat) and gives a better error
+    // message too hopefully.
+    if size >= usize::max_value() / 2 || align == 0 || size % align != 0 {
+        super::throw("invalid malloc request");
+    }
+}
+
+static mut ALLOCATOR: &'static Allocator = &Global;
+
+/// Installs `allocator` for all allocations made by the bindings from now on.
+///
+/// Memory is always freed by whichever allocator is installed at the time,
+/// so this should be called before any values are passed in from JS,
+/// typically from a function JS calls first.
+pub fn set_allocator(allocator: &'static Allocator) {
+    unsafe {
+        ALLOCATOR = allocator;
+    }
+}
+
+/// Returns the `Allocator` currently in use.
+pub fn allocator() -> &'static Allocator {
+    unsafe { ALLOCATOR }
+}
+
+/// Counters of the allocations made through the bindings, readable from JS
+/// with the generated `allocationStats()` function.
+///
+/// Only `Allocator::malloc`, `Allocator::free` and `Allocator::realloc` are
+/// counted, so memory which JS hands over to Rust and Rust frees itself never
+/// shows up as freed. The byte totals treat a `realloc` as freeing the old
+/// size and allocating the new one.
+#[cfg(feature = "alloc-accounting")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stats {
+    /// Number of calls to `malloc`.
+    pub mallocs: u32,
+    /// Total bytes requested from `malloc`.
+    pub malloc_bytes: u32,
+    /// Number of calls to `free`.
+    pub frees: u32,
+    /// Total bytes released with `free`.
+    pub free_bytes: u32,
+    /// Number of calls to `realloc`.
+    pub reallocs: u32,
+}
+
+// keep in sync with `allocationStats` in the CLI
+#[cfg(feature = "alloc-accounting")]
+static mut STATS: [u32; 5] = [0; 5];
+
+/// Returns the allocations counted so far.
+#[cfg(feature = "alloc-accounting")]
+pub fn stats() -> Stats {
+    let s = unsafe { STATS };
+    Stats {
+        mallocs: s[0],
+        malloc_bytes: s[1],
+        frees: s[2],
+        free_bytes: s[3],
+        reallocs: s[4],
+    }
+}
+
+#[cfg(feature = "alloc-accounting")]
+fn count(idx: usize, size: usize) {
+    unsafe {
+        STATS[idx] = STATS[idx].wrapping_add(1);
+        STATS[idx + 1] = STATS[idx + 1].wrapping_add(size as u32);
+    }
+}
+
+#[cfg(not(feature = "alloc-accounting"))]
+fn count(_idx: usize, _size: usize) {}
+
+#[cfg(feature = "alloc-accounting")]
+fn count_realloc(old_size: usize, new_size: usize) {
+    unsafe {
+        STATS[1] = STATS[1].wrapping_add(new_size as u32);
+        STATS[3] = STATS[3].wrapping_add(old_size as u32);
+        STATS[4] = STATS[4].wrapping_add(1);
+    }
+}
+
+#[cfg(not(feature = "alloc-accounting"))]
+fn count_realloc(_old_size: usize, _new_size: usize) {}
+
+#[doc(hidden)]
+pub fn malloc(size: usize, align: usize) -> *mut u8 {
+    count(0, size);
+    allocator().malloc(size, align)
+}
+
+#[doc(hidden)]
+pub unsafe fn free(ptr: *mut u8, size: usize, align: usize) {
+    count(2, size);
+    allocator().free(ptr, size, align)
+}
+
+#[doc(hidden)]
+pub unsafe fn realloc(ptr: *mut u8,
+                      old_size: usize,
+                      new_size: usize,
+                      align: usize) -> *mut u8 {
+    count_realloc(old_size, new_size);
+    allocator().realloc(ptr, old_size, new_size, align)
+}
+
+#[doc(hidden)]
+pub unsafe fn free_boxed_str(ptr: *mut String) {
+    allocator().free_boxed_str(ptr)
+}
+
+#[cfg(feature = "alloc-accounting")]
+#[no_mangle]
+pub unsafe extern fn __wbindgen_alloc_stats_ptr() -> *const u32 {
+    STATS.as_ptr()
+}
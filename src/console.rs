@@ -0,0 +1,54 @@
+//! Bindings to the JS `console`, for diagnostics which show up in DevTools or
+//! on stderr in Node.
+
+use super::JsValue;
+
+// keep in sync with the `__wbindgen_console` intrinsic in the CLI
+const LOG: u32 = 0;
+const GROUP: u32 = 1;
+const GROUP_COLLAPSED: u32 = 2;
+const GROUP_END: u32 = 3;
+const TABLE: u32 = 4;
+
+extern {
+    fn __wbindgen_console(method: u32, args: *const u32, len: usize);
+}
+
+fn call(method: u32, args: &[JsValue]) {
+    // `JsValue` is just a heap index so the slice is passed as-is, as with
+    // `JsValue::construct`.
+    unsafe {
+        __wbindgen_console(method, args.as_ptr() as *const u32, args.len());
+    }
+}
+
+/// Logs each of `values` with `console.log`, so objects can be inspected
+/// rather than being printed as strings.
+pub fn log(values: &[JsValue]) {
+    call(LOG, values)
+}
+
+/// Starts a new group with `console.group`, indenting everything logged until
+/// the matching `group_end`.
+pub fn group(label: &str) {
+    call(GROUP, &[JsValue::from(label)])
+}
+
+/// Like `group`, but the group starts out collapsed.
+pub fn group_collapsed(label: &str) {
+    call(GROUP_COLLAPSED, &[JsValue::from(label)])
+}
+
+/// Ends the innermost group started with `group` or `group_collapsed`.
+pub fn group_end() {
+    call(GROUP_END, &[])
+}
+
+/// Shows `rows` as a table with `console.table`.
+///
+/// Each row is typically an object whose properties become the columns. With
+/// the `serde-json` feature rows can be built from `serde_json::Value`s with
+/// `JsValue::from`.
+pub fn table(rows: &[JsValue]) {
+    call(TABLE, rows)
+}
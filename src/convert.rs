@@ -1,13 +1,18 @@
 use std::mem::{self, ManuallyDrop};
 use std::ops::{Deref, DerefMut};
 
-use super::JsValue;
+use super::{JsGenerator, JsValue};
 
 // keep in sync with shared/src/lib.rs TYPE constants
 pub const DESCRIPTOR_CUSTOM_REF_FLAG: u32 = 0x1;
 pub const DESCRIPTOR_NUMBER: u32 = 0x5e;
 pub const DESCRIPTOR_BOOLEAN: u32 = 0x61;
 pub const DESCRIPTOR_JS_OWNED: u32 = 0x62;
+pub const DESCRIPTOR_CHAR: u32 = 0x79;
+pub const DESCRIPTOR_DATE: u32 = 0x80;
+/// The first of the descriptors left for types converted by a `TypeHook` in
+/// the CLI, which extend up to `0x10ffff`.
+pub const DESCRIPTOR_HOOK_START: u32 = 0x10f000;
 
 pub trait WasmBoundary {
     type Js: WasmAbi;
@@ -76,6 +81,81 @@ impl WasmBoundary for bool {
     unsafe fn from_js(js: u32) -> bool { js != 0 }
 }
 
+impl WasmBoundary for char {
+    type Js = u32;
+    const DESCRIPTOR: u32 = DESCRIPTOR_CHAR;
+
+    fn into_js(self) -> u32 { self as u32 }
+    unsafe fn from_js(js: u32) -> char {
+        // JS strings can hold lone surrogates, which aren't valid `char`s
+        match ::std::char::from_u32(js) {
+            Some(c) => c,
+            None => super::throw("invalid char value"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn duration_to_millis(d: ::std::time::Duration) -> f64 {
+    d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1_000_000.0
+}
+
+#[cfg(feature = "std")]
+fn duration_from_millis(ms: f64) -> ::std::time::Duration {
+    ::std::time::Duration::new((ms / 1000.0) as u64,
+                               ((ms % 1000.0) * 1_000_000.0) as u32)
+}
+
+// Passed as milliseconds since the epoch, the same as `Date.prototype.getTime`
+#[cfg(feature = "std")]
+impl WasmBoundary for ::std::time::SystemTime {
+    type Js = f64;
+    const DESCRIPTOR: u32 = DESCRIPTOR_DATE;
+
+    fn into_js(self) -> f64 {
+        match self.duration_since(::std::time::UNIX_EPOCH) {
+            Ok(d) => duration_to_millis(d),
+            Err(e) => -duration_to_millis(e.duration()),
+        }
+    }
+
+    unsafe fn from_js(js: f64) -> ::std::time::SystemTime {
+        // an invalid `Date` has a time of `NaN`
+        if js.is_nan() {
+            super::throw("invalid Date value");
+        }
+        let d = duration_from_millis(js.abs());
+        if js < 0.0 {
+            ::std::time::UNIX_EPOCH - d
+        } else {
+            ::std::time::UNIX_EPOCH + d
+        }
+    }
+}
+
+// Passed as a number of milliseconds, as taken by `setTimeout` and friends
+#[cfg(feature = "std")]
+impl WasmBoundary for ::std::time::Duration {
+    type Js = f64;
+    const DESCRIPTOR: u32 = DESCRIPTOR_NUMBER;
+
+    fn into_js(self) -> f64 {
+        duration_to_millis(self)
+    }
+
+    unsafe fn from_js(js: f64) -> ::std::time::Duration {
+        // also catches `NaN`
+        if !(js >= 0.0 && js < 18446744073709551616000.0) {
+            super::throw("invalid Duration value");
+        }
+        duration_from_millis(js)
+    }
+}
+
+// Passed as the address in wasm memory, which is also the byte offset JS
+// needs to build a view of `memory.buffer`. Nothing is checked in either
+// direction, so it's up to the caller to keep the memory alive and in place
+// for as long as JS refers to it.
 impl<T> WasmBoundary for *const T {
     type Js = u32;
     const DESCRIPTOR: u32 = DESCRIPTOR_NUMBER;
@@ -120,3 +200,16 @@ impl FromRefWasmBoundary for JsValue {
         ManuallyDrop::new(JsValue { idx: js })
     }
 }
+
+impl WasmBoundary for JsGenerator {
+    type Js = u32;
+    const DESCRIPTOR: u32 = DESCRIPTOR_JS_OWNED;
+
+    fn into_js(self) -> u32 {
+        self.obj.into_js()
+    }
+
+    unsafe fn from_js(js: u32) -> JsGenerator {
+        JsGenerator { obj: JsValue::from_js(js) }
+    }
+}
@@ -0,0 +1,204 @@
+//! A serde `Deserializer` which reads JS values directly.
+//!
+//! This is the counterpart of the `ser` module, walking arrays and objects
+//! through the JS glue instead of stringifying them first, and accepts the
+//! same layout that `serde_json` does.
+
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde_json::Error;
+
+use super::{JsIterator, JsValue};
+use json::{KIND_NULL, KIND_BOOL, KIND_NUMBER, KIND_STRING, KIND_ARRAY, KIND_OBJECT};
+
+/// Deserializes a `T` from the JS value `value`.
+pub fn from_value<T: DeserializeOwned>(value: &JsValue) -> Result<T, Error> {
+    T::deserialize(Deserializer::new(value.clone()))
+}
+
+/// A deserializer reading from a `JsValue`.
+pub struct Deserializer {
+    value: JsValue,
+}
+
+impl Deserializer {
+    pub fn new(value: JsValue) -> Deserializer {
+        Deserializer { value }
+    }
+
+    fn kind(&self) -> u32 {
+        unsafe { super::__wbindgen_json_kind(self.value.idx) }
+    }
+
+    fn keys(&self) -> JsIterator {
+        let keys = unsafe {
+            JsValue { idx: super::__wbindgen_object_keys(self.value.idx) }
+        };
+        keys.iter()
+    }
+}
+
+fn invalid(what: &str) -> Error {
+    de::Error::custom(format!("invalid type: expected {}", what))
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.kind() {
+            KIND_NULL => visitor.visit_unit(),
+            KIND_BOOL => visitor.visit_bool(self.value.as_bool().unwrap()),
+            KIND_NUMBER => {
+                let n = self.value.as_f64().unwrap();
+                // Integral numbers are handed out as integers so they can be
+                // deserialized into integer types.
+                if n.fract() == 0.0 && n >= 0.0 && n < 18446744073709551616.0 {
+                    visitor.visit_u64(n as u64)
+                } else if n.fract() == 0.0 && n < 0.0 && n >= -9223372036854775808.0 {
+                    visitor.visit_i64(n as i64)
+                } else {
+                    visitor.visit_f64(n)
+                }
+            }
+            KIND_STRING => visitor.visit_string(self.value.as_string().unwrap()),
+            KIND_ARRAY => visitor.visit_seq(SeqAccess { iter: self.value.iter() }),
+            KIND_OBJECT => {
+                visitor.visit_map(MapAccess {
+                    keys: self.keys(),
+                    object: self.value,
+                    value: None,
+                })
+            }
+            _ => Err(invalid("a value which can be represented in JSON")),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.kind() {
+            KIND_NULL => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V)
+        -> Result<V::Value, Error>
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Enums are externally tagged, either as just the variant's name or as
+    // an object with the variant's name as its only key.
+    fn deserialize_enum<V: Visitor<'de>>(self,
+                                         _name: &'static str,
+                                         _variants: &'static [&'static str],
+                                         visitor: V)
+        -> Result<V::Value, Error>
+    {
+        match self.kind() {
+            KIND_STRING => {
+                let variant = self.value.as_string().unwrap();
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            KIND_OBJECT => {
+                let mut keys = self.keys();
+                let variant = match (keys.next(), keys.next()) {
+                    (Some(variant), None) => variant.as_string().unwrap(),
+                    _ => return Err(invalid("an object with a single key")),
+                };
+                let value = self.value.get_property(&variant);
+                visitor.visit_enum(EnumAccess { variant, value })
+            }
+            _ => Err(invalid("a string or an object")),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+        byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct SeqAccess {
+    iter: JsIterator,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T)
+        -> Result<Option<T::Value>, Error>
+    {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(Deserializer::new(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct MapAccess {
+    keys: JsIterator,
+    object: JsValue,
+    value: Option<JsValue>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K)
+        -> Result<Option<K::Value>, Error>
+    {
+        let key = match self.keys.next() {
+            Some(key) => key.as_string().unwrap(),
+            None => return Ok(None),
+        };
+        self.value = Some(self.object.get_property(&key));
+        seed.deserialize(key.into_deserializer()).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self.value.take().expect("`next_value` called before `next_key`");
+        seed.deserialize(Deserializer::new(value))
+    }
+}
+
+struct EnumAccess {
+    variant: String,
+    value: JsValue,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess {
+    type Error = Error;
+    type Variant = Deserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V)
+        -> Result<(V::Value, Deserializer), Error>
+    {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, Deserializer::new(self.value)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self,
+                                       _fields: &'static [&'static str],
+                                       visitor: V)
+        -> Result<V::Value, Error>
+    {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
@@ -0,0 +1,126 @@
+//! Conversions between `serde_json::Value` and `JsValue`, and of anything
+//! implementing serde's traits.
+//!
+//! `serde_json::Value`s are built and inspected directly through the JS glue
+//! rather than by printing and parsing JSON, so no string is ever allocated
+//! for the document as a whole. Other types go through a JSON string, see
+//! the `ser` and `de` modules for the direct equivalent.
+
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use serde_json::{self, Map, Number, Value};
+
+use super::JsValue;
+
+// keep in sync with the `__wbindgen_json_kind` intrinsic in the CLI
+pub const KIND_NULL: u32 = 0;
+pub const KIND_BOOL: u32 = 1;
+pub const KIND_NUMBER: u32 = 2;
+pub const KIND_STRING: u32 = 3;
+pub const KIND_ARRAY: u32 = 4;
+pub const KIND_OBJECT: u32 = 5;
+
+impl JsValue {
+    /// Serializes `t` to JSON and parses it into a JS value with
+    /// `JSON.parse`.
+    ///
+    /// Returns an error if `t` can't be serialized to JSON, such as a map
+    /// with non-string keys.
+    pub fn from_serde<T: Serialize + ?Sized>(t: &T) -> serde_json::Result<JsValue> {
+        let s = serde_json::to_string(t)?;
+        unsafe {
+            Ok(JsValue { idx: super::__wbindgen_json_parse(s.as_ptr(), s.len()) })
+        }
+    }
+
+    /// Stringifies this JS value with `JSON.stringify` and deserializes a `T`
+    /// from the result.
+    ///
+    /// `undefined` is treated as `null`. Returns an error if the JSON doesn't
+    /// match `T`, and throws if `JSON.stringify` does, such as for a value
+    /// which refers to itself.
+    pub fn into_serde<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        let s = unsafe {
+            let mut len = 0;
+            let ptr = super::__wbindgen_json_serialize(self.idx, &mut len);
+            String::from_utf8_unchecked(Vec::from_raw_parts(ptr, len, len))
+        };
+        serde_json::from_str(&s)
+    }
+
+    /// Converts this JS value to a `serde_json::Value`.
+    ///
+    /// `null` and `undefined` become `Value::Null`, arrays and plain objects
+    /// are converted recursively (using an object's own enumerable keys) and
+    /// booleans, strings and finite numbers map to the corresponding variants.
+    /// Returns `None` if this value, or anything inside of it, can't be
+    /// represented in JSON, such as a function, a symbol or `NaN`.
+    pub fn to_json(&self) -> Option<Value> {
+        let kind = unsafe { super::__wbindgen_json_kind(self.idx) };
+        let ret = match kind {
+            KIND_NULL => Value::Null,
+            KIND_BOOL => Value::Bool(self.as_bool()?),
+            KIND_NUMBER => Value::Number(Number::from_f64(self.as_f64()?)?),
+            KIND_STRING => Value::String(self.as_string()?),
+            KIND_ARRAY => {
+                let mut ret = Vec::new();
+                for item in self.iter() {
+                    ret.push(item.to_json()?);
+                }
+                Value::Array(ret)
+            }
+            KIND_OBJECT => {
+                let keys = unsafe {
+                    JsValue { idx: super::__wbindgen_object_keys(self.idx) }
+                };
+                let mut ret = Map::new();
+                for key in keys.iter() {
+                    let key = key.as_string()?;
+                    let value = self.get_property(&key).to_json()?;
+                    ret.insert(key, value);
+                }
+                Value::Object(ret)
+            }
+            _ => return None,
+        };
+        Some(ret)
+    }
+}
+
+impl<'a> From<&'a Value> for JsValue {
+    fn from(value: &'a Value) -> JsValue {
+        match *value {
+            Value::Null => JsValue::null(),
+            Value::Bool(b) => JsValue::from_bool(b),
+            Value::Number(ref n) => {
+                JsValue::from_f64(n.as_f64().unwrap_or(0.0))
+            }
+            Value::String(ref s) => JsValue::from_str(s),
+            Value::Array(ref items) => {
+                let array = unsafe {
+                    JsValue { idx: super::__wbindgen_array_new() }
+                };
+                for item in items {
+                    let item = JsValue::from(item);
+                    unsafe {
+                        super::__wbindgen_array_push(array.idx, item.idx);
+                    }
+                }
+                array
+            }
+            Value::Object(ref map) => {
+                let obj = JsValue::object();
+                for (key, value) in map {
+                    obj.set_property(key, &JsValue::from(value));
+                }
+                obj
+            }
+        }
+    }
+}
+
+impl From<Value> for JsValue {
+    fn from(value: Value) -> JsValue {
+        JsValue::from(&value)
+    }
+}
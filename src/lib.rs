@@ -6,10 +6,32 @@
 //! interface.
 
 #![feature(use_extern_macros)]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(not(feature = "std"), feature(alloc))]
 
 extern crate wasm_bindgen_macro;
+#[cfg(feature = "serde-json")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "serde-json")]
+extern crate serde_json;
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+/// Without `std` the same paths are provided by `core` and `alloc`, so the
+/// rest of the crate doesn't need to care which it's built with.
+#[cfg(not(feature = "std"))]
+mod std {
+    pub use core::*;
+    pub use alloc::boxed;
+    pub use alloc::string;
+    pub use alloc::vec;
+}
 
+use std::ops::{Deref, DerefMut};
 use std::ptr;
+use std::string::String;
+use std::vec::Vec;
 
 /// A module which is typically glob imported from:
 ///
@@ -21,7 +43,15 @@ pub mod prelude {
     pub use JsValue;
 }
 
+pub mod allocator;
+pub mod console;
 pub mod convert;
+#[cfg(feature = "serde-json")]
+pub mod de;
+#[cfg(feature = "serde-json")]
+mod json;
+#[cfg(feature = "serde-json")]
+pub mod ser;
 
 /// Representation of an object owned by JS.
 ///
@@ -29,6 +59,7 @@ pub mod convert;
 /// owned by the `wasm-bindgen` generated JS glue code. Eventually the ownership
 /// will transfer into wasm directly and this will likely become more efficient,
 /// but for now it may be slightly slow.
+#[repr(C)]
 pub struct JsValue {
     idx: u32,
 }
@@ -54,6 +85,13 @@ impl JsValue {
         }
     }
 
+    /// Creates a new, empty JS object.
+    pub fn object() -> JsValue {
+        unsafe {
+            JsValue { idx: __wbindgen_object_new() }
+        }
+    }
+
     /// Creates a new JS value which is a boolean.
     ///
     /// This function creates a JS object representing a boolean (a heap
@@ -90,6 +128,35 @@ impl JsValue {
         }
     }
 
+    /// Creates a JS `Uint8Array` which is a view directly into wasm memory
+    /// over the contents of `slice`.
+    ///
+    /// No data is copied, so this is an efficient way to hand bulk data to
+    /// APIs like WebGL or WebAudio.
+    ///
+    /// # Unsafety
+    ///
+    /// The returned view aliases `slice`, so JS must not modify it while Rust
+    /// has any borrow of the underlying data, and the view must not be used
+    /// after `slice` is deallocated. Additionally if wasm memory is grown
+    /// (for example by any allocation in Rust) then the view is detached and
+    /// will appear empty in JS, so views should be used immediately and not
+    /// held on to.
+    pub unsafe fn uint8_array_view(slice: &[u8]) -> JsValue {
+        JsValue { idx: __wbindgen_uint8_array_view_new(slice.as_ptr(), slice.len()) }
+    }
+
+    /// Creates a JS `Float32Array` which is a view directly into wasm memory
+    /// over the contents of `slice`.
+    ///
+    /// # Unsafety
+    ///
+    /// This function has the same caveats as `uint8_array_view`: the view
+    /// aliases `slice` and is invalidated when wasm memory grows.
+    pub unsafe fn float32_array_view(slice: &[f32]) -> JsValue {
+        JsValue { idx: __wbindgen_float32_array_view_new(slice.as_ptr(), slice.len()) }
+    }
+
     // #[doc(hidden)]
     // pub fn __from_idx(idx: u32) -> JsValue {
     //     JsValue { idx }
@@ -157,6 +224,91 @@ impl JsValue {
         }
     }
 
+    /// Returns the property `name` of this JS value, as with `Reflect.get`.
+    ///
+    /// Missing properties are returned as `undefined`. If this value isn't an
+    /// object then a JS `TypeError` is thrown.
+    pub fn get_property(&self, name: &str) -> JsValue {
+        unsafe {
+            let idx = __wbindgen_reflect_get(self.idx, name.as_ptr(), name.len());
+            JsValue { idx }
+        }
+    }
+
+    /// Sets the property `name` of this JS value to `value`, as with
+    /// `Reflect.set`.
+    ///
+    /// Returns whether the property was successfully set. If this value isn't
+    /// an object then a JS `TypeError` is thrown.
+    pub fn set_property(&self, name: &str, value: &JsValue) -> bool {
+        unsafe {
+            __wbindgen_reflect_set(self.idx, name.as_ptr(), name.len(), value.idx) == 1
+        }
+    }
+
+    /// Tests whether this JS value has the property `name`, including through
+    /// its prototype chain, as with `Reflect.has`.
+    pub fn has_property(&self, name: &str) -> bool {
+        unsafe {
+            __wbindgen_reflect_has(self.idx, name.as_ptr(), name.len()) == 1
+        }
+    }
+
+    /// Deletes the property `name` from this JS value, as with
+    /// `Reflect.deleteProperty`.
+    ///
+    /// Returns whether the property no longer exists on the object.
+    pub fn delete_property(&self, name: &str) -> bool {
+        unsafe {
+            __wbindgen_reflect_delete(self.idx, name.as_ptr(), name.len()) == 1
+        }
+    }
+
+    /// Returns a deep copy of this JS value made with the structured clone
+    /// algorithm, the same one used by `postMessage`.
+    ///
+    /// This is useful for detaching state from an object the caller still
+    /// owns or for preparing data to hand off to a worker. If the value can't
+    /// be cloned (for example it contains a function) then a JS exception is
+    /// thrown.
+    pub fn structured_clone(&self) -> JsValue {
+        unsafe {
+            JsValue { idx: __wbindgen_structured_clone(self.idx) }
+        }
+    }
+
+    /// Invokes this JS value as a constructor with `args`, as with
+    /// `Reflect.construct`.
+    ///
+    /// This is useful for instantiating classes whose arity isn't known
+    /// statically, such as user-provided plugin classes. If this value isn't a
+    /// constructor then a JS `TypeError` is thrown.
+    pub fn construct(&self, args: &[JsValue]) -> JsValue {
+        // `JsValue` is just a heap index so a slice of them can be read
+        // directly as a `Uint32Array` on the JS side.
+        unsafe {
+            let idx = __wbindgen_reflect_construct(self.idx,
+                                                   args.as_ptr() as *const u32,
+                                                   args.len());
+            JsValue { idx }
+        }
+    }
+
+    /// Starts iterating over this JS value with the JS iteration protocol.
+    ///
+    /// This calls `value[Symbol.iterator]()` and returns an iterator yielding
+    /// each value produced by the JS iterator, so any iterable like an array,
+    /// `Map`, `Set`, `NodeList` or generator can be looped over. If this value
+    /// isn't iterable then a JS `TypeError` is thrown.
+    pub fn iter(&self) -> JsIterator {
+        unsafe {
+            JsIterator {
+                iter: JsValue { idx: __wbindgen_iter_new(self.idx) },
+                done: false,
+            }
+        }
+    }
+
     /// Tests whether this JS value is `null`
     pub fn is_null(&self) -> bool {
         unsafe {
@@ -179,6 +331,97 @@ impl JsValue {
     }
 }
 
+/// An iterator over the values produced by a JS iterable.
+///
+/// Created with `JsValue::iter`.
+pub struct JsIterator {
+    iter: JsValue,
+    done: bool,
+}
+
+impl Iterator for JsIterator {
+    type Item = JsValue;
+
+    fn next(&mut self) -> Option<JsValue> {
+        if self.done {
+            return None
+        }
+        unsafe {
+            let mut done = 0;
+            let idx = __wbindgen_iter_next(self.iter.idx, &mut done);
+            if done == 1 {
+                self.done = true;
+                None
+            } else {
+                Some(JsValue { idx })
+            }
+        }
+    }
+}
+
+/// A handle to a JS generator object, typically returned from an imported
+/// generator function.
+///
+/// Unlike `JsIterator` this exposes the full generator protocol, so values can
+/// be sent into the generator and it can be finished early or have an
+/// exception thrown into it.
+pub struct JsGenerator {
+    obj: JsValue,
+}
+
+/// The result of resuming a `JsGenerator`.
+pub enum GeneratorState {
+    /// The generator paused at a `yield` with this value.
+    Yielded(JsValue),
+    /// The generator finished, returning this value.
+    Complete(JsValue),
+}
+
+impl JsGenerator {
+    /// Resumes the generator, passing `value` as the result of the `yield`
+    /// it's paused at, as with `gen.next(value)`.
+    pub fn next(&self, value: &JsValue) -> GeneratorState {
+        self.resume(0, value)
+    }
+
+    /// Finishes the generator as if it executed `return value`, as with
+    /// `gen.return(value)`.
+    pub fn return_(&self, value: &JsValue) -> GeneratorState {
+        self.resume(1, value)
+    }
+
+    /// Throws `value` into the generator at the `yield` it's paused at, as
+    /// with `gen.throw(value)`.
+    ///
+    /// If the generator doesn't catch the exception then it propagates out as
+    /// a JS exception.
+    pub fn throw_(&self, value: &JsValue) -> GeneratorState {
+        self.resume(2, value)
+    }
+
+    fn resume(&self, method: u32, value: &JsValue) -> GeneratorState {
+        unsafe {
+            let mut done = 0;
+            let idx = __wbindgen_generator_resume(self.obj.idx,
+                                                  method,
+                                                  value.idx,
+                                                  &mut done);
+            let value = JsValue { idx };
+            if done == 1 {
+                GeneratorState::Complete(value)
+            } else {
+                GeneratorState::Yielded(value)
+            }
+        }
+    }
+}
+
+impl From<JsGenerator> for JsValue {
+    fn from(gen: JsGenerator) -> JsValue {
+        gen.obj
+    }
+}
+
 impl<'a> From<&'a str> for JsValue {
     fn from(s: &'a str) -> JsValue {
         JsValue::from_str(s)
@@ -216,6 +459,13 @@ extern {
     fn __wbindgen_number_new(f: f64) -> u32;
     fn __wbindgen_number_get(idx: u32, invalid: *mut u8) -> f64;
     fn __wbindgen_null_new() -> u32;
+    fn __wbindgen_object_new() -> u32;
+    fn __wbindgen_object_keys(idx: u32) -> u32;
+    fn __wbindgen_array_new() -> u32;
+    fn __wbindgen_array_push(array: u32, val: u32);
+    fn __wbindgen_json_kind(idx: u32) -> u32;
+    fn __wbindgen_json_parse(ptr: *const u8, len: usize) -> u32;
+    fn __wbindgen_json_serialize(idx: u32, len: *mut usize) -> *mut u8;
     fn __wbindgen_undefined_new() -> u32;
     fn __wbindgen_is_null(idx: u32) -> u32;
     fn __wbindgen_is_undefined(idx: u32) -> u32;
@@ -224,6 +474,17 @@ extern {
     fn __wbindgen_symbol_new(ptr: *const u8, len: usize) -> u32;
     fn __wbindgen_is_symbol(idx: u32) -> u32;
     fn __wbindgen_string_get(idx: u32, len: *mut usize) -> *mut u8;
+    fn __wbindgen_uint8_array_view_new(ptr: *const u8, len: usize) -> u32;
+    fn __wbindgen_float32_array_view_new(ptr: *const f32, len: usize) -> u32;
+    fn __wbindgen_structured_clone(idx: u32) -> u32;
+    fn __wbindgen_reflect_construct(idx: u32, args: *const u32, len: usize) -> u32;
+    fn __wbindgen_iter_new(idx: u32) -> u32;
+    fn __wbindgen_iter_next(idx: u32, done: *mut u32) -> u32;
+    fn __wbindgen_generator_resume(idx: u32, method: u32, val: u32, done: *mut u32) -> u32;
+    fn __wbindgen_reflect_get(idx: u32, ptr: *const u8, len: usize) -> u32;
+    fn __wbindgen_reflect_set(idx: u32, ptr: *const u8, len: usize, val: u32) -> u32;
+    fn __wbindgen_reflect_has(idx: u32, ptr: *const u8, len: usize) -> u32;
+    fn __wbindgen_reflect_delete(idx: u32, ptr: *const u8, len: usize) -> u32;
 }
 
 impl Clone for JsValue {
@@ -243,6 +504,28 @@ impl Drop for JsValue {
     }
 }
 
+/// A byte buffer which is passed to and from JS as a `Uint8ClampedArray`
+/// instead of a `Uint8Array`, as used for example by canvas `ImageData`.
+///
+/// Exported functions may take or return a `Clamped<&[u8]>`,
+/// `Clamped<Vec<u8>>` or `Clamped<Box<[u8]>>`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Clamped<T>(pub T);
+
+impl<T> Deref for Clamped<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Clamped<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
 /// Throws a JS exception.
 ///
 /// This function will throw a JS exception with the message provided. The
@@ -259,11 +542,69 @@ pub fn throw(s: &str) -> ! {
     }
 }
 
+/// Returns the current time in milliseconds, with sub-millisecond precision
+/// where the JS engine allows it.
+///
+/// This uses `performance.now()`, falling back to `Date.now()` in
+/// environments without it, so it's only meaningful for measuring the time
+/// between two calls rather than as a time of day.
+pub fn now() -> f64 {
+    extern {
+        fn __wbindgen_now() -> f64;
+    }
+    unsafe {
+        __wbindgen_now()
+    }
+}
+
+/// Fills `buf` with cryptographically secure random bytes.
+///
+/// This uses `crypto.getRandomValues` in browsers and `crypto.randomFillSync`
+/// in Node, and is intended for seeding random number generators.
+pub fn random_fill(buf: &mut [u8]) {
+    extern {
+        fn __wbindgen_random_fill(ptr: *mut u8, len: usize);
+    }
+    unsafe {
+        __wbindgen_random_fill(buf.as_mut_ptr(), buf.len());
+    }
+}
+
 #[doc(hidden)]
 pub mod __rt {
     use std::cell::{Cell, UnsafeCell};
+    use std::iter::FromIterator;
     use std::mem;
     use std::ops::{Deref, DerefMut};
+    use std::slice;
+    use std::string::String;
+    use std::vec::Vec;
+
+    use JsValue;
+
+    /// The parts of `std` referenced by code generated by `#[wasm_bindgen]`,
+    /// which works whether or not the `std` feature is enabled.
+    pub mod std {
+        pub use std::boxed;
+        pub use std::mem;
+        pub use std::ptr;
+        pub use std::slice;
+        pub use std::str;
+        pub use std::string;
+        pub use std::vec;
+    }
+
+    /// Throws the JS value with heap index `idx`, an exception caught by an
+    /// import which doesn't match its declared error type.
+    #[cold]
+    pub fn rethrow(idx: u32) -> ! {
+        extern {
+            fn __wbindgen_rethrow(idx: u32) -> !;
+        }
+        unsafe {
+            __wbindgen_rethrow(idx)
+        }
+    }
 
     #[inline]
     pub fn assert_not_null<T>(s: *mut T) {
@@ -398,27 +739,148 @@ pub mod __rt {
     }
 
     #[no_mangle]
-    pub extern fn __wbindgen_malloc(size: usize) -> *mut u8 {
-        // Any malloc request this big is bogus anyway. If this actually
-        // goes down to `Vec` we trigger a whole bunch of panicking
-        // machinery to get pulled in from libstd anyway as it'll verify
-        // the size passed in below.
-        //
-        // Head this all off by just aborting on too-big sizes. This
-        // avoids panicking (code bloat) and gives a better error
-        // message too hopefully.
-        if size >= usize::max_value() / 2 {
-            super::throw("invalid malloc request");
-        }
-        let mut ret = Vec::with_capacity(size);
-        let ptr = ret.as_mut_ptr();
-        mem::forget(ret);
-        return ptr
+    pub extern fn __wbindgen_malloc(size: usize, align: usize) -> *mut u8 {
+        super::allocator::malloc(size, align)
+    }
+
+    #[no_mangle]
+    pub unsafe extern fn __wbindgen_free(ptr: *mut u8, size: usize, align: usize) {
+        super::allocator::free(ptr, size, align)
+    }
+
+    #[no_mangle]
+    pub unsafe extern fn __wbindgen_realloc(ptr: *mut u8,
+                                            old_size: usize,
+                                            new_size: usize,
+                                            align: usize) -> *mut u8 {
+        super::allocator::realloc(ptr, old_size, new_size, align)
+    }
+
+    /// Scratch space used to hand extra values back to JS from an exported
+    /// function, for example the length of a returned slice, whether an
+    /// optional value is present or an error. The last two slots only hold
+    /// the upper limbs of returned 128-bit integers.
+    static mut GLOBAL_ARGUMENT: [u32; 5] = [0; 5];
+
+    #[inline]
+    pub fn set_global_argument(idx: usize, val: u32) {
+        unsafe {
+            GLOBAL_ARGUMENT[idx] = val;
+        }
+    }
+
+    #[no_mangle]
+    pub unsafe extern fn __wbindgen_global_argument_ptr() -> *mut u32 {
+        GLOBAL_ARGUMENT.as_mut_ptr()
+    }
+
+    /// Scratch space tuples returned from exported functions are written to,
+    /// one element per slot. Keep the size in sync with the limit in the
+    /// macro's `extract_tuple_ret`.
+    static mut TUPLE_RET: [f64; 8] = [0.0; 8];
+
+    /// Stores the `idx`th element of a tuple returned to JS.
+    #[inline]
+    pub fn set_tuple_ret(idx: usize, val: f64) {
+        unsafe {
+            TUPLE_RET[idx] = val;
+        }
     }
 
     #[no_mangle]
-    pub unsafe extern fn __wbindgen_free(ptr: *mut u8, size: usize) {
-        drop(Vec::<u8>::from_raw_parts(ptr, 0, size));
+    pub unsafe extern fn __wbindgen_tuple_ret_ptr() -> *mut f64 {
+        TUPLE_RET.as_mut_ptr()
+    }
+
+    /// Takes ownership of the `len` strings JS passed in as pointer and
+    /// length pairs starting at `ptr`. JS frees the pairs themselves.
+    pub unsafe fn strings_from_js(ptr: *const usize, len: usize) -> Vec<String> {
+        slice::from_raw_parts(ptr, len * 2)
+            .chunks(2)
+            .map(|s| {
+                let vec = Vec::from_raw_parts(s[0] as *mut u8, s[1], s[1]);
+                String::from_utf8_unchecked(vec)
+            })
+            .collect()
+    }
+
+    /// Hands `strings` over to JS as pointer and length pairs, with the
+    /// number of strings going through the first global argument slot. JS
+    /// frees each string and then the pairs themselves.
+    pub fn strings_into_js(strings: Vec<String>) -> *mut usize {
+        set_global_argument(0, strings.len() as u32);
+        let mut pairs = Vec::with_capacity(strings.len() * 2);
+        for s in strings {
+            // JS frees exactly `len` bytes, so drop any spare capacity
+            let s = s.into_boxed_str();
+            pairs.push(s.as_ptr() as usize);
+            pairs.push(s.len());
+            mem::forget(s);
+        }
+        let mut pairs = pairs.into_boxed_slice();
+        let ptr = pairs.as_mut_ptr();
+        mem::forget(pairs);
+        ptr
+    }
+
+    /// Builds a map from the `len` strings JS passed in, which alternate
+    /// between keys and values.
+    pub unsafe fn string_map_from_js<T>(ptr: *const usize, len: usize) -> T
+        where T: FromIterator<(String, String)>
+    {
+        let mut strings = strings_from_js(ptr, len).into_iter();
+        let mut pairs = Vec::with_capacity(len / 2);
+        while let (Some(key), Some(value)) = (strings.next(), strings.next()) {
+            pairs.push((key, value));
+        }
+        pairs.into_iter().collect()
+    }
+
+    /// Hands `map` over to JS as strings alternating between keys and values,
+    /// in the same way as `strings_into_js`.
+    pub fn string_map_into_js<T>(map: T) -> *mut usize
+        where T: IntoIterator<Item = (String, String)>
+    {
+        let mut strings = Vec::new();
+        for (key, value) in map {
+            strings.push(key);
+            strings.push(value);
+        }
+        strings_into_js(strings)
+    }
+
+    /// Builds a map from the `len` values JS passed in, which alternate
+    /// between string keys and values.
+    pub unsafe fn js_map_from_js<T>(ptr: *mut JsValue, len: usize) -> T
+        where T: FromIterator<(String, JsValue)>
+    {
+        let mut values = Vec::from_raw_parts(ptr, len, len).into_iter();
+        let mut pairs = Vec::with_capacity(len / 2);
+        while let (Some(key), Some(value)) = (values.next(), values.next()) {
+            let key = match key.as_string() {
+                Some(key) => key,
+                None => super::throw("map keys must be strings"),
+            };
+            pairs.push((key, value));
+        }
+        pairs.into_iter().collect()
+    }
+
+    /// Hands `map` over to JS as values alternating between keys and values,
+    /// with their number going through the first global argument slot.
+    pub fn js_map_into_js<T>(map: T) -> *mut JsValue
+        where T: IntoIterator<Item = (String, JsValue)>
+    {
+        let mut values = Vec::new();
+        for (key, value) in map {
+            values.push(JsValue::from_str(&key));
+            values.push(value);
+        }
+        let mut values = values.into_boxed_slice();
+        set_global_argument(0, values.len() as u32);
+        let ptr = values.as_mut_ptr();
+        mem::forget(values);
+        ptr
     }
 
     #[no_mangle]
@@ -433,6 +895,6 @@ pub mod __rt {
 
     #[no_mangle]
     pub unsafe extern fn __wbindgen_boxed_str_free(ptr: *mut String) {
-        drop(Box::from_raw(ptr));
+        super::allocator::free_boxed_str(ptr)
     }
 }
@@ -0,0 +1,348 @@
+//! A serde `Serializer` which builds JS values directly.
+//!
+//! Unlike `JsValue::from_serde` no JSON text is produced, each value is
+//! created through the JS glue as it's serialized. Values are laid out the
+//! same way `serde_json` would: structs and maps become plain objects,
+//! sequences and tuples become arrays and enums are externally tagged.
+
+use serde::ser::{self, Serialize};
+use serde_json::Error;
+
+use super::JsValue;
+
+/// Serializes `t` into a new JS value.
+pub fn to_value<T: Serialize + ?Sized>(t: &T) -> Result<JsValue, Error> {
+    t.serialize(Serializer)
+}
+
+/// A serializer whose output is a `JsValue`.
+pub struct Serializer;
+
+fn array() -> JsValue {
+    unsafe { JsValue { idx: super::__wbindgen_array_new() } }
+}
+
+fn push(array: &JsValue, value: &JsValue) {
+    unsafe { super::__wbindgen_array_push(array.idx, value.idx) }
+}
+
+fn tagged(variant: &str, value: &JsValue) -> JsValue {
+    let obj = JsValue::object();
+    obj.set_property(variant, value);
+    obj
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = JsValue;
+    type Error = Error;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeObject;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeObject;
+
+    fn serialize_bool(self, v: bool) -> Result<JsValue, Error> {
+        Ok(JsValue::from_bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v as f64))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v as f64))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v as f64))
+    }
+
+    // Like JSON numbers, values beyond 2^53 lose precision.
+    fn serialize_i64(self, v: i64) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v as f64))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v as f64))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v as f64))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v as f64))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v as f64))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v as f64))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<JsValue, Error> {
+        Ok(JsValue::from_f64(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<JsValue, Error> {
+        let mut buf = [0; 4];
+        Ok(JsValue::from_str(v.encode_utf8(&mut buf)))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<JsValue, Error> {
+        Ok(JsValue::from_str(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<JsValue, Error> {
+        let ret = array();
+        for b in v {
+            push(&ret, &JsValue::from_f64(*b as f64));
+        }
+        Ok(ret)
+    }
+
+    fn serialize_none(self) -> Result<JsValue, Error> {
+        Ok(JsValue::null())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<JsValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<JsValue, Error> {
+        Ok(JsValue::null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<JsValue, Error> {
+        Ok(JsValue::null())
+    }
+
+    fn serialize_unit_variant(self,
+                              _name: &'static str,
+                              _index: u32,
+                              variant: &'static str)
+        -> Result<JsValue, Error>
+    {
+        Ok(JsValue::from_str(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self,
+                                                       _name: &'static str,
+                                                       value: &T)
+        -> Result<JsValue, Error>
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self,
+                                                        _name: &'static str,
+                                                        _index: u32,
+                                                        variant: &'static str,
+                                                        value: &T)
+        -> Result<JsValue, Error>
+    {
+        Ok(tagged(variant, &value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SerializeArray, Error> {
+        Ok(SerializeArray { array: array(), variant: None })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize)
+        -> Result<SerializeArray, Error>
+    {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(self,
+                               _name: &'static str,
+                               _index: u32,
+                               variant: &'static str,
+                               _len: usize)
+        -> Result<SerializeArray, Error>
+    {
+        Ok(SerializeArray { array: array(), variant: Some(variant) })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SerializeObject, Error> {
+        Ok(SerializeObject { object: JsValue::object(), key: None, variant: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize)
+        -> Result<SerializeObject, Error>
+    {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(self,
+                                _name: &'static str,
+                                _index: u32,
+                                variant: &'static str,
+                                _len: usize)
+        -> Result<SerializeObject, Error>
+    {
+        Ok(SerializeObject {
+            object: JsValue::object(),
+            key: None,
+            variant: Some(variant),
+        })
+    }
+}
+
+/// Builds an array, wrapped in an object tagged with `variant` if present.
+pub struct SerializeArray {
+    array: JsValue,
+    variant: Option<&'static str>,
+}
+
+impl SerializeArray {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        push(&self.array, &value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<JsValue, Error> {
+        match self.variant {
+            Some(variant) => Ok(tagged(variant, &self.array)),
+            None => Ok(self.array),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeArray {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+/// Builds an object, wrapped in another tagged with `variant` if present.
+pub struct SerializeObject {
+    object: JsValue,
+    key: Option<String>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeObject {
+    fn finish(self) -> Result<JsValue, Error> {
+        match self.variant {
+            Some(variant) => Ok(tagged(variant, &self.object)),
+            None => Ok(self.object),
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeObject {
+    type Ok = JsValue;
+    type Error = Error;
+
+    // Object keys are always strings, so numeric keys are converted the way
+    // JS would.
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = key.serialize(Serializer)?;
+        let key = match key.as_string() {
+            Some(s) => s,
+            None => match key.as_f64() {
+                Some(n) => n.to_string(),
+                None => return Err(ser::Error::custom("map keys must be strings or numbers")),
+            },
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().expect("`serialize_value` called before `serialize_key`");
+        self.object.set_property(&key, &value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeObject {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+        -> Result<(), Error>
+    {
+        self.object.set_property(key, &value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeObject {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T)
+        -> Result<(), Error>
+    {
+        self.object.set_property(key, &value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
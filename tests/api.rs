@@ -157,3 +157,402 @@ fn works() {
         .test();
 }
 
+#[test]
+fn typed_array_views() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            static BYTES: [u8; 3] = [1, 2, 3];
+            static FLOATS: [f32; 2] = [1.5, 2.5];
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn bytes() -> JsValue {
+                unsafe { JsValue::uint8_array_view(&BYTES) }
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn floats() -> JsValue {
+                unsafe { JsValue::float32_array_view(&FLOATS) }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const bytes = wasm.bytes();
+                assert.strictEqual(bytes instanceof Uint8Array, true);
+                assert.deepStrictEqual(Array.from(bytes), [1, 2, 3]);
+
+                const floats = wasm.floats();
+                assert.strictEqual(floats instanceof Float32Array, true);
+                assert.deepStrictEqual(Array.from(floats), [1.5, 2.5]);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn reflect() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn touch(obj: &JsValue) -> JsValue {
+                assert!(obj.has_property("a"));
+                assert!(!obj.has_property("b"));
+                assert!(obj.has_property("toString"));
+                assert_eq!(obj.get_property("a").as_f64(), Some(1.0));
+                assert!(obj.get_property("b").is_undefined());
+
+                assert!(obj.set_property("b", &JsValue::from("x")));
+                assert!(obj.delete_property("a"));
+                assert!(!obj.has_property("a"));
+                obj.get_property("b")
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const obj: any = { a: 1 };
+                assert.strictEqual(wasm.touch(obj), "x");
+                assert.deepStrictEqual(obj, { b: "x" });
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn structured_clone() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn deep_copy(obj: &JsValue) -> JsValue {
+                obj.structured_clone()
+            }
+        "#)
+        .file("test.js", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const obj = { a: [1, 2], b: new Map([["c", 3]]) };
+                const copy = wasm.deep_copy(obj);
+                assert.notStrictEqual(copy, obj);
+                assert.notStrictEqual(copy.a, obj.a);
+                assert.deepStrictEqual(copy, obj);
+
+                assert.throws(() => wasm.deep_copy({ f: () => 1 }));
+            }
+        "#)
+        .file("test.d.ts", r#"
+            export function test(): void;
+        "#)
+        .test();
+}
+
+#[test]
+fn iterables() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn sum(obj: &JsValue) -> f64 {
+                obj.iter().map(|v| v.as_f64().unwrap()).sum()
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn count(obj: &JsValue) -> u32 {
+                obj.iter().count() as u32
+            }
+        "#)
+        .file("test.js", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            function* gen() {
+                yield 1;
+                yield 2;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.sum([1, 2, 3]), 6);
+                assert.strictEqual(wasm.sum(new Set([4, 4, 5])), 9);
+                assert.strictEqual(wasm.sum(gen()), 3);
+                assert.strictEqual(wasm.count(new Map([["a", 1], ["b", 2]])), 2);
+                assert.strictEqual(wasm.count("abc"), 3);
+                assert.throws(() => wasm.count(3));
+            }
+        "#)
+        .file("test.d.ts", r#"
+            export function test(): void;
+        "#)
+        .test();
+}
+
+#[test]
+fn construct() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn make(class: &JsValue) -> JsValue {
+                class.construct(&[JsValue::from(1), JsValue::from("two")])
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn make_empty(class: &JsValue) -> JsValue {
+                class.construct(&[])
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            class Plugin {
+                args: any[];
+
+                constructor(...args: any[]) {
+                    this.args = args;
+                }
+            }
+
+            export function test() {
+                const p = wasm.make(Plugin);
+                assert.strictEqual(p instanceof Plugin, true);
+                assert.deepStrictEqual(p.args, [1, "two"]);
+                assert.deepStrictEqual(wasm.make_empty(Plugin).args, []);
+                assert.throws(() => wasm.make_empty(3));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn now() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn elapsed() -> f64 {
+                let start = wasm_bindgen::now();
+                let mut end = start;
+                while end == start {
+                    end = wasm_bindgen::now();
+                }
+                end - start
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const before = Date.now();
+                const elapsed = wasm.elapsed();
+                assert.ok(elapsed > 0);
+                assert.ok(elapsed <= Date.now() - before + 1);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn random_fill() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn random_bytes(len: u32) -> Vec<u8> {
+                let mut buf = vec![0; len as usize];
+                wasm_bindgen::random_fill(&mut buf);
+                buf
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.random_bytes(0).length, 0);
+                const a = wasm.random_bytes(100000);
+                assert.strictEqual(a.length, 100000);
+                assert.ok(a.some(b => b !== 0));
+                assert.notDeepStrictEqual(Array.from(wasm.random_bytes(32)),
+                                          Array.from(wasm.random_bytes(32)));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn console() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen::console;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn report(rows: &JsValue) {
+                console::group("report");
+                console::log(&[JsValue::from("rows:"), JsValue::from(2)]);
+                console::table(&rows.iter().collect::<Vec<_>>());
+                console::group_end();
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const calls: any[] = [];
+                const saved: any = {};
+                for (const m of ["group", "log", "table", "groupEnd"]) {
+                    saved[m] = (console as any)[m];
+                    (console as any)[m] = (...args: any[]) => calls.push([m, ...args]);
+                }
+                const rows = [{ a: 1 }, { a: 2 }];
+                try {
+                    wasm.report(rows);
+                } finally {
+                    for (const m in saved)
+                        (console as any)[m] = saved[m];
+                }
+                assert.deepStrictEqual(calls, [
+                    ["group", "report"],
+                    ["log", "rows:", 2],
+                    ["table", rows],
+                    ["groupEnd"],
+                ]);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn allocator() {
+    test_support::project()
+        .feature("alloc-accounting")
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen::allocator::{self, Allocator, Global};
+
+            static mut MALLOCS: u32 = 0;
+
+            struct Counting;
+
+            impl Allocator for Counting {
+                fn malloc(&self, size: usize, align: usize) -> *mut u8 {
+                    unsafe { MALLOCS += 1; }
+                    Global.malloc(size, align)
+                }
+
+                unsafe fn free(&self, ptr: *mut u8, size: usize, align: usize) {
+                    Global.free(ptr, size, align)
+                }
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn install() {
+                allocator::set_allocator(&Counting);
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn mallocs() -> u32 {
+                unsafe { MALLOCS }
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn len(s: &str) -> u32 {
+                s.len() as u32
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                wasm.install();
+                const before = wasm.allocationStats();
+                assert.strictEqual(wasm.len("hello"), 5);
+                assert.strictEqual(wasm.mallocs(), 1);
+
+                const after = wasm.allocationStats();
+                assert.strictEqual(after.mallocs - before.mallocs, 1);
+                assert.strictEqual(after.mallocBytes - before.mallocBytes, 5);
+                assert.strictEqual(after.frees - before.frees, 1);
+                assert.strictEqual(after.freeBytes - before.freeBytes, 5);
+                assert.strictEqual(after.reallocs, before.reallocs);
+
+                // Non-ASCII strings grow the allocation to fit and then
+                // shrink it back down to size.
+                assert.strictEqual(wasm.len("h\u00e9llo \ud83d\ude00"), 11);
+                const last = wasm.allocationStats();
+                assert.strictEqual(last.mallocs - after.mallocs, 1);
+                assert.strictEqual(last.reallocs - after.reallocs, 2);
+                assert.strictEqual(last.mallocBytes - after.mallocBytes,
+                                   last.freeBytes - after.freeBytes);
+            }
+        "#)
+        .test();
+}
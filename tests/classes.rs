@@ -156,7 +156,7 @@ fn exceptions() {
                 assert.throws(() => new A(), /cannot invoke `new` directly/);
                 let a = A.new();
                 a.free();
-                assert.throws(() => a.free(), /null pointer passed to rust/);
+                assert.throws(() => a.free(), /`A` has already been freed/);
 
                 let b = A.new();
                 b.foo(b);
@@ -224,3 +224,1034 @@ fn pass_one_to_another() {
         "#)
         .test();
 }
+
+#[test]
+fn float32_views() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Mesh {
+                vertices: Vec<f32>,
+            }
+
+            #[wasm_bindgen]
+            impl Mesh {
+                pub fn new() -> Mesh {
+                    Mesh { vertices: vec![0.0, 0.5, 1.0, 1.5] }
+                }
+
+                pub fn vertices(&self) -> &[f32] {
+                    &self.vertices
+                }
+
+                pub fn grow(&mut self, amt: u32) {
+                    for i in 0..amt {
+                        self.vertices.push(i as f32);
+                    }
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Mesh } from "./out";
+
+            export function test() {
+                const m = Mesh.new();
+                assert.deepStrictEqual(Array.from(m.vertices()), [0, 0.5, 1, 1.5]);
+                assert.deepStrictEqual(Array.from(m.vertices(1)), [0.5, 1, 1.5]);
+                assert.deepStrictEqual(Array.from(m.vertices(1, 2)), [0.5, 1]);
+                assert.deepStrictEqual(Array.from(m.vertices(3, 10)), [1.5]);
+
+                m.grow(100000);
+                const v = m.vertices();
+                assert.strictEqual(v.length, 100004);
+                assert.strictEqual(v[100003], 99999);
+                m.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn raw_views() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(raw_view)]
+            #[repr(C)]
+            pub struct Sample {
+                pub id: u16,
+                pub value: f64,
+                #[wasm_bindgen(big_endian)]
+                pub tag: u32,
+            }
+
+            #[wasm_bindgen(raw_view, big_endian)]
+            #[repr(C, packed)]
+            pub struct Header {
+                pub kind: u8,
+                pub len: u32,
+            }
+
+            static mut SAMPLES: [Sample; 2] = [
+                Sample { id: 1, value: 1.5, tag: 0x01020304 },
+                Sample { id: 2, value: -2.5, tag: 0 },
+            ];
+            static mut HEADER: Header = Header { kind: 3, len: 0x0a0b0c0d };
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn samples() -> *mut Sample {
+                unsafe { SAMPLES.as_mut_ptr() }
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn sample_value(i: u32) -> f64 {
+                unsafe { SAMPLES[i as usize].value }
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn header() -> *mut Header {
+                unsafe { &mut HEADER }
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn header_len() -> u32 {
+                unsafe { HEADER.len }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.Sample.size, 24);
+                assert.strictEqual(wasm.Header.size, 5);
+
+                const s = new wasm.Sample(wasm.samples());
+                assert.strictEqual(s.id, 1);
+                assert.strictEqual(s.value, 1.5);
+                assert.strictEqual(s.tag, 0x04030201);
+
+                const s2 = s.at(1);
+                assert.strictEqual(s2.id, 2);
+                assert.strictEqual(s2.value, -2.5);
+                s2.value = 7.25;
+                assert.strictEqual(wasm.sample_value(1), 7.25);
+
+                const h = new wasm.Header(wasm.header());
+                assert.strictEqual(h.kind, 3);
+                assert.strictEqual(h.len, 0x0d0c0b0a);
+                h.len = 0x0a0b0c0d;
+                assert.strictEqual(wasm.header_len(), 0x0d0c0b0a);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn streams() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Chunks {
+                left: u8,
+                total: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Chunks {
+                pub fn new(left: u8) -> Chunks {
+                    Chunks { left, total: 0 }
+                }
+
+                #[wasm_bindgen(readable_stream)]
+                pub fn stream(&mut self) -> Vec<u8> {
+                    if self.left == 0 {
+                        return Vec::new()
+                    }
+                    self.left -= 1;
+                    vec![self.left; 2]
+                }
+
+                #[wasm_bindgen(stream_sink)]
+                pub fn consume(&mut self, chunk: Vec<u8>) {
+                    self.total += chunk.iter().map(|b| *b as u32).sum::<u32>();
+                }
+
+                pub fn total(&self) -> u32 {
+                    self.total
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Chunks } from "./out";
+
+            export function test() {
+                const source = Chunks.new(4);
+                const sink = Chunks.new(0);
+                return sink.consume(source.stream()).then(() => {
+                    assert.strictEqual(sink.total(), 2 * (3 + 2 + 1 + 0));
+                    source.free();
+                    sink.free();
+                });
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn doc_comments() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            /// A counter which "counts" things.
+            ///
+            /// Comments may contain `*/` and backslashes \ too.
+            #[wasm_bindgen]
+            pub struct Counter {
+                count: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                /// Creates a counter starting at zero.
+                pub fn new() -> Counter {
+                    Counter { count: 0 }
+                }
+
+                /// Bumps the count, returning the new value.
+                pub fn bump(&mut self) -> u32 {
+                    self.count += 1;
+                    self.count
+                }
+            }
+
+            /// Doubles `a`.
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn double(a: u32) -> u32 {
+                a * 2
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const c = wasm.Counter.new();
+                assert.strictEqual(c.bump(), 1);
+                assert.strictEqual(c.bump(), 2);
+                c.free();
+                assert.strictEqual(wasm.double(4), 8);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn constants() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                contents: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub const MAX: u32 = 10;
+                pub const SCALE: f64 = 0.5;
+                pub const ENABLED: bool = true;
+                const HIDDEN: u32 = 3;
+
+                pub fn new(contents: u32) -> Foo {
+                    assert!(contents <= Foo::MAX);
+                    Foo { contents: contents + Foo::HIDDEN }
+                }
+
+                pub fn get(&self) -> u32 {
+                    self.contents
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Foo } from "./out";
+
+            export function test() {
+                assert.strictEqual(Foo.MAX, 10);
+                assert.strictEqual(Foo.SCALE, 0.5);
+                assert.strictEqual(Foo.ENABLED, true);
+                assert.strictEqual((Foo as any).HIDDEN, undefined);
+
+                const a = Foo.new(Foo.MAX);
+                assert.strictEqual(a.get(), 13);
+                a.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn namespaces() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Arith;
+
+            #[wasm_bindgen]
+            impl Arith {
+                pub const ANSWER: u32 = 42;
+
+                pub fn double(a: u32) -> u32 {
+                    a * 2
+                }
+
+                pub fn greet(name: &str) -> String {
+                    format!("hello {}", name)
+                }
+            }
+
+            #[wasm_bindgen]
+            pub struct Token;
+
+            #[wasm_bindgen]
+            impl Token {
+                pub fn new() -> Token {
+                    Token
+                }
+
+                pub fn id(&self) -> u32 {
+                    1
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Arith, Token } from "./out";
+
+            export function test() {
+                assert.strictEqual(typeof Arith, "object");
+                assert.ok(Object.isFrozen(Arith));
+                assert.strictEqual(Arith.ANSWER, 42);
+                assert.strictEqual(Arith.double(3), 6);
+                assert.strictEqual(Arith.greet("wasm"), "hello wasm");
+                assert.strictEqual((Arith as any).free, undefined);
+
+                // unit structs with instances are still classes
+                const t = Token.new();
+                assert.ok(t instanceof Token);
+                assert.strictEqual(t.id(), 1);
+                t.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn symbol_dispose() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                contents: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo { contents: 3 }
+                }
+
+                pub fn get(&self) -> u32 {
+                    self.contents
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Foo } from "./out";
+
+            export function test() {
+                const dispose = (Symbol as any).dispose;
+                const a: any = Foo.new();
+                assert.strictEqual(a.get(), 3);
+                if (typeof dispose === 'symbol') {
+                    a[dispose]();
+                    assert.strictEqual(a.ptr, 0);
+                } else {
+                    assert.deepStrictEqual(Object.getOwnPropertySymbols(Foo.prototype), []);
+                    a.free();
+                }
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn renamed_free() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(free = "close")]
+            pub struct Connection {
+                open: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Connection {
+                pub fn new() -> Connection {
+                    Connection { open: 1 }
+                }
+
+                pub fn free(&self) -> u32 {
+                    self.open
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Connection } from "./out";
+
+            export function test() {
+                const c = Connection.new();
+                assert.strictEqual(c.free(), 1);
+                c.close();
+                assert.throws(() => c.close(), /has already been freed/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn is_freed() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                contents: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new(contents: u32) -> Foo {
+                    Foo { contents }
+                }
+
+                pub fn consume(other: Foo) -> u32 {
+                    other.contents
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Foo } from "./out";
+
+            export function test() {
+                const a = Foo.new(1);
+                assert.strictEqual(a.isFreed(), false);
+                a.free();
+                assert.strictEqual(a.isFreed(), true);
+
+                const b = Foo.new(2);
+                assert.strictEqual(Foo.consume(b), 2);
+                assert.strictEqual(b.isFreed(), true);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn optional() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                contents: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new(contents: u32) -> Foo {
+                    Foo { contents }
+                }
+
+                pub fn maybe(contents: u32) -> Option<Foo> {
+                    if contents == 0 {
+                        None
+                    } else {
+                        Some(Foo { contents })
+                    }
+                }
+
+                pub fn contents_or(other: Option<Foo>, default: u32) -> u32 {
+                    other.map(|f| f.contents).unwrap_or(default)
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Foo } from "./out";
+
+            export function test() {
+                assert.strictEqual(Foo.maybe(0), undefined);
+                const a = Foo.maybe(3);
+                assert.ok(a instanceof Foo);
+
+                assert.strictEqual(Foo.contents_or(a, 5), 3);
+                assert.strictEqual(a!.isFreed(), true);
+                assert.strictEqual(Foo.contents_or(undefined, 5), 5);
+
+                const b = Foo.new(4);
+                assert.strictEqual(Foo.contents_or(b, 5), 4);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn getters_and_setters() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Temperature {
+                celsius: f64,
+            }
+
+            #[wasm_bindgen]
+            impl Temperature {
+                pub fn new(celsius: f64) -> Temperature {
+                    Temperature { celsius }
+                }
+
+                #[wasm_bindgen(getter)]
+                pub fn celsius(&self) -> f64 {
+                    self.celsius
+                }
+
+                #[wasm_bindgen(setter)]
+                pub fn set_celsius(&mut self, celsius: f64) {
+                    self.celsius = celsius;
+                }
+
+                #[wasm_bindgen(getter)]
+                pub fn fahrenheit(&self) -> f64 {
+                    self.celsius * 1.8 + 32.0
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Temperature } from "./out";
+
+            export function test() {
+                const t = Temperature.new(100);
+                assert.strictEqual(t.celsius, 100);
+                assert.strictEqual(t.fahrenheit, 212);
+
+                t.celsius = -40;
+                assert.strictEqual(t.celsius, -40);
+                assert.strictEqual(t.fahrenheit, -40);
+
+                // `fahrenheit` is declared `readonly`
+                assert.throws(() => { (t as any).fahrenheit = 0; });
+                t.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn public_fields() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            pub struct Cache(Vec<u8>);
+
+            #[wasm_bindgen]
+            pub struct Person {
+                /// Full name
+                pub name: String,
+                pub age: u32,
+                pub height: Option<f64>,
+                pub position: (f64, f64),
+                #[wasm_bindgen(skip)]
+                pub cache: Cache,
+                secret: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Person {
+                pub fn new(name: String, age: u32) -> Person {
+                    Person {
+                        name,
+                        age,
+                        height: None,
+                        position: (1.0, 2.0),
+                        cache: Cache(Vec::new()),
+                        secret: 7,
+                    }
+                }
+
+                pub fn describe(&self) -> String {
+                    format!("{} ({}), {}", self.name, self.age, self.secret)
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Person } from "./out";
+
+            export function test() {
+                const p = Person.new("Ada", 36);
+                assert.strictEqual(p.name, "Ada");
+                assert.strictEqual(p.age, 36);
+                assert.strictEqual(p.height, undefined);
+                assert.deepStrictEqual(p.position, [1, 2]);
+
+                p.name = "Grace";
+                p.age += 1;
+                p.height = 1.6;
+                assert.strictEqual(p.height, 1.6);
+                assert.strictEqual(p.describe(), "Grace (37), 7");
+
+                assert.strictEqual((p as any).secret, undefined);
+                assert.strictEqual((p as any).cache, undefined);
+                assert.throws(() => { (p as any).position = [0, 0]; });
+                p.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn readonly_fields() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Account {
+                #[wasm_bindgen(readonly)]
+                pub id: u32,
+                pub balance: f64,
+            }
+
+            #[wasm_bindgen]
+            impl Account {
+                pub fn open(id: u32) -> Account {
+                    Account { id, balance: 0.0 }
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Account } from "./out";
+
+            export function test() {
+                const a = Account.open(3);
+                assert.strictEqual(a.id, 3);
+                a.balance = 10;
+                assert.strictEqual(a.balance, 10);
+
+                assert.throws(() => { (a as any).id = 4; });
+                assert.strictEqual(a.id, 3);
+                a.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn constructors() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Counter {
+                count: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                #[wasm_bindgen(constructor)]
+                pub fn new(start: u32) -> Counter {
+                    Counter { count: start }
+                }
+
+                pub fn increment(&mut self) -> u32 {
+                    self.count += 1;
+                    self.count
+                }
+
+                pub fn fork(&self) -> Counter {
+                    Counter { count: self.count }
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Counter } from "./out";
+
+            export function test() {
+                const c = new Counter(5);
+                assert.ok(c instanceof Counter);
+                assert.strictEqual(c.increment(), 6);
+
+                const d = c.fork();
+                assert.ok(d instanceof Counter);
+                assert.strictEqual(d.increment(), 7);
+                assert.strictEqual(c.increment(), 7);
+
+                assert.strictEqual((Counter as any).new, undefined);
+                c.free();
+                d.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn js_names() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(js_name = "Point")]
+            pub struct RustPoint {
+                pub x: u32,
+                pub y: u32,
+            }
+
+            #[wasm_bindgen]
+            pub fn make_point(x: u32, y: u32) -> RustPoint {
+                RustPoint { x, y }
+            }
+
+            #[wasm_bindgen]
+            pub fn point_sum(p: &RustPoint) -> u32 {
+                p.x + p.y
+            }
+
+            #[wasm_bindgen]
+            pub struct Counter {
+                count: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                #[wasm_bindgen(js_name = "startingAt")]
+                pub fn starting_at(count: u32) -> Counter {
+                    Counter { count }
+                }
+
+                #[wasm_bindgen(js_name = "addTo")]
+                pub fn add_to(&mut self, amount: u32) -> u32 {
+                    self.count += amount;
+                    self.count
+                }
+
+                #[wasm_bindgen(js_name = "MAX_COUNT")]
+                pub const MAX: u32 = 10;
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Point, Counter, make_point, point_sum } from "./out";
+
+            export function test() {
+                const p = make_point(1, 2);
+                assert.ok(p instanceof Point);
+                assert.strictEqual(p.x, 1);
+                p.y = 5;
+                assert.strictEqual(point_sum(p), 6);
+                p.free();
+
+                const c = Counter.startingAt(3);
+                assert.strictEqual(c.addTo(4), 7);
+                assert.strictEqual(Counter.MAX_COUNT, 10);
+                assert.strictEqual((Counter as any).starting_at, undefined);
+                assert.strictEqual((c as any).add_to, undefined);
+                c.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn js_class() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(js_name = "Counter")]
+            pub struct RustCounter {
+                count: u32,
+            }
+
+            #[wasm_bindgen(js_class = "Counter")]
+            impl RustCounter {
+                #[wasm_bindgen(constructor)]
+                pub fn new(count: u32) -> RustCounter {
+                    RustCounter { count }
+                }
+
+                pub fn increment(&mut self) -> u32 {
+                    self.count += 1;
+                    self.count
+                }
+
+                pub const START: u32 = 0;
+            }
+
+            #[wasm_bindgen(js_class = "Counter")]
+            impl RustCounter {
+                #[wasm_bindgen(getter)]
+                pub fn count(&self) -> u32 {
+                    self.count
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Counter } from "./out";
+
+            export function test() {
+                const c = new Counter(Counter.START);
+                assert.strictEqual(c.increment(), 1);
+                assert.strictEqual(c.increment(), 2);
+                assert.strictEqual(c.count, 2);
+                c.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn iterators() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Countdown {
+                left: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Countdown {
+                pub fn new(from: u32) -> Countdown {
+                    Countdown { left: from }
+                }
+            }
+
+            #[wasm_bindgen]
+            impl Iterator for Countdown {
+                type Item = u32;
+
+                fn next(&mut self) -> Option<u32> {
+                    if self.left == 0 {
+                        return None
+                    }
+                    self.left -= 1;
+                    Some(self.left)
+                }
+            }
+
+            #[wasm_bindgen]
+            pub struct Words {
+                words: Vec<String>,
+            }
+
+            #[wasm_bindgen]
+            impl Words {
+                pub fn new(s: &str) -> Words {
+                    Words { words: s.split(' ').rev().map(|s| s.to_string()).collect() }
+                }
+            }
+
+            #[wasm_bindgen]
+            impl Iterator for Words {
+                type Item = String;
+
+                fn next(&mut self) -> Option<Self::Item> {
+                    self.words.pop()
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { Countdown, Words } from "./out";
+
+            export function test() {
+                const c = Countdown.new(3);
+                assert.strictEqual(c[Symbol.iterator](), c);
+                assert.deepStrictEqual(Array.from(c), [2, 1, 0]);
+                assert.deepStrictEqual(c.next(), { done: true, value: undefined });
+                c.free();
+
+                const w = Words.new("a bc d");
+                const first = w.next();
+                assert.strictEqual(first.done, false);
+                assert.strictEqual(first.value, "a");
+                const rest: string[] = [];
+                for (const word of Array.from(w)) {
+                    rest.push(word);
+                }
+                assert.deepStrictEqual(rest, ["bc", "d"]);
+                w.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn display() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use std::fmt;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(display)]
+            pub struct Point {
+                pub x: i32,
+                pub y: i32,
+            }
+
+            impl fmt::Display for Point {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(f, "({}, {})", self.x, self.y)
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn point(x: i32, y: i32) -> Point {
+                Point { x, y }
+            }
+
+            #[wasm_bindgen]
+            pub struct Plain {}
+
+            #[wasm_bindgen]
+            pub fn plain() -> Plain {
+                Plain {}
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { point, plain } from "./out";
+
+            export function test() {
+                const p = point(1, -2);
+                assert.strictEqual(p.toString(), "(1, -2)");
+                assert.strictEqual(`${p}`, "(1, -2)");
+                p.x = 3;
+                assert.strictEqual(String(p), "(3, -2)");
+                p.free();
+
+                const q = plain();
+                assert.strictEqual(q.toString(), "[object Object]");
+                q.free();
+            }
+        "#)
+        .test();
+}
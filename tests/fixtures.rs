@@ -0,0 +1,27 @@
+//! End-to-end tests for the crates in `tests/fixtures`.
+//!
+//! Each fixture directory holds a `lib.rs` and a `test.ts` which are built
+//! and run under Node just like the inline tests elsewhere, but are easier to
+//! grow since they're ordinary files. To add one, create the directory and
+//! list it in the `fixtures!` invocation below.
+
+extern crate test_support;
+
+macro_rules! fixtures {
+    ($($name:ident)*) => ($(
+        #[test]
+        fn $name() {
+            test_support::project()
+                .file("src/lib.rs",
+                      include_str!(concat!("fixtures/", stringify!($name), "/lib.rs")))
+                .file("test.ts",
+                      include_str!(concat!("fixtures/", stringify!($name), "/test.ts")))
+                .test();
+        }
+    )*)
+}
+
+fixtures! {
+    strings
+    classes
+}
@@ -0,0 +1,42 @@
+#![feature(proc_macro)]
+
+extern crate wasm_bindgen;
+
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
+
+use wasm_bindgen::prelude::*;
+
+static LIVE: AtomicUsize = ATOMIC_USIZE_INIT;
+
+#[no_mangle]
+#[wasm_bindgen]
+pub extern fn live() -> u32 {
+    LIVE.load(Ordering::SeqCst) as u32
+}
+
+#[wasm_bindgen]
+pub struct Tracked {
+    value: u32,
+}
+
+#[wasm_bindgen]
+impl Tracked {
+    pub fn new(value: u32) -> Tracked {
+        LIVE.fetch_add(1, Ordering::SeqCst);
+        Tracked { value }
+    }
+
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+
+    pub fn merge(&self, other: Tracked) -> Tracked {
+        Tracked::new(self.value + other.value)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        LIVE.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,19 @@
+import * as assert from "assert";
+import * as wasm from "./out";
+
+export function test() {
+    assert.strictEqual(wasm.live(), 0);
+
+    const a = wasm.Tracked.new(1);
+    const b = wasm.Tracked.new(2);
+    assert.strictEqual(wasm.live(), 2);
+
+    // Passing `b` by value moves it into Rust, which drops it.
+    const c = a.merge(b);
+    assert.strictEqual(c.value(), 3);
+    assert.strictEqual(wasm.live(), 2);
+
+    a.free();
+    c.free();
+    assert.strictEqual(wasm.live(), 0);
+}
@@ -0,0 +1,23 @@
+#![feature(proc_macro)]
+
+extern crate wasm_bindgen;
+
+use wasm_bindgen::prelude::*;
+
+#[no_mangle]
+#[wasm_bindgen]
+pub extern fn echo(a: &str) -> String {
+    a.to_string()
+}
+
+#[no_mangle]
+#[wasm_bindgen]
+pub extern fn char_count(a: &str) -> u32 {
+    a.chars().count() as u32
+}
+
+#[no_mangle]
+#[wasm_bindgen]
+pub extern fn repeat(a: &str, n: u32) -> String {
+    a.repeat(n as usize)
+}
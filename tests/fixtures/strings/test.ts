@@ -0,0 +1,13 @@
+import * as assert from "assert";
+import * as wasm from "./out";
+
+export function test() {
+    for (const s of ["", "a", "hello, world", "ünïcödé", "日本語", "🦀🦀", "a\u0000b"]) {
+        assert.strictEqual(wasm.echo(s), s);
+        assert.strictEqual(wasm.char_count(s), Array.from(s).length);
+    }
+
+    const big = wasm.repeat("xy", 100000);
+    assert.strictEqual(big.length, 200000);
+    assert.strictEqual(wasm.echo(big), big);
+}
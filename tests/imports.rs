@@ -289,3 +289,734 @@ fn free_imports() {
         "#)
         .test();
 }
+
+#[test]
+fn this_arg() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(this_arg)]
+                fn detached(this: &JsValue, a: u32) -> u32;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run(obj: &JsValue) -> u32 {
+                detached(obj, 2)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import { run } from "./out";
+
+            export function detached(this: { value: number }, a: number): number {
+                return this.value + a;
+            }
+
+            export function test() {
+                assert.strictEqual(run({ value: 40 }), 42);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn globals() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            extern {
+                #[wasm_bindgen(global = "globalThis.appScale")]
+                fn app_scale() -> f64;
+                #[wasm_bindgen(global = "globalThis.appScale")]
+                fn set_app_scale(scale: f64);
+                #[wasm_bindgen(global = "globalThis.appConfig")]
+                fn set_app_config(config: JsValue);
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn double_scale() -> f64 {
+                set_app_scale(app_scale() * 2.0);
+                set_app_config(JsValue::from("configured"));
+                app_scale()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const g = global as any;
+                g.appScale = 1.5;
+                assert.strictEqual(wasm.double_scale(), 3);
+                assert.strictEqual(g.appScale, 3);
+                assert.strictEqual(g.appConfig, "configured");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn generators() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen::{GeneratorState, JsGenerator};
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn accumulate() -> JsGenerator;
+            }
+
+            fn yielded(state: GeneratorState) -> f64 {
+                match state {
+                    GeneratorState::Yielded(v) => v.as_f64().unwrap(),
+                    GeneratorState::Complete(_) => panic!("generator finished"),
+                }
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run() {
+                let gen = accumulate();
+                assert_eq!(yielded(gen.next(&JsValue::undefined())), 0.0);
+                assert_eq!(yielded(gen.next(&JsValue::from(2))), 2.0);
+                assert_eq!(yielded(gen.next(&JsValue::from(3))), 5.0);
+                assert_eq!(yielded(gen.throw_(&JsValue::from("reset"))), 0.0);
+                match gen.return_(&JsValue::from(7)) {
+                    GeneratorState::Complete(v) => assert_eq!(v.as_f64(), Some(7.0)),
+                    GeneratorState::Yielded(_) => panic!("generator didn't finish"),
+                }
+                match gen.next(&JsValue::undefined()) {
+                    GeneratorState::Complete(v) => assert!(v.is_undefined()),
+                    GeneratorState::Yielded(_) => panic!("generator resumed"),
+                }
+            }
+        "#)
+        .file("test.js", r#"
+            import { run } from "./out";
+
+            export function* accumulate() {
+                let total = 0;
+                while (true) {
+                    try {
+                        total += yield total;
+                    } catch (e) {
+                        total = 0;
+                    }
+                }
+            }
+
+            export function test() {
+                run();
+            }
+        "#)
+        .file("test.d.ts", r#"
+            export function test(): void;
+            export function accumulate(): any;
+        "#)
+        .test();
+}
+
+#[test]
+fn optional_returns() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn lookup(key: &str) -> Option<JsValue>;
+                fn lookup_num(key: &str) -> Option<f64>;
+                fn lookup_str(key: &str) -> Option<String>;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run() {
+                assert!(lookup("missing").is_none());
+                assert!(lookup("null").is_none());
+                assert_eq!(lookup("a").unwrap().as_f64(), Some(1.0));
+                assert_eq!(lookup_num("a"), Some(1.0));
+                assert_eq!(lookup_num("zero"), Some(0.0));
+                assert_eq!(lookup_num("missing"), None);
+                assert_eq!(lookup_str("s"), Some(String::from("hi")));
+                assert_eq!(lookup_str("null"), None);
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            const MAP: { [key: string]: any } = { a: 1, zero: 0, s: "hi", null: null };
+
+            export function lookup(key: string): any {
+                return MAP[key];
+            }
+
+            export function lookup_num(key: string): number | undefined {
+                return MAP[key];
+            }
+
+            export function lookup_str(key: string): string | null | undefined {
+                return MAP[key];
+            }
+
+            export function test() {
+                run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn optional_arguments() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn describe(a: Option<f64>, b: Option<bool>, c: u32) -> String;
+                fn quote(s: Option<&str>) -> String;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run() {
+                assert_eq!(describe(None, None, 1), "undefined undefined 1");
+                assert_eq!(describe(Some(0.0), Some(false), 2), "0 false 2");
+                assert_eq!(describe(Some(1.5), None, 3), "1.5 undefined 3");
+                assert_eq!(quote(None), "none");
+                assert_eq!(quote(Some("")), "''");
+                assert_eq!(quote(Some("hi")), "'hi'");
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export function describe(a: number | undefined,
+                                     b: boolean | undefined,
+                                     c: number): string {
+                return `${a} ${b} ${c}`;
+            }
+
+            export function quote(s: string | undefined): string {
+                return s === undefined ? "none" : `'${s}'`;
+            }
+
+            export function test() {
+                run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn chars() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn to_upper(c: char) -> char;
+                fn describe(c: char) -> String;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run() {
+                assert_eq!(to_upper('a'), 'A');
+                assert_eq!(to_upper('\u{e9}'), '\u{c9}');
+                assert_eq!(describe('\u{1f600}'), "\u{1f600} 2");
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export function to_upper(c: string): string {
+                return c.toUpperCase();
+            }
+
+            export function describe(c: string): string {
+                return `${c} ${c.length}`;
+            }
+
+            export function test() {
+                run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn dates() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn next_day(d: SystemTime) -> SystemTime;
+                fn year(d: SystemTime) -> u32;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run() {
+                let t = UNIX_EPOCH + Duration::from_millis(1_500);
+                let day = Duration::from_secs(24 * 60 * 60);
+                assert_eq!(next_day(t), t + day);
+                assert_eq!(next_day(UNIX_EPOCH - day), UNIX_EPOCH);
+                assert_eq!(year(t), 1970);
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            export function next_day(d: Date): Date {
+                return new Date(d.getTime() + 24 * 60 * 60 * 1000);
+            }
+
+            export function year(d: Date): number {
+                return d.getUTCFullYear();
+            }
+
+            export function test() {
+                run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn durations() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use std::time::Duration;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn twice(d: Duration) -> Duration;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn seconds(d: Duration) -> u32 {
+                d.as_secs() as u32
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn backoff(attempt: u32) -> Duration {
+                twice(Duration::from_millis(250 * attempt as u64))
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function twice(d: number): number {
+                return d * 2;
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.seconds(2500), 2);
+                assert.strictEqual(wasm.seconds(0.5), 0);
+                assert.strictEqual(wasm.backoff(3), 1500);
+                assert.throws(() => wasm.seconds(-1), /invalid Duration/);
+                assert.throws(() => wasm.seconds(NaN), /invalid Duration/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn int64() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn double(a: i64) -> i64;
+                fn describe(a: u64) -> String;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run() {
+                assert_eq!(double(-3), -6);
+                assert_eq!(double(1 << 40), 1 << 41);
+                assert_eq!(describe(u64::max_value()), "18446744073709551615");
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            const big: (n: number) => bigint = (global as any).BigInt;
+
+            export function double(a: bigint): bigint {
+                return a * big(2);
+            }
+
+            export function describe(a: bigint): string {
+                return a.toString();
+            }
+
+            export function test() {
+                if (big !== undefined)
+                    run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn int128() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn double(a: i128) -> i128;
+                fn describe(a: u128) -> String;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run() {
+                assert_eq!(double(-3), -6);
+                assert_eq!(double(1 << 100), 1 << 101);
+                assert_eq!(describe(u128::max_value()),
+                           "340282366920938463463374607431768211455");
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+
+            const big: (n: number) => bigint = (global as any).BigInt;
+
+            export function double(a: bigint): bigint {
+                return a * big(2);
+            }
+
+            export function describe(a: bigint): string {
+                return a.toString();
+            }
+
+            export function test() {
+                if (big !== undefined)
+                    run();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn typed_catch() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                type MyError;
+                #[wasm_bindgen(method)]
+                fn code(this: &MyError) -> u32;
+
+                #[wasm_bindgen(catch)]
+                fn risky(kind: u32) -> Result<u32, MyError>;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run(kind: u32) -> u32 {
+                match risky(kind) {
+                    Ok(n) => n,
+                    Err(e) => 100 + e.code(),
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+            import * as assert from "assert";
+
+            export class MyError {
+                constructor(public value: number) {}
+
+                code(): number {
+                    return this.value;
+                }
+            }
+
+            export function risky(kind: number): number {
+                if (kind === 1)
+                    throw new MyError(7);
+                if (kind === 2)
+                    throw new Error('unexpected');
+                return 5;
+            }
+
+            export function test() {
+                assert.strictEqual(run(0), 5);
+                assert.strictEqual(run(1), 107);
+                assert.throws(() => run(2), /unexpected/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn typed_catch_global() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            extern {
+                type TypeError;
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(catch)]
+                fn risky(kind: u32) -> Result<u32, TypeError>;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run(kind: u32) -> u32 {
+                match risky(kind) {
+                    Ok(n) => n,
+                    Err(_) => 100,
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import { run } from "./out";
+            import * as assert from "assert";
+
+            export function risky(kind: number): number {
+                if (kind === 1)
+                    throw new TypeError('bad type');
+                if (kind === 2)
+                    throw new RangeError('out of range');
+                return 5;
+            }
+
+            export function test() {
+                assert.strictEqual(run(0), 5);
+                assert.strictEqual(run(1), 100);
+                assert.throws(() => run(2), RangeError);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn string_enums() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[derive(Copy, Clone, PartialEq, Debug)]
+            pub enum ContextKind {
+                TwoD = "2d",
+                WebGl = "webgl",
+            }
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn describe(kind: ContextKind) -> JsValue;
+                fn preferred() -> ContextKind;
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run() {
+                assert_eq!(describe(ContextKind::TwoD).as_string(), Some("2d".to_string()));
+                assert_eq!(describe(ContextKind::WebGl).as_string(), Some("webgl".to_string()));
+                assert_eq!(preferred(), ContextKind::WebGl);
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn flip(kind: ContextKind) -> ContextKind {
+                match kind {
+                    ContextKind::TwoD => ContextKind::WebGl,
+                    ContextKind::WebGl => ContextKind::TwoD,
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function describe(kind: wasm.ContextKind): any {
+                return kind;
+            }
+
+            export function preferred(): wasm.ContextKind {
+                return "webgl";
+            }
+
+            export function test() {
+                wasm.run();
+                assert.strictEqual(wasm.flip("2d"), "webgl");
+                assert.strictEqual(wasm.flip("webgl"), "2d");
+                assert.throws(() => wasm.flip("3d" as any), /invalid value for ContextKind/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn static_strings() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                fn record(name: &'static str);
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn run() {
+                const NAME: &'static str = "click";
+                for _ in 0..3 {
+                    record(NAME);
+                }
+                record(&NAME[..3]);
+                record("keydown");
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            const names: string[] = [];
+
+            export function record(name: string) {
+                names.push(name);
+            }
+
+            export function test() {
+                wasm.run();
+                assert.deepStrictEqual(names, ["click", "click", "click", "cli", "keydown"]);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn ascii_strings() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(module = "./test")]
+            extern {
+                #[wasm_bindgen(ascii = "id")]
+                fn record(id: &str, label: &str);
+            }
+
+            #[wasm_bindgen(ascii = "id")]
+            #[no_mangle]
+            pub extern fn forward(id: &str, label: &str) -> u32 {
+                record(id, label);
+                id.len() as u32
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as wasm from "./out";
+            import * as assert from "assert";
+
+            let SEEN: string[] = [];
+
+            export function record(id: string, label: string): void {
+                SEEN.push(id, label);
+            }
+
+            export function test() {
+                assert.strictEqual(wasm.forward("user-42", "ünïcödé"), 7);
+                assert.deepStrictEqual(SEEN, ["user-42", "ünïcödé"]);
+                assert.throws(() => wasm.forward("ü", ""), /non-ASCII/);
+            }
+        "#)
+        .test();
+}
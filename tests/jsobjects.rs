@@ -176,3 +176,163 @@ fn promote() {
         "#)
         .test();
 }
+
+#[test]
+fn serde_json_values() {
+    test_support::project()
+        .feature("serde-json")
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn round_trip(a: &JsValue) -> JsValue {
+                let json = a.to_json().unwrap();
+                JsValue::from(&json)
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn describe(a: &JsValue) -> String {
+                match a.to_json() {
+                    Some(json) => json.to_string(),
+                    None => String::from("invalid"),
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const doc = { x: 1.5, y: [1, "two", false, null], z: { nested: {} } };
+                assert.deepStrictEqual(wasm.round_trip(doc), doc);
+                assert.strictEqual(wasm.describe([1, 2]), "[1.0,2.0]");
+                assert.strictEqual(wasm.describe(() => 1), "invalid");
+                assert.strictEqual(wasm.describe({ a: NaN }), "invalid");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn serde_values() {
+    test_support::project()
+        .feature("serde-json")
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use std::collections::BTreeMap;
+
+            use wasm_bindgen::prelude::*;
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn config() -> JsValue {
+                let mut map = BTreeMap::new();
+                map.insert("name", vec![(1, "one"), (2, "two")]);
+                map.insert("empty", Vec::new());
+                JsValue::from_serde(&map).unwrap()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn sum(a: &JsValue) -> f64 {
+                let map: BTreeMap<String, Vec<f64>> = a.into_serde().unwrap();
+                map.values().flat_map(|v| v.iter()).sum()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn is_valid(a: &JsValue) -> bool {
+                a.into_serde::<Option<Vec<String>>>().is_ok()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.deepStrictEqual(wasm.config(), {
+                    empty: [],
+                    name: [[1, "one"], [2, "two"]],
+                });
+                assert.strictEqual(wasm.sum({ a: [1, 2], b: [], c: [0.5] }), 3.5);
+                assert.strictEqual(wasm.is_valid(["a", "\u{1f600}"]), true);
+                assert.strictEqual(wasm.is_valid(undefined), true);
+                assert.strictEqual(wasm.is_valid([1]), false);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn serde_direct() {
+    test_support::project()
+        .feature("serde-json")
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use std::collections::BTreeMap;
+
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen::{de, ser};
+
+            type Doc = BTreeMap<String, (Option<u32>, Vec<Result<i64, String>>)>;
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn make() -> JsValue {
+                let mut doc = Doc::new();
+                doc.insert("a".to_string(), (Some(1), vec![Ok(-2), Err("bad".to_string())]));
+                doc.insert("b".to_string(), (None, vec![]));
+                ser::to_value(&doc).unwrap()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn round_trip(a: &JsValue) -> JsValue {
+                let doc: Doc = de::from_value(a).unwrap();
+                ser::to_value(&doc).unwrap()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn keys(a: &JsValue) -> JsValue {
+                let mut map = BTreeMap::new();
+                map.insert(1, 'x');
+                map.insert(20, 'y');
+                match de::from_value::<Vec<u8>>(a) {
+                    Ok(_) => ser::to_value(&map).unwrap(),
+                    Err(_) => JsValue::null(),
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const doc = {
+                    a: [1, [{ Ok: -2 }, { Err: "bad" }]],
+                    b: [null, []],
+                };
+                assert.deepStrictEqual(wasm.make(), doc);
+                assert.deepStrictEqual(wasm.round_trip(doc), doc);
+                assert.deepStrictEqual(wasm.round_trip({ c: [7.0, [{ Ok: 3 }]] }),
+                                       { c: [7, [{ Ok: 3 }]] });
+                assert.deepStrictEqual(wasm.keys([1, 2]), { 1: "x", 20: "y" });
+                assert.strictEqual(wasm.keys([1.5]), null);
+                assert.strictEqual(wasm.keys(["a"]), null);
+            }
+        "#)
+        .test();
+}
@@ -37,6 +37,71 @@ fn works() {
                 assert.strictEqual(wasm.clone(sym), sym);
                 let a = wasm.A.new();
                 a.free();
+                a.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn numeric_exports() {
+    test_support::project()
+        .debug(false)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn add(a: u32, b: u32) -> u32 {
+                a + b
+            }
+
+            #[wasm_bindgen]
+            pub fn scale(a: f64) -> f64 {
+                a * 2.0
+            }
+
+            #[wasm_bindgen]
+            pub fn nothing(_a: i32) {}
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.add(1, 2), 3);
+                assert.strictEqual(wasm.scale(1.5), 3);
+                assert.strictEqual(wasm.nothing(3), undefined);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn tuple_exports() {
+    test_support::project()
+        .debug(false)
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn polar(x: f64, y: f64) -> (f64, f64) {
+                ((x * x + y * y).sqrt(), y.atan2(x))
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.deepStrictEqual(wasm.polar(0, 2), [2, Math.PI / 2]);
             }
         "#)
         .test();
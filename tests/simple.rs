@@ -212,3 +212,1195 @@ fn other_exports() {
         "#)
         .test();
 }
+
+#[test]
+fn byte_vectors() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn sum(a: Vec<u8>) -> u32 {
+                a.iter().map(|i| *i as u32).sum()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn reverse(mut a: Vec<u8>) -> Vec<u8> {
+                a.reverse();
+                a
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen(array_buffer)]
+            pub extern fn buffer(len: u32) -> Vec<u8> {
+                (0..len).map(|i| i as u8).collect()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn max(a: &[u8]) -> u32 {
+                a.iter().cloned().max().unwrap_or(0) as u32
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.sum(new Uint8Array([1, 2, 3])), 6);
+
+                const buf = new Uint8Array([4, 5]).buffer;
+                assert.strictEqual(wasm.sum(buf), 9);
+                if (typeof((global as any).structuredClone) === 'function')
+                    assert.strictEqual(buf.byteLength, 0);
+
+                const rev = wasm.reverse(new Uint8Array([1, 2, 3]));
+                assert.strictEqual(rev instanceof Uint8Array, true);
+                assert.deepStrictEqual(Array.from(rev), [3, 2, 1]);
+
+                const ab = wasm.buffer(3);
+                assert.strictEqual(ab instanceof ArrayBuffer, true);
+                assert.deepStrictEqual(Array.from(new Uint8Array(ab)), [0, 1, 2]);
+
+                // Borrowed slices leave the caller's buffer alone
+                const borrowed = new Uint8Array([7, 9, 8]).buffer;
+                assert.strictEqual(wasm.max(borrowed), 9);
+                assert.strictEqual(borrowed.byteLength, 3);
+                assert.strictEqual(wasm.max(new Uint8Array([1, 2, 3]).subarray(0, 2)), 2);
+                assert.strictEqual(wasm.max(new Uint8Array([])), 0);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn typed_arrays() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn sum_i8(a: &[i8]) -> i32 {
+                a.iter().map(|i| *i as i32).sum()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn sum_u16(a: &[u16]) -> u32 {
+                a.iter().map(|i| *i as u32).sum()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn sum_i32(a: &[i32], b: &[u32]) -> f64 {
+                a.iter().map(|i| *i as f64).sum::<f64>() +
+                    b.iter().map(|i| *i as f64).sum::<f64>()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn dot(a: &[f64], b: &[f32]) -> f64 {
+                a.iter().zip(b).map(|(a, b)| *a * *b as f64).sum()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn squares(n: u32) -> Vec<f64> {
+                (0..n).map(|i| (i * i) as f64).collect()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn negate(mut a: Vec<i32>) -> Vec<i32> {
+                for i in a.iter_mut() {
+                    *i = -*i;
+                }
+                a
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn halve(a: Vec<f64>) -> Vec<f64> {
+                assert_eq!(a.as_ptr() as usize % 8, 0);
+                a.into_iter().map(|f| f / 2.0).collect()
+            }
+
+            #[wasm_bindgen]
+            pub struct Samples {
+                data: Vec<i16>,
+            }
+
+            #[wasm_bindgen]
+            impl Samples {
+                pub fn new(data: &[i16]) -> Samples {
+                    Samples { data: data.to_vec() }
+                }
+
+                pub fn data(&self) -> &[i16] {
+                    &self.data
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.sum_i8(new Int8Array([-1, -2, 10])), 7);
+                assert.strictEqual(wasm.sum_u16(new Uint16Array([65535, 1])), 65536);
+                assert.strictEqual(wasm.sum_i32(new Int32Array([-5]), new Uint32Array([0xffffffff])),
+                                   0xffffffff - 5);
+                assert.strictEqual(wasm.dot(new Float64Array([0.5, 2]), new Float32Array([4, 3])), 8);
+                assert.throws(() => wasm.dot([0.5, 2] as any, new Float32Array([4, 3])));
+                assert.strictEqual(wasm.dot(new Float64Array([]), new Float32Array([])), 0);
+                assert.strictEqual(wasm.sum_u16(new Uint16Array([])), 0);
+
+                const s = wasm.Samples.new(new Int16Array([-3, 7, 9]));
+                const data = s.data();
+                assert.strictEqual(data instanceof Int16Array, true);
+                assert.deepStrictEqual(Array.from(data), [-3, 7, 9]);
+                assert.deepStrictEqual(Array.from(s.data(1, 1)), [7]);
+                s.free();
+
+                const sq = wasm.squares(4);
+                assert.strictEqual(sq instanceof Float64Array, true);
+                assert.deepStrictEqual(Array.from(sq), [0, 1, 4, 9]);
+                assert.strictEqual(wasm.squares(0).length, 0);
+
+                const neg = wasm.negate(new Int32Array([1, -2, 3]));
+                assert.strictEqual(neg instanceof Int32Array, true);
+                assert.deepStrictEqual(Array.from(neg), [-1, 2, -3]);
+                assert.strictEqual(wasm.negate(new Int32Array([])).length, 0);
+
+                assert.deepStrictEqual(Array.from(wasm.halve(new Float64Array([3, -1]))), [1.5, -0.5]);
+                assert.strictEqual(wasm.halve(new Float64Array([])).length, 0);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn boxed() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn shout(a: Box<str>) -> Box<str> {
+                a.to_uppercase().into_boxed_str()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn doubled(a: Box<[u8]>) -> Box<[u8]> {
+                a.iter().map(|i| i * 2).collect::<Vec<_>>().into_boxed_slice()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.shout("hello"), "HELLO");
+                assert.strictEqual(wasm.shout(""), "");
+
+                const d = wasm.doubled(new Uint8Array([1, 2, 3]));
+                assert.strictEqual(d instanceof Uint8Array, true);
+                assert.deepStrictEqual(Array.from(d), [2, 4, 6]);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn blobs() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[no_mangle]
+            #[wasm_bindgen(blob = "text/plain")]
+            pub extern fn greeting() -> Vec<u8> {
+                b"hello".to_vec()
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen(read_blobs)]
+            pub extern fn concat_len(a: Vec<u8>, b: Vec<u8>) -> u32 {
+                (a.len() + b.len()) as u32
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const blob = wasm.greeting();
+                assert.strictEqual(blob instanceof Blob, true);
+                assert.strictEqual(blob.type, "text/plain");
+                assert.strictEqual(blob.size, 5);
+
+                return blob.text()
+                    .then(text => {
+                        assert.strictEqual(text, "hello");
+                        return wasm.concat_len(blob, new Uint8Array([1, 2]));
+                    })
+                    .then(len => assert.strictEqual(len, 7));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn optional_args() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn scale(a: u32, factor: Option<u32>, round: Option<bool>) -> u32 {
+                let ret = a * factor.unwrap_or(2);
+                if round.unwrap_or(false) {
+                    ret / 10 * 10
+                } else {
+                    ret
+                }
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn describe(a: Option<JsValue>) -> JsValue {
+                match a {
+                    Some(a) => a,
+                    None => JsValue::from_str("missing"),
+                }
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn clamp(min: Option<f64>, x: f64) -> f64 {
+                min.map(|m| m.max(x)).unwrap_or(x)
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn half(a: u32) -> Option<u32> {
+                if a % 2 == 0 { Some(a / 2) } else { None }
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn negate(a: Option<bool>) -> Option<bool> {
+                a.map(|a| !a)
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn greet(name: Option<&str>) -> String {
+                format!("hello {}", name.unwrap_or("stranger"))
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn upper(s: Option<String>) -> Option<String> {
+                s.map(|s| s.to_uppercase())
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.scale(3), 6);
+                assert.strictEqual(wasm.scale(3, 5), 15);
+                assert.strictEqual(wasm.scale(3, undefined, true), 0);
+                assert.strictEqual(wasm.scale(7, 3, true), 20);
+                assert.strictEqual(wasm.describe(), "missing");
+                assert.strictEqual(wasm.describe(undefined), "missing");
+                assert.strictEqual(wasm.describe("here"), "here");
+
+                assert.strictEqual(wasm.clamp(undefined, -1), -1);
+                assert.strictEqual(wasm.clamp(0, -1), 0);
+                assert.strictEqual(wasm.half(4), 2);
+                assert.strictEqual(wasm.half(0), 0);
+                assert.strictEqual(wasm.half(3), undefined);
+                assert.strictEqual(wasm.negate(true), false);
+                assert.strictEqual(wasm.negate(false), true);
+                assert.strictEqual(wasm.negate(undefined), undefined);
+
+                assert.strictEqual(wasm.greet(), "hello stranger");
+                assert.strictEqual(wasm.greet(""), "hello ");
+                assert.strictEqual(wasm.greet("ferris"), "hello ferris");
+                assert.strictEqual(wasm.upper("ab"), "AB");
+                assert.strictEqual(wasm.upper(""), "");
+                assert.strictEqual(wasm.upper(null), undefined);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn chars() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn next_char(c: char) -> char {
+                ::std::char::from_u32(c as u32 + 1).unwrap()
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn code(c: char) -> u32 {
+                c as u32
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn first(s: &str) -> Option<char> {
+                s.chars().next()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.next_char("a"), "b");
+                assert.strictEqual(wasm.code("a"), 97);
+                assert.strictEqual(wasm.code("\u00e9"), 0xe9);
+                assert.strictEqual(wasm.code("\u{1f600}"), 0x1f600);
+                assert.strictEqual(wasm.next_char("\u{1f600}"), "\u{1f601}");
+                assert.strictEqual(wasm.first("\u{1f600}!"), "\u{1f600}");
+                assert.strictEqual(wasm.first(""), undefined);
+                assert.throws(() => wasm.code("ab"));
+                assert.throws(() => wasm.code(""));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn int64() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn add_u64(a: u64, b: u64) -> u64 {
+                a.wrapping_add(b)
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn neg_i64(a: i64) -> i64 {
+                -a
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn high(a: u64) -> u32 {
+                (a >> 32) as u32
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            const big: (n: number | string) => bigint = (global as any).BigInt;
+
+            export function test() {
+                if (big === undefined) {
+                    assert.throws(() => wasm.neg_i64(1 as any), /BigInt/);
+                    return;
+                }
+                assert.strictEqual(wasm.add_u64(big(1), big(2)), big(3));
+                assert.strictEqual(wasm.add_u64(big("0xffffffff"), big(1)), big("0x100000000"));
+                assert.strictEqual(wasm.add_u64(big("0xffffffffffffffff"), big(2)), big(1));
+                assert.strictEqual(wasm.high(big("0x123456789abcdef0")), 0x12345678);
+                assert.strictEqual(wasm.neg_i64(big(5)), big(-5));
+                assert.strictEqual(wasm.neg_i64(big("-9223372036854775807")),
+                                   big("9223372036854775807"));
+                assert.throws(() => wasm.neg_i64(5 as any));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn int128() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn add_u128(a: u128, b: u128) -> u128 {
+                a.wrapping_add(b)
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn neg_i128(a: i128) -> i128 {
+                -a
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn limb(a: u128, i: u32) -> u32 {
+                (a >> (i * 32)) as u32
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            const big: (n: number | string) => bigint = (global as any).BigInt;
+
+            export function test() {
+                if (big === undefined) {
+                    assert.throws(() => wasm.neg_i128(1 as any), /BigInt/);
+                    return;
+                }
+                assert.strictEqual(wasm.add_u128(big(1), big(2)), big(3));
+                assert.strictEqual(wasm.add_u128(big("0xffffffffffffffff"), big(1)),
+                                   big("0x10000000000000000"));
+                assert.strictEqual(wasm.add_u128(big("0xffffffffffffffffffffffffffffffff"), big(2)),
+                                   big(1));
+                const n = big("0x0123456789abcdeffedcba9876543210");
+                assert.strictEqual(wasm.limb(n, 0), 0x76543210);
+                assert.strictEqual(wasm.limb(n, 1), 0xfedcba98);
+                assert.strictEqual(wasm.limb(n, 2), 0x89abcdef);
+                assert.strictEqual(wasm.limb(n, 3), 0x01234567);
+                assert.strictEqual(wasm.neg_i128(big(5)), big(-5));
+                assert.strictEqual(wasm.neg_i128(big("-170141183460469231731687303715884105727")),
+                                   big("170141183460469231731687303715884105727"));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn js_value_arrays() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn reverse(mut a: Vec<JsValue>) -> Vec<JsValue> {
+                a.reverse();
+                a
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn count_strings(a: Box<[JsValue]>) -> u32 {
+                a.iter().filter(|v| v.as_string().is_some()).count() as u32
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn make(n: u32) -> Box<[JsValue]> {
+                (0..n).map(|i| JsValue::from(&i.to_string())).collect::<Vec<_>>()
+                    .into_boxed_slice()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const obj = {};
+                assert.deepStrictEqual(wasm.reverse([1, "a", null, obj]), [obj, null, "a", 1]);
+                assert.strictEqual(wasm.reverse([1, obj])[0], obj);
+                assert.deepStrictEqual(wasm.reverse([]), []);
+                assert.strictEqual(wasm.count_strings(["a", 1, "b", undefined]), 2);
+                assert.deepStrictEqual(wasm.make(3), ["0", "1", "2"]);
+                assert.throws(() => wasm.count_strings("ab" as any));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn string_vectors() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn join(words: Vec<String>, sep: &str) -> String {
+                words.join(sep)
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn split(s: &str) -> Vec<String> {
+                s.split(',').map(|s| s.to_string()).collect()
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn shout(words: Vec<String>) -> Vec<String> {
+                words.into_iter().map(|s| s.to_uppercase() + "!").collect()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.join(["a", "b", "c"], "-"), "a-b-c");
+                assert.strictEqual(wasm.join([], "-"), "");
+                assert.strictEqual(wasm.join(["\u00e9", "", "\u{1f600}"], ","), "\u00e9,,\u{1f600}");
+                assert.deepStrictEqual(wasm.split("x,,y"), ["x", "", "y"]);
+                assert.deepStrictEqual(wasm.shout(["hi", "\u00e9"]), ["HI!", "\u00c9!"]);
+                assert.deepStrictEqual(wasm.shout([]), []);
+                assert.throws(() => wasm.shout("hi" as any));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn enums() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[repr(u16)]
+            #[derive(Copy, Clone, PartialEq, Debug)]
+            pub enum Status {
+                Ok = 200,
+                NotFound = 404,
+                Error = 500,
+                Unknown,
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn is_failure(status: Status) -> bool {
+                status != Status::Ok
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn worst() -> Status {
+                Status::Unknown
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.Status.Ok, 200);
+                assert.strictEqual(wasm.Status.Error, 500);
+                assert.strictEqual(wasm.is_failure(wasm.Status.Ok), false);
+                assert.strictEqual(wasm.is_failure(wasm.Status.NotFound), true);
+                assert.strictEqual(wasm.worst(), wasm.Status.Unknown);
+                assert.strictEqual(wasm.worst(), 501);
+                assert.throws(() => wasm.is_failure(3 as any), /invalid enum value/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn overloads() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen(js_name = "add")]
+            #[no_mangle]
+            pub extern fn add_u32(a: u32, b: u32) -> u32 {
+                a + b
+            }
+
+            #[wasm_bindgen(js_name = "add")]
+            #[no_mangle]
+            pub extern fn add_str(a: &str, b: &str) -> String {
+                format!("{}{}", a, b)
+            }
+
+            #[wasm_bindgen(js_name = "negate")]
+            #[no_mangle]
+            pub extern fn negate_i32(a: i32) -> i32 {
+                -a
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.add(1, 2), 3);
+                assert.strictEqual(wasm.add("a", "b"), "ab");
+                assert.throws(() => (wasm.add as any)(1, "b"), /no overload of `add`/);
+                assert.strictEqual(wasm.negate(3), -3);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn tagged_enums() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub enum Shape {
+                Circle { r: f64 },
+                Rect { w: f64, h: f64 },
+                Label { text: String, bold: bool },
+                Empty,
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn area(s: Shape) -> f64 {
+                match s {
+                    Shape::Circle { r } => 3.0 * r * r,
+                    Shape::Rect { w, h } => w * h,
+                    Shape::Label { .. } | Shape::Empty => 0.0,
+                }
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn grow(s: Shape) -> Shape {
+                match s {
+                    Shape::Circle { r } => Shape::Circle { r: r * 2.0 },
+                    Shape::Rect { w, h } => Shape::Rect { w: w * 2.0, h: h * 2.0 },
+                    Shape::Label { text, bold } => {
+                        Shape::Label { text: text.to_uppercase(), bold: !bold }
+                    }
+                    Shape::Empty => Shape::Empty,
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.area({ kind: "circle", r: 2 }), 12);
+                assert.strictEqual(wasm.area({ kind: "rect", w: 2, h: 3 }), 6);
+                assert.strictEqual(wasm.area({ kind: "empty" }), 0);
+
+                const s: wasm.Shape = wasm.grow({ kind: "rect", w: 1, h: 2 });
+                assert.deepStrictEqual(s, { kind: "rect", w: 2, h: 4 });
+                assert.deepStrictEqual(wasm.grow({ kind: "label", text: "hi", bold: false }),
+                                       { kind: "label", text: "HI", bold: true });
+                assert.deepStrictEqual(wasm.grow({ kind: "empty" }), { kind: "empty" });
+
+                assert.throws(() => wasm.area({ kind: "triangle" } as any), /invalid `kind`/);
+                assert.throws(() => wasm.area({ kind: "circle" } as any), /invalid `r` field/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn result_errors() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub enum ParseError {
+                Empty,
+                BadDigit { pos: u32, found: String },
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn parse(s: &str) -> Result<u32, ParseError> {
+                if s.is_empty() {
+                    return Err(ParseError::Empty)
+                }
+                let mut n = 0;
+                for (i, c) in s.chars().enumerate() {
+                    match c.to_digit(10) {
+                        Some(d) => n = n * 10 + d,
+                        None => {
+                            return Err(ParseError::BadDigit {
+                                pos: i as u32,
+                                found: c.to_string(),
+                            })
+                        }
+                    }
+                }
+                Ok(n)
+            }
+
+            #[no_mangle]
+            #[wasm_bindgen]
+            pub extern fn check(ok: bool) -> Result<(), JsValue> {
+                if ok {
+                    Ok(())
+                } else {
+                    Err(JsValue::from("not ok"))
+                }
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.parse("123"), 123);
+
+                try {
+                    wasm.parse("");
+                    throw new Error("should have thrown");
+                } catch (e) {
+                    assert.ok(e instanceof wasm.ParseErrorEmpty);
+                    assert.ok(e instanceof wasm.ParseError);
+                    assert.ok(e instanceof Error);
+                    assert.strictEqual(e.kind, "empty");
+                    assert.strictEqual(e.message, "ParseError::Empty");
+                }
+
+                try {
+                    wasm.parse("1x3");
+                    throw new Error("should have thrown");
+                } catch (e) {
+                    assert.ok(e instanceof wasm.ParseErrorBadDigit);
+                    assert.ok(!(e instanceof wasm.ParseErrorEmpty));
+                    assert.strictEqual(e.pos, 1);
+                    assert.strictEqual(e.found, "x");
+                }
+
+                // errors don't leave anything behind for the next call
+                assert.strictEqual(wasm.parse("4"), 4);
+
+                wasm.check(true);
+                assert.throws(() => wasm.check(false), (e: any) => e === "not ok");
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn cfg() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            #[cfg(all())]
+            pub extern fn enabled() -> u32 {
+                1
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            #[cfg(any())]
+            pub extern fn disabled() -> u32 {
+                2
+            }
+
+            #[wasm_bindgen]
+            #[cfg(any())]
+            pub struct Gone {
+                a: u32,
+            }
+
+            #[wasm_bindgen]
+            pub struct Foo {
+                a: u32,
+            }
+
+            #[wasm_bindgen]
+            impl Foo {
+                pub fn new() -> Foo {
+                    Foo { a: 3 }
+                }
+
+                #[cfg(all())]
+                pub fn get(&self) -> u32 {
+                    self.a
+                }
+
+                #[cfg(any())]
+                pub fn missing(&self) -> u32 {
+                    self.a
+                }
+            }
+
+            #[wasm_bindgen]
+            extern {
+                #[cfg(any())]
+                fn not_provided();
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.enabled(), 1);
+                assert.strictEqual((wasm as any).disabled, undefined);
+                assert.strictEqual((wasm as any).Gone, undefined);
+
+                const foo = wasm.Foo.new();
+                assert.strictEqual(foo.get(), 3);
+                assert.strictEqual((foo as any).missing, undefined);
+                foo.free();
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn tuples() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn divmod(a: u32, b: u32) -> (u32, u32) {
+                (a / b, a % b)
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn polar(x: f64, y: f64) -> (f64, f64) {
+                ((x * x + y * y).sqrt(), y.atan2(x))
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn mixed(a: i32) -> (i32, bool, char) {
+                (-a, a > 0, 'x')
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn small(a: i8, b: i16) -> (i8, i16, u8) {
+                (a, b, a as u8)
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const [q, r]: [number, number] = wasm.divmod(7, 2);
+                assert.strictEqual(q, 3);
+                assert.strictEqual(r, 1);
+
+                assert.deepStrictEqual(wasm.polar(0, 2), [2, Math.PI / 2]);
+                assert.deepStrictEqual(wasm.mixed(3), [-3, true, "x"]);
+                assert.deepStrictEqual(wasm.mixed(-1), [1, false, "x"]);
+                assert.deepStrictEqual(wasm.small(-3, -300), [-3, -300, 253]);
+                assert.deepStrictEqual(wasm.small(5, 300), [5, 300, 5]);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn maps() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use std::collections::{BTreeMap, HashMap};
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn lookup(map: HashMap<String, String>, key: &str) -> Option<String> {
+                map.get(key).cloned()
+            }
+
+            fn swapped(map: BTreeMap<String, String>) -> BTreeMap<String, String> {
+                map.into_iter().map(|(k, v)| (v, k)).collect()
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn swap(map: BTreeMap<String, String>) -> BTreeMap<String, String> {
+                swapped(map)
+            }
+
+            #[wasm_bindgen(plain_object)]
+            #[no_mangle]
+            pub extern fn swap_object(map: BTreeMap<String, String>) -> BTreeMap<String, String> {
+                swapped(map)
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn count(map: HashMap<String, JsValue>) -> HashMap<String, JsValue> {
+                let mut ret = HashMap::new();
+                ret.insert("len".to_string(), JsValue::from(map.len() as f64));
+                ret.insert("has_a".to_string(), JsValue::from(map.contains_key("a")));
+                ret
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                const m = new Map<string, string>();
+                m.set("a", "x");
+                m.set("b", "y");
+                assert.strictEqual(wasm.lookup(m, "a"), "x");
+                assert.strictEqual(wasm.lookup(m, "c"), undefined);
+                assert.strictEqual(wasm.lookup({ b: "y" }, "b"), "y");
+
+                const swapped = wasm.swap(m);
+                assert.ok(swapped instanceof Map);
+                assert.strictEqual(swapped.get("x"), "a");
+                assert.strictEqual(swapped.get("y"), "b");
+                assert.deepStrictEqual(wasm.swap_object({ a: "x" }), { x: "a" });
+
+                const counted = wasm.count({ a: [1, 2], b: null });
+                assert.strictEqual(counted.get("len"), 2);
+                assert.strictEqual(counted.get("has_a"), true);
+
+                const bad = new Map<any, string>();
+                bad.set(1, "x");
+                assert.throws(() => wasm.lookup(bad, "a"), /keys to be strings/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn dates() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn epoch() -> SystemTime {
+                UNIX_EPOCH
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn later(d: SystemTime, ms: u32) -> SystemTime {
+                d + Duration::from_millis(ms as u64)
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn is_before_epoch(d: SystemTime) -> bool {
+                d < UNIX_EPOCH
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.ok(wasm.epoch() instanceof Date);
+                assert.strictEqual(wasm.epoch().getTime(), 0);
+
+                const d = new Date(Date.UTC(2018, 3, 1, 12, 30));
+                assert.strictEqual(wasm.later(d, 1500).getTime(), d.getTime() + 1500);
+                assert.strictEqual(wasm.is_before_epoch(d), false);
+                assert.strictEqual(wasm.is_before_epoch(new Date(-1000)), true);
+
+                assert.throws(() => wasm.later(new Date(NaN), 0), /invalid Date/);
+                assert.throws(() => wasm.later(0 as any, 0), /expected a Date/);
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn clamped() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen::Clamped;
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn brightest(pixels: Clamped<&[u8]>) -> u8 {
+                pixels.iter().cloned().max().unwrap_or(0)
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn invert(mut pixels: Clamped<Vec<u8>>) -> Clamped<Vec<u8>> {
+                for p in pixels.iter_mut() {
+                    *p = 255 - *p;
+                }
+                pixels
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn black(len: u32) -> Clamped<Box<[u8]>> {
+                Clamped(vec![0; len as usize].into_boxed_slice())
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+
+            export function test() {
+                assert.strictEqual(wasm.brightest(new Uint8ClampedArray([1, 300, 7])), 255);
+
+                const inverted = wasm.invert(new Uint8ClampedArray([0, 100, 255]));
+                assert.ok(inverted instanceof Uint8ClampedArray);
+                assert.deepStrictEqual(Array.from(inverted), [255, 155, 0]);
+
+                const black = wasm.black(4);
+                assert.ok(black instanceof Uint8ClampedArray);
+                assert.deepStrictEqual(Array.from(black), [0, 0, 0, 0]);
+
+                assert.throws(() => wasm.brightest(new Uint8Array([1]) as any));
+            }
+        "#)
+        .test();
+}
+
+#[test]
+fn raw_pointer_views() {
+    test_support::project()
+        .file("src/lib.rs", r#"
+            #![feature(proc_macro)]
+
+            extern crate wasm_bindgen;
+
+            use wasm_bindgen::prelude::*;
+
+            static mut BUF: [u8; 16] = [0; 16];
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn buffer_ptr() -> *mut u8 {
+                unsafe { BUF.as_mut_ptr() }
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn buffer_len() -> usize {
+                unsafe { BUF.len() }
+            }
+
+            #[wasm_bindgen]
+            #[no_mangle]
+            pub extern fn sum_raw(ptr: *const u8, len: usize) -> u32 {
+                let data = unsafe { std::slice::from_raw_parts(ptr, len) };
+                data.iter().map(|b| *b as u32).sum()
+            }
+        "#)
+        .file("test.ts", r#"
+            import * as assert from "assert";
+            import * as wasm from "./out";
+            import { memory } from "./out_wasm";
+
+            export function test() {
+                const ptr = wasm.buffer_ptr();
+                const view = new Uint8Array(memory.buffer, ptr, wasm.buffer_len());
+                view.set([1, 2, 3]);
+                view[15] = 10;
+
+                assert.strictEqual(wasm.sum_raw(ptr, view.length), 16);
+                assert.strictEqual(wasm.sum_raw(ptr + 1, 2), 5);
+            }
+        "#)
+        .test();
+}